@@ -0,0 +1,45 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use sfxr::{Generator, Sample, WaveType};
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // Audio callback sized buffers, where per call overhead matters
+    let mut group = c.benchmark_group("callback");
+
+    for &frames in [64, 128, 256, 512].iter() {
+        group.throughput(Throughput::Elements(frames as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("generate", frames),
+            &frames,
+            |b, &frames| {
+                let mut buffer = vec![0.0; frames];
+
+                let mut sample = Sample::new();
+                sample.wave_type = WaveType::Square;
+                let mut generator = Generator::new(sample);
+
+                b.iter(|| {
+                    generator.generate(&mut buffer);
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("generate_exact", frames),
+            &frames,
+            |b, &frames| {
+                let mut sample = Sample::new();
+                sample.wave_type = WaveType::Square;
+                let mut generator = Generator::new(sample);
+
+                b.iter(|| {
+                    generator.generate_exact(frames);
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
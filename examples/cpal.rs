@@ -1,38 +1,36 @@
 use cpal::traits::{EventLoopTrait, HostTrait};
-use rand::rngs::SmallRng;
-use rand::RngCore;
-use rand::SeedableRng;
+use sfxr::device::{best_config, OutputAdapter};
+use sfxr::rt::{GeneratorSender, SharedGenerator};
+use std::thread;
 use std::time::Duration;
-use std::{
-    sync::{Arc, Mutex},
-    thread,
-};
 
 /// Manages the audio.
 pub struct Audio {
-    generator: Arc<Mutex<Option<sfxr::Generator>>>,
+    sender: GeneratorSender,
+    shared: Option<SharedGenerator>,
 }
 
 #[allow(clippy::new_without_default)]
 impl Audio {
     /// Instantiate a new audio object without a generator.
     pub fn new() -> Self {
+        let (sender, shared) = SharedGenerator::new();
         Self {
-            generator: Arc::new(Mutex::new(None)),
+            sender,
+            shared: Some(shared),
         }
     }
 
     /// Play a sample.
     pub fn play(&mut self, sample: sfxr::Sample) {
-        let mut generator = self.generator.lock().unwrap();
-        *generator = Some(sfxr::Generator::new(sample));
+        self.sender.play(sample);
     }
 
     /// Start a thread which will emit the audio.
     pub fn run(&mut self) {
-        let generator = self.generator.clone();
+        let mut shared = self.shared.take().expect("audio is already running");
 
-        thread::spawn(|| {
+        thread::spawn(move || {
             // Setup the audio system
             let host = cpal::default_host();
             let event_loop = host.event_loop();
@@ -41,12 +39,9 @@ impl Audio {
                 .default_output_device()
                 .expect("no output device available");
 
-            // This is the only format sfxr supports
-            let format = cpal::Format {
-                channels: 1,
-                sample_rate: cpal::SampleRate(44_100),
-                data_type: cpal::SampleFormat::F32,
-            };
+            // The closest format to the mono 44.1 kHz f32 output of sfxr the device supports
+            let format = best_config(&device).expect("no usable output format");
+            let mut adapter = OutputAdapter::new(&format);
 
             let stream_id = event_loop
                 .build_output_stream(&device, &format)
@@ -65,18 +60,8 @@ impl Audio {
                     }
                 };
 
-                match stream_data {
-                    cpal::StreamData::Output {
-                        buffer: cpal::UnknownTypeOutputBuffer::F32(mut buffer),
-                    } => match *generator.lock().unwrap() {
-                        Some(ref mut generator) => generator.generate(&mut buffer),
-                        None => {
-                            for elem in buffer.iter_mut() {
-                                *elem = 0.0;
-                            }
-                        }
-                    },
-                    _ => panic!("output type buffer can not be used"),
+                if let cpal::StreamData::Output { mut buffer } = stream_data {
+                    adapter.fill(shared.receive(), &mut buffer);
                 }
             });
         });
@@ -85,7 +70,7 @@ impl Audio {
 
 fn main() {
     let mut sample = sfxr::Sample::new();
-    sample.mutate(Some(SmallRng::from_entropy().next_u64()));
+    sample.mutate(None);
 
     let mut audio = Audio::new();
 
@@ -0,0 +1,104 @@
+//! Plays a Sample as a synthesizer voice from MIDI input
+//!
+//! Reads raw MIDI bytes from the file given as argument, such as the `/dev/snd/midiC1D0` device
+//! of a USB keyboard on Linux, or from standard input:
+//!
+//! ``` text
+//! cargo run --example midi --features device -- /dev/snd/midiC1D0
+//! ```
+//!
+//! Messages from a MIDI library such as `midir` can be passed to `NoteEvent::from_midi` the
+//! same way.
+
+use cpal::traits::{EventLoopTrait, HostTrait};
+use sfxr::device::{best_config, OutputAdapter};
+use sfxr::{Generator, NoteEvent, Sample, WaveType};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Start a thread which plays `sample` with the note events from `notes`.
+fn run_audio(sample: Sample, notes: Receiver<NoteEvent>) {
+    thread::spawn(move || {
+        // Silent until the first note
+        let mut generator: Option<Generator> = None;
+
+        let host = cpal::default_host();
+        let event_loop = host.event_loop();
+
+        let device = host
+            .default_output_device()
+            .expect("no output device available");
+        let format = best_config(&device).expect("no usable output format");
+        let mut adapter = OutputAdapter::new(&format);
+
+        let stream_id = event_loop
+            .build_output_stream(&device, &format)
+            .expect("could not build output stream");
+        event_loop
+            .play_stream(stream_id)
+            .expect("could not play stream");
+
+        event_loop.run(move |stream_id, stream_result| {
+            let stream_data = match stream_result {
+                Ok(data) => data,
+                Err(err) => {
+                    eprintln!("an error occurred on stream {:?}: {:?}", stream_id, err);
+                    return;
+                }
+            };
+
+            for event in notes.try_iter() {
+                generator
+                    .get_or_insert_with(|| Generator::new(sample.clone()))
+                    .handle_note(event);
+            }
+            if let cpal::StreamData::Output { mut buffer } = stream_data {
+                adapter.fill(generator.as_mut(), &mut buffer);
+            }
+        });
+    });
+}
+
+fn main() -> io::Result<()> {
+    let input: Box<dyn Read> = match std::env::args().nth(1) {
+        Some(path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin()),
+    };
+    let input = BufReader::new(input);
+
+    // A bleepy square wave voice
+    let mut sample = Sample::new();
+    sample.wave_type = WaveType::Square;
+    sample.env_attack = 0.05;
+    sample.env_sustain = 0.1;
+    sample.env_decay = 0.3;
+    sample.duty = 0.3;
+
+    let (sender, notes) = mpsc::channel();
+    run_audio(sample, notes);
+
+    // Splits the byte stream into three byte messages, keeping the status of the previous
+    // message for data bytes sent with running status
+    let mut message = Vec::with_capacity(3);
+    for byte in input.bytes() {
+        let byte = byte?;
+        if byte & 0x80 != 0 {
+            message.clear();
+            message.push(byte);
+        } else if !message.is_empty() {
+            message.push(byte);
+        }
+        if message.len() == 3 {
+            if let Some(event) = NoteEvent::from_midi(&message) {
+                println!("{:?}", event);
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+            message.truncate(1);
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,3 @@
-use rand::rngs::SmallRng;
-use rand::RngCore;
-use rand::SeedableRng;
 use sdl2::audio::{AudioCallback, AudioSpecDesired};
 use std::time::Duration;
 
@@ -26,7 +23,7 @@ fn main() {
     };
 
     let mut sample = sfxr::Sample::new();
-    sample.mutate(Some(SmallRng::from_entropy().next_u64()));
+    sample.mutate(None);
     let generator = sfxr::Generator::new(sample);
 
     let device = audio_subsystem
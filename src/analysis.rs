@@ -0,0 +1,179 @@
+//! Perceptual attributes of sound effects, for sorting and searching sound banks
+//!
+//! The attributes are measured on a render of the Sample rather than read from its
+//! parameters, so Samples reaching the same sound through different parameters are described
+//! alike.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{Generator, Sample, SAMPLE_RATE};
+
+/// Length of the analysis frames in samples
+const FRAME: usize = 2_048;
+/// Longest period searched for the fundamental, in samples, about 43 Hz
+const MAX_LAG: usize = FRAME / 2;
+/// Largest number of frames analyzed, spread evenly over the audible part of longer sounds
+const MAX_FRAMES: usize = 16;
+/// Frames quieter than this fraction of the loudest frame are ignored
+const QUIET: f32 = 0.1;
+/// Smallest periodicity, between `0.0` and `1.0`, of a frame with a fundamental
+const PITCHED: f32 = 0.6;
+
+/// Perceptual attributes of a sound effect, returned by `describe`
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SoundDescription {
+    /// Fundamental frequency in Hz at the loudest moment, or `None` if the sound is noise
+    /// there or pitched below about 43 Hz
+    pub fundamental_hz: Option<f32>,
+    /// Time until the sound, including the tail of its filters, has faded to silence
+    pub duration: Duration,
+    /// How much of the sound is in high frequencies, from `0.0` (dull) to `1.0` (all energy
+    /// at the Nyquist frequency)
+    pub brightness: f32,
+    /// How little the sound repeats itself, from `0.0` (a steady tone) to `1.0` (white noise)
+    pub noisiness: f32,
+}
+
+/// Renders `sample` and measures its perceptual attributes
+///
+/// A Sample without a `noise_seed` is rendered with seed `0`, so the description is the same
+/// on every call.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{analysis, Sample, WaveType};
+/// let mut tone = Sample::new();
+/// tone.wave_type = WaveType::Sine;
+/// tone.base_freq = Sample::freq_from_hz(440.0);
+/// let description = analysis::describe(&tone);
+/// let hz = description.fundamental_hz.unwrap();
+/// assert!((hz - 440.0).abs() < 5.0);
+///
+/// let mut noise = tone;
+/// noise.wave_type = WaveType::Noise;
+/// assert!(analysis::describe(&noise).noisiness > description.noisiness);
+/// ```
+pub fn describe(sample: &Sample) -> SoundDescription {
+    let mut sample = sample.clone();
+    sample.noise_seed.get_or_insert(0);
+    let buffer = Generator::new(sample).render_to_vec();
+
+    let amplitudes: Vec<f32> = buffer
+        .chunks(FRAME)
+        .map(|frame| rms(frame.iter().copied()))
+        .collect();
+    let loudest = amplitudes.iter().copied().fold(0.0, f32::max);
+    let audible: Vec<usize> = (0..amplitudes.len())
+        .filter(|&i| loudest > 0.0 && amplitudes[i] >= QUIET * loudest)
+        .collect();
+    let step = audible.len().div_ceil(MAX_FRAMES).max(1);
+
+    let mut weight = 0.0;
+    let mut brightness = 0.0;
+    let mut periodicity = 0.0;
+    for &i in audible.iter().step_by(step) {
+        let frame = &buffer[i * FRAME..((i + 1) * FRAME).min(buffer.len())];
+        let amplitude = amplitudes[i];
+        weight += amplitude;
+        brightness += amplitude * frame_brightness(frame, amplitude);
+        periodicity += amplitude * pitch(frame).map_or(0.0, |(_, clarity)| clarity);
+    }
+
+    // The loudest frame is always analyzed for the fundamental, even if skipped above
+    let fundamental_hz = amplitudes
+        .iter()
+        .position(|&amplitude| amplitude == loudest && loudest > 0.0)
+        .and_then(|i| pitch(&buffer[i * FRAME..((i + 1) * FRAME).min(buffer.len())]))
+        .filter(|&(_, clarity)| clarity >= PITCHED)
+        .map(|(period, _)| SAMPLE_RATE as f32 / period);
+
+    let (brightness, noisiness) = if weight > 0.0 {
+        (brightness / weight, 1.0 - periodicity / weight)
+    } else {
+        (0.0, 0.0)
+    };
+    SoundDescription {
+        fundamental_hz,
+        duration: Duration::from_secs_f64(buffer.len() as f64 / f64::from(SAMPLE_RATE)),
+        brightness: brightness.clamp(0.0, 1.0),
+        noisiness: noisiness.clamp(0.0, 1.0),
+    }
+}
+
+/// Root mean square of `values`, `0.0` if empty
+pub(crate) fn rms(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0), |(s, c), v| (s + v * v, c + 1));
+    if count > 0 {
+        (sum / count as f32).sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Brightness of `frame` with root mean square `amplitude`, from its slope
+pub(crate) fn frame_brightness(frame: &[f32], amplitude: f32) -> f32 {
+    let slope = rms(frame.windows(2).map(|pair| pair[1] - pair[0]));
+    // The slope of a signal at the Nyquist frequency is twice its amplitude
+    if amplitude > 0.0 {
+        (slope / (2.0 * amplitude)).min(1.0)
+    } else {
+        0.0
+    }
+}
+
+/// Period in samples and periodicity between `0.0` and `1.0` of `frame`, using the normalized
+/// square difference function of McLeod and Wyvill, or `None` if it doesn't repeat
+fn pitch(frame: &[f32]) -> Option<(f32, f32)> {
+    let max_lag = MAX_LAG.min(frame.len() / 2);
+    let nsdf: Vec<f32> = (0..max_lag)
+        .map(|lag| {
+            let (correlation, energy) = frame
+                .iter()
+                .zip(frame[lag..].iter())
+                .fold((0.0, 0.0), |(c, e), (a, b)| (c + a * b, e + a * a + b * b));
+            if energy > 0.0 {
+                2.0 * correlation / energy
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    // Peaks between positive going and negative going zero crossings, skipping the one at
+    // lag zero
+    let mut peaks = Vec::new();
+    let mut lag = nsdf.iter().position(|&v| v < 0.0)?;
+    while let Some(start) = nsdf[lag..].iter().position(|&v| v > 0.0) {
+        let start = lag + start;
+        let end = nsdf[start..]
+            .iter()
+            .position(|&v| v <= 0.0)
+            .map_or(nsdf.len(), |len| start + len);
+        peaks.extend((start..end).max_by(|&a, &b| nsdf[a].total_cmp(&nsdf[b])));
+        if end == nsdf.len() {
+            break;
+        }
+        lag = end;
+    }
+
+    let clarity = peaks.iter().map(|&peak| nsdf[peak]).fold(0.0, f32::max);
+    // The first peak close to the highest is the fundamental, later ones are its multiples
+    let peak = *peaks.iter().find(|&&peak| nsdf[peak] >= 0.9 * clarity)?;
+    let period = if peak > 0 && peak + 1 < nsdf.len() {
+        // Parabolic interpolation between lags
+        let (left, center, right) = (nsdf[peak - 1], nsdf[peak], nsdf[peak + 1]);
+        let curvature = left - 2.0 * center + right;
+        if curvature < 0.0 {
+            peak as f32 + 0.5 * (left - right) / curvature
+        } else {
+            peak as f32
+        }
+    } else {
+        peak as f32
+    };
+    Some((period, clarity.min(1.0)))
+}
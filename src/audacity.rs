@@ -0,0 +1,109 @@
+//! Export of rendered sound effects for inspection in Audacity
+//!
+//! `Generator::render_with_labels` renders a sound along with a label for every generator event.
+//! Write the samples with `LabeledRender::write_raw` and import them with "File > Import > Raw
+//! Data" as mono 32-bit float little-endian at the generator sample rate, then import the labels
+//! written with `LabeledRender::write_labels` using "File > Import > Labels".
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::{Generator, Sample};
+//! let mut generator = Generator::new(Sample::new());
+//! let render = generator.render_with_labels();
+//! let mut labels = Vec::new();
+//! render.write_labels(&mut labels).unwrap();
+//! assert!(String::from_utf8(labels).unwrap().contains("decay"));
+//! ```
+
+use crate::{EnvelopeStage, Generator, GeneratorEvent};
+use std::io::{self, Write};
+use std::sync::mpsc;
+
+/// A point label at a moment of a rendered sound
+#[derive(PartialEq, Clone, Debug)]
+pub struct Label {
+    /// Position of the label in seconds from the start of the sound
+    pub seconds: f32,
+    /// Label text
+    pub text: String,
+}
+
+/// A rendered sound with labels marking its events
+#[derive(PartialEq, Clone, Debug)]
+pub struct LabeledRender {
+    /// Rendered samples, as returned by `Generator::render_to_vec`
+    pub samples: Vec<f32>,
+    /// Sample rate of `samples` in Hz
+    pub sample_rate: u32,
+    /// Labels in order of time
+    pub labels: Vec<Label>,
+}
+
+impl LabeledRender {
+    /// Writes the samples as raw 32-bit float little-endian PCM
+    pub fn write_raw<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for v in self.samples.iter() {
+            writer.write_all(&v.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes the labels in the Audacity label track text format
+    pub fn write_labels<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for label in self.labels.iter() {
+            writeln!(
+                writer,
+                "{:.6}\t{:.6}\t{}",
+                label.seconds, label.seconds, label.text
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Generator {
+    /// Renders the whole sound effect like `render_to_vec`, labeling envelope stage changes,
+    /// repeats and arpeggio steps. The event handler registered with `on_event` is not called
+    /// during rendering, but is kept.
+    pub fn render_with_labels(&mut self) -> LabeledRender {
+        let (sender, receiver) = mpsc::channel();
+        let handler = self.event_handler.take();
+        self.on_event(move |event| {
+            let _ = sender.send(event);
+        });
+
+        let sample_rate = self.config().sample_rate;
+        let mut labels = Vec::new();
+        let samples = self.render_chunks(1.0e-3, 1_024, |position| {
+            for event in receiver.try_iter() {
+                let (offset, text) = match event {
+                    GeneratorEvent::EnvelopeStageChanged { offset, stage } => {
+                        let text = match stage {
+                            EnvelopeStage::Attack => "attack",
+                            EnvelopeStage::Sustain => "sustain",
+                            EnvelopeStage::Decay => "decay",
+                            EnvelopeStage::End => "end",
+                        };
+                        (offset, text)
+                    }
+                    GeneratorEvent::Repeat { offset } => (offset, "repeat"),
+                    GeneratorEvent::ArpStep { offset } => (offset, "arpeggio"),
+                    // Always coincides with the end stage
+                    GeneratorEvent::Finished { .. } => continue,
+                };
+                labels.push(Label {
+                    seconds: (position + offset) as f32 / sample_rate as f32,
+                    text: text.to_string(),
+                });
+            }
+        });
+
+        self.event_handler = handler;
+        LabeledRender {
+            samples,
+            sample_rate,
+            labels,
+        }
+    }
+}
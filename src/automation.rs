@@ -0,0 +1,213 @@
+//! Keyframes moving Sample fields over the duration of a sound
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{Easing, Generator, Param, RangeError, Sample};
+
+/// A value a Sample field reaches at a point of the sound
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Keyframe {
+    /// Position in the sound, from `0.0` at the start to `1.0` where the envelope ends
+    pub time: f32,
+    /// Value of the field at `time`, within the range of its Param
+    pub value: f64,
+    /// Shape of the transition from the previous keyframe, or from the Sample's own value at
+    /// the start of the sound for the first keyframe
+    pub easing: Easing,
+}
+
+/// Keyframes of one Sample field, sorted by time
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Track {
+    param: Param,
+    keyframes: Vec<Keyframe>,
+}
+
+/// Keyframes of Sample fields, applied during playback with `Generator::set_automation`
+///
+/// Each automated field starts at the Sample's value, moves through its keyframes and keeps
+/// the value of the last one. Unlike the fixed rate ramps such as `lpf_ramp`, a change can be
+/// placed anywhere in the sound.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Automation, Easing, Generator, Keyframe, Param, Sample};
+/// let mut sample = Sample::new();
+/// sample.lpf_freq = 0.2;
+/// // Open the filter only in the last 20 % of the sound
+/// let mut automation = Automation::new();
+/// automation
+///     .add(Param::LpfFreq, Keyframe { time: 0.8, value: 0.2, easing: Easing::Linear })
+///     .unwrap();
+/// automation
+///     .add(Param::LpfFreq, Keyframe { time: 1.0, value: 1.0, easing: Easing::EaseIn })
+///     .unwrap();
+/// assert_eq!(automation.value_at(Param::LpfFreq, 0.2, 0.5), Some(0.2));
+///
+/// let mut generator = Generator::new(sample);
+/// generator.set_automation(automation);
+/// let rendered = generator.render_to_vec();
+/// ```
+#[derive(PartialEq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Automation {
+    tracks: Vec<Track>,
+}
+
+impl Automation {
+    /// Constructs an Automation without keyframes
+    pub fn new() -> Automation {
+        Automation { tracks: Vec::new() }
+    }
+    /// Adds `keyframe` to the field named by `param`, replacing any keyframe at the same time.
+    /// Fails if the time is outside `0.0..=1.0` or the value outside `param.range()`.
+    pub fn add(&mut self, param: Param, keyframe: Keyframe) -> Result<(), RangeError> {
+        if !(0.0..=1.0).contains(&keyframe.time) {
+            return Err(RangeError {
+                param: None,
+                value: f64::from(keyframe.time),
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        let (min, max) = param.range();
+        if !(min..=max).contains(&keyframe.value) {
+            return Err(RangeError {
+                param: Some(param),
+                value: keyframe.value,
+                min,
+                max,
+            });
+        }
+
+        let index = match self.tracks.iter().position(|track| track.param == param) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(Track {
+                    param,
+                    keyframes: Vec::new(),
+                });
+                self.tracks.len() - 1
+            }
+        };
+        let keyframes = &mut self.tracks[index].keyframes;
+        match keyframes.iter().position(|k| k.time >= keyframe.time) {
+            Some(i) if keyframes[i].time == keyframe.time => keyframes[i] = keyframe,
+            Some(i) => keyframes.insert(i, keyframe),
+            None => keyframes.push(keyframe),
+        }
+        Ok(())
+    }
+    /// Removes the keyframes of the field named by `param`
+    pub fn clear(&mut self, param: Param) {
+        self.tracks.retain(|track| track.param != param);
+    }
+    /// Returns the keyframes of the field named by `param`, sorted by time
+    pub fn keyframes(&self, param: Param) -> &[Keyframe] {
+        self.tracks
+            .iter()
+            .find(|track| track.param == param)
+            .map_or(&[], |track| &track.keyframes)
+    }
+    /// Returns the Params with keyframes
+    pub fn params(&self) -> impl Iterator<Item = Param> + '_ {
+        self.tracks.iter().map(|track| track.param)
+    }
+    /// Returns the value of the field named by `param` at `time`, starting from `initial` at
+    /// time `0.0`, or `None` if it has no keyframes
+    pub fn value_at(&self, param: Param, initial: f64, time: f32) -> Option<f64> {
+        let keyframes = self.keyframes(param);
+        let next = match keyframes.iter().position(|k| k.time > time) {
+            Some(next) => next,
+            None => return keyframes.last().map(|k| k.value),
+        };
+        let (from_time, from_value) = match next {
+            0 => (0.0, initial),
+            _ => (keyframes[next - 1].time, keyframes[next - 1].value),
+        };
+        let to = keyframes[next];
+        let t = (time - from_time) / (to.time - from_time);
+        let t = f64::from(to.easing.apply(t.clamp(0.0, 1.0)));
+        Some(from_value + (to.value - from_value) * t)
+    }
+}
+
+/// An Automation playing on a Generator, with the values its fields had before automation
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Automated {
+    automation: Automation,
+    base: Sample,
+    /// Length of the envelope of `base` in samples, which keyframe times are relative to
+    length: usize,
+}
+
+impl Generator {
+    /// Plays the keyframes of `automation`, timed from the start of the sound, replacing any
+    /// previous Automation
+    ///
+    /// The low and high pass cutoffs, resonance, duty cycle, vibrato, phaser offset and base
+    /// frequency follow their keyframes sample by sample, taking over from their ramps and
+    /// slides. `volume` and `gain_db` change from the next `generate` call, and other fields,
+    /// such as the envelope, at the next repeat. `reset` restores the automated fields to
+    /// their values before automation and plays the keyframes again.
+    pub fn set_automation(&mut self, automation: Automation) {
+        self.clear_automation();
+        let length = self.sample.envelope_length().max(1);
+        self.automation = Some(Box::new(Automated {
+            automation,
+            base: self.sample.clone(),
+            length,
+        }));
+    }
+    /// Stops automation, restoring the automated fields to their values before it
+    pub fn clear_automation(&mut self) {
+        if let Some(automated) = self.automation.take() {
+            self.restore_automated(&automated);
+        }
+    }
+    /// Restores the fields automated by `automated` to their values before automation
+    pub(crate) fn restore_automated(&mut self, automated: &Automated) {
+        for param in automated.automation.params() {
+            self.sample.set_param(param, automated.base.param(param));
+        }
+    }
+    /// Sets the automated fields to their values at the current position and applies them
+    pub(crate) fn advance_automation(&mut self) {
+        let automated = match self.automation.take() {
+            Some(automated) => automated,
+            None => return,
+        };
+        let time = (self.elapsed as f64 / automated.length as f64).min(1.0) as f32;
+        for param in automated.automation.params() {
+            let initial = automated.base.param(param);
+            if let Some(value) = automated.automation.value_at(param, initial, time) {
+                self.sample.set_param(param, value);
+                self.apply_live(param);
+            }
+        }
+        self.automation = Some(automated);
+    }
+    /// Applies the Sample field named by `param` to the playing sound, if it has an immediate
+    /// effect
+    fn apply_live(&mut self, param: Param) {
+        match param {
+            Param::LpfFreq | Param::LpfResonance => self.hlpf.set_low_pass(
+                self.sample.lpf_resonance,
+                self.sample.lpf_freq * self.lpf_scale(),
+            ),
+            Param::HpfFreq => self.hlpf.set_high_pass(self.sample.hpf_freq),
+            Param::Duty => self.oscillator.set_duty(self.sample.duty),
+            Param::VibSpeed | Param::VibStrength => self
+                .oscillator
+                .set_vibrato(self.sample.vib_speed, self.sample.vib_strength),
+            Param::PhaOffset => self.phaser.set_offset(self.sample.pha_offset),
+            Param::BaseFreq => self.oscillator.set_base_freq(self.sample.base_freq),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,328 @@
+//! Pre-rendering of sound effects at asset build time
+//!
+//! Renders sounds and their variations to sample data and WAV files, for example from a
+//! `build.rs` script, so the game only needs to play back the files. `read_wav` goes the other
+//! way, loading short recordings to use as `Sample::custom_noise`.
+//!
+//! `build` goes one step further and embeds the sounds in the program. It reads a manifest with
+//! one `name = category seed` line per sound, using the categories of `PresetDef::builtin`:
+//!
+//! ``` text
+//! # Sounds of the game
+//! coin = pickup 3
+//! boom = explosion 7
+//! ```
+//!
+//! and writes a Rust module with a `SOUND_COIN` and `SOUND_BOOM` WAV file static for it, to be
+//! included with `include!(concat!(env!("OUT_DIR"), "/sounds.rs"))`. The game then ships no
+//! audio files and the sounds are versioned as a few bytes of text. In `build.rs`:
+//!
+//! ``` rust no_run
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("sounds.rs");
+//! sfxr::bake::build("sounds.txt", out, 44_100).unwrap();
+//! ```
+//!
+//! # Examples
+//!
+//! Five slightly different footsteps
+//!
+//! ``` rust
+//! use sfxr::{bake, Sample};
+//! let step = Sample::hit(Some(3));
+//! let footsteps = bake::variations(&step, 5, 0.5, 44_100);
+//! assert_eq!(footsteps.len(), 5);
+//!
+//! let mut wav = Vec::new();
+//! bake::write_wav(&mut wav, &footsteps[0], 44_100).unwrap();
+//! ```
+
+use crate::Error;
+#[cfg(feature = "rand")]
+use crate::{Generator, GeneratorConfig, GeneratorOptions, Param, PresetDef, Prng};
+#[cfg(feature = "rand")]
+use crate::{Sample, VariedSample};
+#[cfg(feature = "rand")]
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Renders `n` variations of `sample` at `rate` Hz. `jitter` between `0.0` and `1.0` scales how
+/// much the variations differ, up to a semitone of pitch, 20% of gain and 0.05 of the envelope,
+/// duty and filter parameters at `1.0`.
+///
+/// The variations are drawn from a fixed seed, so the same arguments always produce the same
+/// sounds and rebuilding assets doesn't change them.
+#[cfg(feature = "rand")]
+pub fn variations(sample: &Sample, n: usize, jitter: f32, rate: u32) -> Vec<Vec<f32>> {
+    let mut varied = VariedSample::new(sample.clone());
+    varied.pitch_jitter_semitones = jitter;
+    varied.gain_jitter = 0.2 * jitter;
+    for &param in [
+        Param::EnvSustain,
+        Param::EnvDecay,
+        Param::EnvPunch,
+        Param::Duty,
+        Param::LpfFreq,
+        Param::HpfFreq,
+    ]
+    .iter()
+    {
+        varied.jitter(param, 0.05 * f64::from(jitter));
+    }
+    variations_of(&varied, n, rate, 0)
+}
+
+/// Renders `n` variations of `varied` at `rate` Hz, drawn from `seed`
+#[cfg(feature = "rand")]
+pub fn variations_of(varied: &VariedSample, n: usize, rate: u32, seed: u64) -> Vec<Vec<f32>> {
+    let mut config = GeneratorConfig::new();
+    config.sample_rate = rate;
+    let rng = &mut Prng::new(seed);
+    (0..n)
+        .map(|_| varied.spawn_with_config(rng, config).render_to_vec())
+        .collect()
+}
+
+/// Writes `samples` as a mono 16-bit PCM WAV file at `rate` Hz. Samples are clamped to
+/// `-1.0..=1.0`.
+pub fn write_wav<W: Write>(mut writer: W, samples: &[f32], rate: u32) -> io::Result<()> {
+    let data_len = samples.len() as u32 * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_len).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // Mono
+    writer.write_all(&rate.to_le_bytes())?;
+    writer.write_all(&(rate * 2).to_le_bytes())?; // Bytes per second
+    writer.write_all(&2u16.to_le_bytes())?; // Bytes per frame
+    writer.write_all(&16u16.to_le_bytes())?; // Bits per sample
+    writer.write_all(b"data")?;
+    writer.write_all(&data_len.to_le_bytes())?;
+    for &v in samples.iter() {
+        let v = (v.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Reads a WAV file, returning its samples mixed down to mono and its sample rate. Supports
+/// 8, 16, 24 and 32-bit integer PCM and 32-bit float data.
+pub fn read_wav<R: Read>(mut reader: R) -> Result<(Vec<f32>, u32), Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(wav_error("not a RIFF WAVE file"));
+    }
+
+    // (format, channels, rate, bits per sample)
+    let mut format = None;
+    let mut chunks = &bytes[12..];
+    while chunks.len() >= 8 {
+        let id = &chunks[0..4];
+        let len = u32::from_le_bytes([chunks[4], chunks[5], chunks[6], chunks[7]]) as usize;
+        let body = &chunks[8..chunks.len().min(8 + len)];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                format = Some((u16_at(0), u16_at(2), rate, u16_at(14)));
+            }
+            b"data" => {
+                let (tag, channels, rate, bits) =
+                    format.ok_or_else(|| wav_error("data before format"))?;
+                let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+                    (1, 8) => |b| (f32::from(b[0]) - 128.0) / 128.0,
+                    (1, 16) => |b| f32::from(i16::from_le_bytes([b[0], b[1]])) / 32_768.0,
+                    (1, 24) => {
+                        |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2_147_483_648.0
+                    }
+                    (1, 32) => {
+                        |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2_147_483_648.0
+                    }
+                    (3, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                    _ => return Err(wav_error("unsupported sample format")),
+                };
+                if channels == 0 {
+                    return Err(wav_error("no channels"));
+                }
+                let width = usize::from(bits / 8);
+                let samples = body
+                    .chunks_exact(width * usize::from(channels))
+                    .map(|frame| {
+                        frame.chunks_exact(width).map(decode).sum::<f32>() / f32::from(channels)
+                    })
+                    .collect();
+                return Ok((samples, rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        chunks = &chunks[chunks.len().min(8 + len + len % 2)..];
+    }
+    Err(wav_error("no data chunk"))
+}
+
+/// Reads the WAV file at `path`, see `read_wav`
+pub fn load_wav<P: AsRef<Path>>(path: P) -> Result<(Vec<f32>, u32), Error> {
+    read_wav(BufReader::new(File::open(path)?))
+}
+
+fn wav_error(message: &str) -> Error {
+    Error::Parse {
+        format: "WAV",
+        message: message.to_string(),
+    }
+}
+
+/// Writes `samples` to a WAV file at `path`, see `write_wav`
+pub fn save_wav<P: AsRef<Path>>(path: P, samples: &[f32], rate: u32) -> io::Result<()> {
+    write_wav(BufWriter::new(File::create(path)?), samples, rate)
+}
+
+/// Writes each of `sounds` to a WAV file named `{stem}_{index}.wav` in `dir`, with indices
+/// starting at `1`. Returns the paths of the written files.
+pub fn save_all<P: AsRef<Path>>(
+    dir: P,
+    stem: &str,
+    sounds: &[Vec<f32>],
+    rate: u32,
+) -> io::Result<Vec<PathBuf>> {
+    sounds
+        .iter()
+        .enumerate()
+        .map(|(index, samples)| {
+            let path = dir.as_ref().join(format!("{}_{}.wav", stem, index + 1));
+            save_wav(&path, samples, rate)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// A sound listed in a `build` manifest
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ManifestEntry {
+    /// Sound name, a Rust identifier
+    pub name: String,
+    /// Name of the built-in category, see `PresetDef::builtin`
+    pub category: String,
+    /// Seed the Sample is generated from
+    pub seed: u64,
+}
+
+#[cfg(feature = "rand")]
+impl ManifestEntry {
+    /// Generates the Sample of this sound, the same as the category constructor such as
+    /// `Sample::pickup(Some(seed))`
+    pub fn sample(&self) -> Result<Sample, Error> {
+        let def = PresetDef::builtin(&self.category).ok_or_else(|| Error::Parse {
+            format: "sfxr manifest",
+            message: format!("unknown category `{}`", self.category),
+        })?;
+        Ok(def.generate(&mut Prng::new(self.seed)))
+    }
+}
+
+/// Parses a manifest of `name = category seed` lines. Empty lines and lines starting with `#`
+/// are ignored. Names must differ in more than case, as each becomes a `SOUND_<NAME>` constant.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::bake::parse_manifest;
+/// let entries = parse_manifest("# Pickups\ncoin = pickup 3\njump = jump 12\n").unwrap();
+/// assert_eq!(entries[1].seed, 12);
+/// assert!(parse_manifest("coin = pickup 3\nCoin = pickup 4").is_err());
+/// ```
+pub fn parse_manifest(text: &str) -> Result<Vec<ManifestEntry>, Error> {
+    let mut entries: Vec<ManifestEntry> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: &str| Error::Parse {
+            format: "sfxr manifest",
+            message: format!("line {}: {}", number + 1, message),
+        };
+
+        let (name, definition) = match line.find('=') {
+            Some(index) => (line[..index].trim(), &line[index + 1..]),
+            None => return Err(error("expected `name = category seed`")),
+        };
+        let mut words = definition.split_whitespace();
+        let (category, seed) = match (words.next(), words.next(), words.next()) {
+            (Some(category), Some(seed), None) => (category, seed),
+            _ => return Err(error("expected `name = category seed`")),
+        };
+
+        let identifier = name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && name.chars().next().is_some_and(|c| !c.is_ascii_digit());
+        if !identifier {
+            return Err(error("name must be a Rust identifier"));
+        }
+        let constant = name.to_uppercase();
+        if let Some(entry) = entries
+            .iter()
+            .find(|entry| entry.name.to_uppercase() == constant)
+        {
+            return Err(error(&format!(
+                "duplicate name, `{}` is also SOUND_{}",
+                entry.name, constant
+            )));
+        }
+        let seed = seed.parse().map_err(|_| error("seed must be an integer"))?;
+
+        entries.push(ManifestEntry {
+            name: name.to_string(),
+            category: category.to_string(),
+            seed,
+        });
+    }
+    Ok(entries)
+}
+
+/// Renders the sounds of `manifest` at `rate` Hz and returns the source of a Rust module with a
+/// `pub static SOUND_<NAME>: &[u8]` WAV file for each
+#[cfg(feature = "rand")]
+pub fn embed_module(manifest: &str, rate: u32) -> Result<String, Error> {
+    let mut config = GeneratorConfig::new();
+    config.sample_rate = rate;
+
+    let mut source = String::from("// Generated by sfxr::bake, do not edit\n");
+    for entry in parse_manifest(manifest)? {
+        let samples = Generator::new_with_config(entry.sample()?, GeneratorOptions::new(), config)
+            .render_to_vec();
+        let mut wav = Vec::new();
+        write_wav(&mut wav, &samples, rate)?;
+
+        let _ = writeln!(
+            source,
+            "\n/// `{} = {} {}`\npub static SOUND_{}: &[u8] = &[",
+            entry.name,
+            entry.category,
+            entry.seed,
+            entry.name.to_uppercase()
+        );
+        for line in wav.chunks(16) {
+            let bytes: Vec<String> = line.iter().map(u8::to_string).collect();
+            let _ = writeln!(source, "    {},", bytes.join(", "));
+        }
+        source.push_str("];\n");
+    }
+    Ok(source)
+}
+
+/// Renders the sounds of the manifest file at `manifest` at `rate` Hz into a Rust module at
+/// `out`, see `embed_module`. Meant to be called from `build.rs`, and tells Cargo to rerun the
+/// build script when the manifest changes.
+#[cfg(feature = "rand")]
+pub fn build<P: AsRef<Path>, Q: AsRef<Path>>(manifest: P, out: Q, rate: u32) -> Result<(), Error> {
+    let manifest = manifest.as_ref();
+    println!("cargo:rerun-if-changed={}", manifest.display());
+    let source = embed_module(&std::fs::read_to_string(manifest)?, rate)?;
+    std::fs::write(out, source)?;
+    Ok(())
+}
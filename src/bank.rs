@@ -0,0 +1,184 @@
+//! Named collections of sound effects
+
+#[cfg(feature = "json")]
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Sample;
+#[cfg(feature = "json")]
+use crate::{Error, TypedSample};
+
+/// Information about a sound effect of a SoundBank, for keeping large sound libraries
+/// organized
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct SampleMeta {
+    /// Name the sound is looked up by in a SoundBank
+    pub name: String,
+    /// Keywords for searching, like `"ui"` or `"weapon"`
+    pub tags: Vec<String>,
+    /// Who made the sound
+    pub author: String,
+    /// Free form notes, like where the sound is used
+    pub comment: String,
+}
+
+impl SampleMeta {
+    /// Constructs a SampleMeta with `name` and other fields empty
+    pub fn new(name: &str) -> SampleMeta {
+        SampleMeta {
+            name: name.to_string(),
+            ..SampleMeta::default()
+        }
+    }
+}
+
+/// A Sample of a SoundBank with its metadata, as serialized in the JSON format
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Entry {
+    meta: SampleMeta,
+    sample: Sample,
+}
+
+/// Samples of a game or a mod, looked up by name
+///
+/// Names are unique, and the Samples keep the order in which they were first inserted. Each
+/// Sample carries a SampleMeta, which is kept when the Sample is replaced.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Sample, SampleMeta, SoundBank};
+/// let mut bank = SoundBank::new();
+/// bank.insert("coin", Sample::pickup(Some(1)));
+/// bank.insert_with_meta(
+///     SampleMeta {
+///         tags: vec!["player".to_string()],
+///         ..SampleMeta::new("jump")
+///     },
+///     Sample::jump(Some(2)),
+/// );
+/// assert_eq!(bank.names().collect::<Vec<_>>(), ["coin", "jump"]);
+/// assert_eq!(bank.meta("jump").unwrap().tags, ["player"]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SoundBank {
+    sounds: Vec<Entry>,
+}
+
+impl SoundBank {
+    /// Constructs an empty SoundBank
+    pub fn new() -> SoundBank {
+        SoundBank { sounds: Vec::new() }
+    }
+    /// Adds `sample` as `name`. Returns the Sample it replaced if the name was taken, keeping
+    /// the position and metadata of the name.
+    pub fn insert(&mut self, name: &str, sample: Sample) -> Option<Sample> {
+        match self.get_mut(name) {
+            Some(old) => Some(std::mem::replace(old, sample)),
+            None => {
+                self.sounds.push(Entry {
+                    meta: SampleMeta::new(name),
+                    sample,
+                });
+                None
+            }
+        }
+    }
+    /// Adds `sample` as `meta.name` with `meta`. Returns the Sample it replaced if the name was
+    /// taken, keeping the position of the name.
+    pub fn insert_with_meta(&mut self, meta: SampleMeta, sample: Sample) -> Option<Sample> {
+        match self.position(&meta.name) {
+            Some(i) => {
+                let old = std::mem::replace(&mut self.sounds[i], Entry { meta, sample });
+                Some(old.sample)
+            }
+            None => {
+                self.sounds.push(Entry { meta, sample });
+                None
+            }
+        }
+    }
+    /// Returns the Sample named `name`
+    pub fn get(&self, name: &str) -> Option<&Sample> {
+        self.position(name).map(|i| &self.sounds[i].sample)
+    }
+    /// Returns the Sample named `name` for changing
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Sample> {
+        self.position(name).map(move |i| &mut self.sounds[i].sample)
+    }
+    /// Returns the metadata of the Sample named `name`
+    pub fn meta(&self, name: &str) -> Option<&SampleMeta> {
+        self.position(name).map(|i| &self.sounds[i].meta)
+    }
+    /// Removes and returns the Sample named `name`
+    pub fn remove(&mut self, name: &str) -> Option<Sample> {
+        self.position(name).map(|i| self.sounds.remove(i).sample)
+    }
+    /// Returns the names in order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sounds.iter().map(|entry| entry.meta.name.as_str())
+    }
+    /// Returns the names and Samples in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Sample)> {
+        self.sounds
+            .iter()
+            .map(|entry| (entry.meta.name.as_str(), &entry.sample))
+    }
+    /// Returns the metadata and Samples in order
+    pub fn iter_meta(&self) -> impl Iterator<Item = (&SampleMeta, &Sample)> {
+        self.sounds.iter().map(|entry| (&entry.meta, &entry.sample))
+    }
+    /// Returns the number of Samples
+    pub fn len(&self) -> usize {
+        self.sounds.len()
+    }
+    /// Returns `true` if the bank has no Samples
+    pub fn is_empty(&self) -> bool {
+        self.sounds.is_empty()
+    }
+
+    /// Returns the bank as JSON, an array of objects with the `meta` and `sample` of each
+    /// Sample in order
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Sample, SoundBank};
+    /// let mut bank = SoundBank::new();
+    /// bank.insert("blip", Sample::new());
+    /// let loaded = SoundBank::from_json(&bank.to_json()).unwrap();
+    /// assert_eq!(loaded.meta("blip").unwrap().name, "blip");
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.sounds).expect("a SoundBank serializes to JSON")
+    }
+    /// Parses a bank from JSON, see `to_json`. Fails if a Sample has values outside their
+    /// valid ranges. Of Samples with the same name, the last one is kept.
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<SoundBank, Error> {
+        SoundBank::from_entries(serde_json::from_str(json)?)
+    }
+    /// Loads a bank from JSON data read from `reader`, see `from_json`
+    #[cfg(feature = "json")]
+    pub fn load<R: std::io::Read>(reader: R) -> Result<SoundBank, Error> {
+        SoundBank::from_entries(serde_json::from_reader(reader)?)
+    }
+    #[cfg(feature = "json")]
+    fn from_entries(entries: Vec<Entry>) -> Result<SoundBank, Error> {
+        let mut bank = SoundBank::new();
+        for entry in entries {
+            TypedSample::try_from(entry.sample.clone())?;
+            bank.insert_with_meta(entry.meta, entry.sample);
+        }
+        Ok(bank)
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.sounds.iter().position(|entry| entry.meta.name == name)
+    }
+}
@@ -0,0 +1,295 @@
+//! Terminal sound effect editor
+//!
+//! Usage: `sfxr-tui [FILE]`, where FILE is a Sample that is loaded if it exists and written on
+//! save. Files ending in `.sfs` use the format of original sfxr, others JSON. Defaults to
+//! `sound.json`.
+
+use cpal::traits::{EventLoopTrait, HostTrait};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Sparkline};
+use ratatui::{DefaultTerminal, Frame};
+use sfxr::device::{best_config, OutputAdapter};
+use sfxr::{sfs, Category, Error, Generator, Prng, Sample, WaveType};
+use std::sync::{Arc, Mutex};
+use std::{fs, io, thread};
+
+/// An editable Sample field
+struct Field {
+    name: &'static str,
+    min: f64,
+    max: f64,
+    get: fn(&Sample) -> f64,
+    set: fn(&mut Sample, f64),
+}
+
+macro_rules! fields {
+    ($($field:ident: $min:expr, $max:expr;)*) => {
+        [$(Field {
+            name: stringify!($field),
+            min: $min,
+            max: $max,
+            get: |s| s.$field as f64,
+            set: |s, v| s.$field = v as _,
+        },)*]
+    };
+}
+
+const FIELDS: [Field; 25] = fields! {
+    base_freq: 0.0, 1.0;
+    freq_limit: 0.0, 1.0;
+    freq_ramp: -1.0, 1.0;
+    freq_dramp: -1.0, 1.0;
+    duty: 0.0, 1.0;
+    duty_ramp: -1.0, 1.0;
+    vib_strength: 0.0, 1.0;
+    vib_speed: 0.0, 1.0;
+    vib_delay: 0.0, 1.0;
+    env_attack: 0.0, 1.0;
+    env_sustain: 0.0, 1.0;
+    env_decay: 0.0, 1.0;
+    env_punch: -1.0, 1.0;
+    lpf_resonance: 0.0, 1.0;
+    lpf_freq: 0.0, 1.0;
+    lpf_ramp: -1.0, 1.0;
+    hpf_freq: 0.0, 1.0;
+    hpf_ramp: -1.0, 1.0;
+    pha_offset: -1.0, 1.0;
+    pha_ramp: -1.0, 1.0;
+    repeat_speed: 0.0, 1.0;
+    arp_speed: 0.0, 1.0;
+    arp_mod: -1.0, 1.0;
+    unison_detune: 0.0, 1.0;
+    volume: 0.0, 1.0;
+};
+
+const WAVE_TYPES: [WaveType; 5] = [
+    WaveType::Square,
+    WaveType::Sawtooth,
+    WaveType::Sine,
+    WaveType::Noise,
+    WaveType::Triangle,
+];
+
+const HELP: &str = "↑↓ select  ←→ adjust (shift: coarse)  w wave  space play  1-8 preset  \
+                    m mutate  n new  s save  q quit";
+
+struct Editor {
+    sample: Sample,
+    selected: usize,
+    path: String,
+    status: String,
+    player: Arc<Mutex<Option<Generator>>>,
+}
+
+impl Editor {
+    fn play(&self) {
+        *self.player.lock().unwrap() = Some(Generator::new(self.sample.clone()));
+    }
+
+    fn adjust(&mut self, steps: f64) {
+        let field = &FIELDS[self.selected];
+        let value = (field.get)(&self.sample) + steps * (field.max - field.min) / 100.0;
+        (field.set)(&mut self.sample, value.clamp(field.min, field.max));
+    }
+
+    fn save(&mut self) {
+        let result = if is_sfs(&self.path) {
+            sfs::save(&self.path, &self.sample)
+        } else {
+            serde_json::to_string_pretty(&self.sample)
+                .map_err(io::Error::from)
+                .and_then(|json| fs::write(&self.path, json))
+        };
+        self.status = match result {
+            Ok(()) => format!("Saved {}", self.path),
+            Err(e) => format!("Saving {} failed: {}", self.path, e),
+        };
+    }
+
+    /// Handles a key press. Returns `false` to quit.
+    fn key(&mut self, code: KeyCode) -> bool {
+        self.status.clear();
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.selected = (self.selected + FIELDS.len() - 1) % FIELDS.len();
+                return true;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1) % FIELDS.len();
+                return true;
+            }
+            KeyCode::Left | KeyCode::Char('h') => self.adjust(-1.0),
+            KeyCode::Right | KeyCode::Char('l') => self.adjust(1.0),
+            KeyCode::Char('H') => self.adjust(-10.0),
+            KeyCode::Char('L') => self.adjust(10.0),
+            KeyCode::Char('w') => {
+                let index = WAVE_TYPES.iter().position(|&w| w == self.sample.wave_type);
+                self.sample.wave_type = WAVE_TYPES[index.map_or(0, |i| (i + 1) % WAVE_TYPES.len())];
+            }
+            KeyCode::Char('m') => {
+                self.sample.mutate(None);
+            }
+            KeyCode::Char('n') => self.sample = Sample::new(),
+            KeyCode::Char(c @ '1'..='8') => {
+                let category = Category::ALL[c as usize - '1' as usize];
+                self.sample = category.def().generate(&mut Prng::new(rand::random()));
+                self.status = format!("New {}", category.name());
+            }
+            KeyCode::Char('s') => {
+                self.save();
+                return true;
+            }
+            KeyCode::Char(' ') => {}
+            _ => return true,
+        }
+        self.play();
+        true
+    }
+
+    fn draw(&self, frame: &mut Frame<'_>) {
+        let [fields, envelope, help] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(6),
+            Constraint::Length(2),
+        ])
+        .areas(frame.area());
+
+        let mut lines = vec![Line::from(format!(
+            "  {:<14} {:?}",
+            "wave_type", self.sample.wave_type
+        ))];
+        for (index, field) in FIELDS.iter().enumerate() {
+            let value = (field.get)(&self.sample);
+            let filled = ((value - field.min) / (field.max - field.min) * 30.0).round() as usize;
+            let text = format!(
+                "{} {:<14} {:>7.3} {}{}",
+                if index == self.selected { ">" } else { " " },
+                field.name,
+                value,
+                "█".repeat(filled),
+                "░".repeat(30 - filled)
+            );
+            let style = if index == self.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            lines.push(Line::styled(text, style));
+        }
+        let title = format!(" sfxr — {} ", self.path);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+            fields,
+        );
+
+        let width = usize::from(envelope.width.saturating_sub(2)).max(1);
+        let curve: Vec<u64> = self
+            .sample
+            .envelope_curve(width)
+            .iter()
+            .map(|&(_, amplitude)| (amplitude * 100.0) as u64)
+            .collect();
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::default().borders(Borders::ALL).title(" envelope "))
+                .data(&curve),
+            envelope,
+        );
+
+        let status = if self.status.is_empty() {
+            HELP
+        } else {
+            &self.status
+        };
+        frame.render_widget(Paragraph::new(status), help);
+    }
+}
+
+fn is_sfs(path: &str) -> bool {
+    path.to_lowercase().ends_with(".sfs")
+}
+
+/// Reads the Sample at `path`, or returns `None` if there is no file
+fn load(path: &str) -> Option<Result<Sample, Error>> {
+    if !std::path::Path::new(path).exists() {
+        return None;
+    }
+    Some(if is_sfs(path) {
+        sfs::load(path)
+    } else {
+        fs::read_to_string(path)
+            .map_err(Error::from)
+            .and_then(|json| serde_json::from_str(&json).map_err(Error::from))
+    })
+}
+
+/// Starts a thread playing the generator in `player` on the default output device
+fn start_audio(player: Arc<Mutex<Option<Generator>>>) {
+    thread::spawn(move || {
+        let host = cpal::default_host();
+        let event_loop = host.event_loop();
+        let device = match host.default_output_device() {
+            Some(device) => device,
+            None => return,
+        };
+        let format = match best_config(&device) {
+            Some(format) => format,
+            None => return,
+        };
+        let mut adapter = OutputAdapter::new(&format);
+        let stream_id = match event_loop.build_output_stream(&device, &format) {
+            Ok(stream_id) => stream_id,
+            Err(_) => return,
+        };
+        if event_loop.play_stream(stream_id).is_err() {
+            return;
+        }
+
+        event_loop.run(move |_, stream_result| {
+            if let Ok(cpal::StreamData::Output { mut buffer }) = stream_result {
+                adapter.fill(player.lock().unwrap().as_mut(), &mut buffer);
+            }
+        });
+    });
+}
+
+fn run(terminal: &mut DefaultTerminal, editor: &mut Editor) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| editor.draw(frame))?;
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !editor.key(key.code) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "sound.json".to_string());
+    let (sample, status) = match load(&path) {
+        Some(Ok(sample)) => (sample, format!("Loaded {}", path)),
+        Some(Err(e)) => (Sample::new(), format!("Loading {} failed: {}", path, e)),
+        None => (Sample::new(), String::new()),
+    };
+
+    let player = Arc::new(Mutex::new(None));
+    start_audio(player.clone());
+
+    let mut editor = Editor {
+        sample,
+        selected: 0,
+        path,
+        status,
+        player,
+    };
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &mut editor);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,101 @@
+//! Playing two Generators one after the other
+
+use crate::{FreqLimitBehavior, Generator};
+
+/// Plays one Generator until its envelope ends, then another from the next sample on,
+/// created by `Generator::chain`
+///
+/// Two phase sounds, such as a beep that ends in an explosion, can be defined as separate
+/// Samples and still play without a gap or overlap. The tail the filters of the first
+/// Generator would produce after its envelope ends is cut off.
+pub struct ChainedGenerator {
+    first: Generator,
+    second: Generator,
+    switched: bool,
+}
+
+impl Generator {
+    /// Returns a ChainedGenerator playing this Generator from its current position, and
+    /// `next` from its current position once this one ends
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let beep = Generator::new(Sample::blip(Some(1)));
+    /// let boom = Generator::new(Sample::explosion(Some(2)));
+    /// let length = beep.remaining_samples() + boom.remaining_samples();
+    ///
+    /// let mut chained = beep.chain(boom);
+    /// let mut buffer = vec![0.0; length];
+    /// chained.generate(&mut buffer);
+    /// assert!(chained.is_finished());
+    /// ```
+    pub fn chain(self, next: Generator) -> ChainedGenerator {
+        ChainedGenerator {
+            first: self,
+            second: next,
+            switched: false,
+        }
+    }
+}
+
+impl ChainedGenerator {
+    /// Fills `buffer` with the next samples, switching to the second Generator at the sample
+    /// where the envelope of the first ends
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        let mut rest = buffer;
+        while !self.switched && !rest.is_empty() {
+            if self.first.is_finished() {
+                self.switched = true;
+                break;
+            }
+            // Generating at most until the end of the envelope keeps the switch sample
+            // accurate. Reaching the frequency limit can end it sooner, at any sample.
+            let sample = &self.first.sample;
+            let len = if sample.freq_limit > 0.0
+                && sample.freq_limit_behavior == FreqLimitBehavior::EndSound
+            {
+                1
+            } else {
+                self.first.remaining_samples().clamp(1, rest.len())
+            };
+            let (block, tail) = rest.split_at_mut(len);
+            self.first.generate(block);
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            self.second.generate(rest);
+        }
+    }
+    /// Resets both Generators, to play the first from the beginning again
+    pub fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+        self.switched = false;
+    }
+    /// Returns `true` once the envelope of the second Generator has ended
+    pub fn is_finished(&self) -> bool {
+        self.switched && self.second.is_finished()
+    }
+    /// Returns `true` once playback has switched to the second Generator
+    pub fn is_switched(&self) -> bool {
+        self.switched
+    }
+    /// Returns the first Generator
+    pub fn first(&self) -> &Generator {
+        &self.first
+    }
+    /// Returns the first Generator, for example to change its volume
+    pub fn first_mut(&mut self) -> &mut Generator {
+        &mut self.first
+    }
+    /// Returns the second Generator
+    pub fn second(&self) -> &Generator {
+        &self.second
+    }
+    /// Returns the second Generator, for example to change its volume
+    pub fn second_mut(&mut self) -> &mut Generator {
+        &mut self.second
+    }
+}
@@ -0,0 +1,138 @@
+use std::convert::TryFrom;
+
+use crate::{Error, Sample, TypedSample, WaveType};
+
+/// Number of values in a clipboard string
+const FIELDS: usize = 24;
+
+impl Sample {
+    /// Returns the parameters as the comma separated settings string that as3sfxr, and the
+    /// editors derived from it, copy to and paste from the clipboard
+    ///
+    /// The string has the wave type index followed by 23 values with at most four decimals,
+    /// with zero written as an empty value. Only the fields of original sfxr except
+    /// `vib_delay` are included, so other fields are read back at their `Sample::new` values.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::Sample;
+    /// let mut blip = Sample::new();
+    /// blip.env_attack = 0.0;
+    /// let text = blip.to_clipboard_string();
+    /// assert_eq!(text, "0,,0.1,,0.5,0.3,,,,,,,,,,,,,1,,,,,0.5");
+    /// let pasted = Sample::from_clipboard_string(&text).unwrap();
+    /// assert_eq!(pasted.env_decay, 0.5);
+    /// ```
+    pub fn to_clipboard_string(&self) -> String {
+        let s = self;
+        let values = [
+            f64::from(s.env_attack),
+            f64::from(s.env_sustain),
+            f64::from(s.env_punch),
+            f64::from(s.env_decay),
+            s.base_freq,
+            s.freq_limit,
+            s.freq_ramp,
+            s.freq_dramp,
+            s.vib_strength,
+            s.vib_speed,
+            s.arp_mod,
+            f64::from(s.arp_speed),
+            f64::from(s.duty),
+            f64::from(s.duty_ramp),
+            f64::from(s.repeat_speed),
+            f64::from(s.pha_offset),
+            f64::from(s.pha_ramp),
+            f64::from(s.lpf_freq),
+            f64::from(s.lpf_ramp),
+            f64::from(s.lpf_resonance),
+            f64::from(s.hpf_freq),
+            f64::from(s.hpf_ramp),
+            f64::from(s.volume),
+        ];
+        let mut text = s.wave_type.as_index().to_string();
+        for &value in values.iter() {
+            text.push(',');
+            text.push_str(&format_value(value));
+        }
+        text
+    }
+    /// Constructs a Sample from a settings string copied from as3sfxr or a compatible editor,
+    /// see `to_clipboard_string`. Fails if the string doesn't have 24 values, has an unknown
+    /// wave type, or has values outside their valid ranges.
+    pub fn from_clipboard_string(text: &str) -> Result<Sample, Error> {
+        let fields: Vec<&str> = text.trim().split(',').map(str::trim).collect();
+        if fields.len() != FIELDS {
+            return Err(parse_error(format!(
+                "expected {} values, found {}",
+                FIELDS,
+                fields.len()
+            )));
+        }
+        let values = fields
+            .iter()
+            .map(|field| {
+                if field.is_empty() {
+                    Ok(0.0)
+                } else {
+                    field
+                        .parse::<f64>()
+                        .map_err(|_| parse_error(format!("invalid number {:?}", field)))
+                }
+            })
+            .collect::<Result<Vec<f64>, Error>>()?;
+
+        let mut s = Sample::new();
+        let wave_type = values[0];
+        s.wave_type = Some(wave_type)
+            .filter(|index| index.fract() == 0.0 && (0.0..=255.0).contains(index))
+            .and_then(|index| WaveType::from_index(index as u8))
+            .ok_or_else(|| parse_error(format!("unknown wave type {}", wave_type)))?;
+        s.env_attack = values[1] as f32;
+        s.env_sustain = values[2] as f32;
+        s.env_punch = values[3] as f32;
+        s.env_decay = values[4] as f32;
+        s.base_freq = values[5];
+        s.freq_limit = values[6];
+        s.freq_ramp = values[7];
+        s.freq_dramp = values[8];
+        s.vib_strength = values[9];
+        s.vib_speed = values[10];
+        s.arp_mod = values[11];
+        s.arp_speed = values[12] as f32;
+        s.duty = values[13] as f32;
+        s.duty_ramp = values[14] as f32;
+        s.repeat_speed = values[15] as f32;
+        s.pha_offset = values[16] as f32;
+        s.pha_ramp = values[17] as f32;
+        s.lpf_freq = values[18] as f32;
+        s.lpf_ramp = values[19] as f32;
+        s.lpf_resonance = values[20] as f32;
+        s.hpf_freq = values[21] as f32;
+        s.hpf_ramp = values[22] as f32;
+        s.volume = values[23] as f32;
+
+        TypedSample::try_from(s.clone())?;
+        Ok(s)
+    }
+}
+
+/// Formats `value` with at most four decimals and no trailing zeros, or as an empty string if
+/// it rounds to zero
+fn format_value(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text.is_empty() || text == "0" || text == "-0" {
+        String::new()
+    } else {
+        text.to_string()
+    }
+}
+
+fn parse_error(message: String) -> Error {
+    Error::Parse {
+        format: "clipboard",
+        message,
+    }
+}
@@ -0,0 +1,262 @@
+//! Compatibility with the random decisions of the original sfxr
+//!
+//! The original sfxr picks preset parameters with the C library `rand()`. This module
+//! reimplements the Microsoft C runtime `rand()` used by the original Windows builds, along with
+//! the original preset code and its `ResetParams` defaults, so that a 32-bit seed passed to
+//! `srand` there gives the same Sample here. The crate's own presets, like `Sample::laser`,
+//! use different ranges and random numbers, and are not affected.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::{compat, Category};
+//! let sample = compat::preset(Category::Laser, 12345);
+//! assert_eq!(sample.base_freq, compat::preset(Category::Laser, 12345).base_freq);
+//! ```
+
+use crate::{Category, Sample, WaveType};
+
+/// The Microsoft C runtime `rand()` linear congruential generator
+#[derive(Clone, Debug, PartialEq)]
+pub struct SfxrRand {
+    state: u32,
+}
+
+impl SfxrRand {
+    /// Constructs a new generator, equivalent to calling `srand(seed)`
+    pub fn new(seed: u32) -> SfxrRand {
+        SfxrRand { state: seed }
+    }
+    /// Returns the next value between `0` and `0x7fff`, like `rand()`
+    pub fn rand(&mut self) -> u32 {
+        self.state = self.state.wrapping_mul(214_013).wrapping_add(2_531_011);
+        (self.state >> 16) & 0x7fff
+    }
+    /// Returns a value between `0` and `n` inclusive, like the original `rnd(n)`
+    pub fn rnd(&mut self, n: u32) -> u32 {
+        self.rand() % (n + 1)
+    }
+    /// Returns a value between `0.0` and `range` inclusive, like the original `frnd(range)`
+    pub fn frnd(&mut self, range: f32) -> f32 {
+        self.rnd(10_000) as f32 / 10_000.0 * range
+    }
+}
+
+/// Returns the parameters set by the original `ResetParams`
+///
+/// These differ from `Sample::new` in the envelope.
+pub fn reset_params() -> Sample {
+    let mut s = Sample::new();
+    s.env_attack = 0.0;
+    s.env_sustain = 0.3;
+    s.env_decay = 0.4;
+    s
+}
+
+/// Generates a Sample in `category` the way the original sfxr does after `srand(seed)`
+///
+/// `Category::Alien` is missing from the original 1.x releases. It is drawn the same way, in
+/// the style of the other original categories, from the ranges of `Sample::alien`.
+pub fn preset(category: Category, seed: u32) -> Sample {
+    preset_with(category, &mut SfxrRand::new(seed))
+}
+
+/// Generates a Sample in `category` the way the original sfxr does, drawing from `rng`. Use this
+/// to reproduce several presets generated in a row from one seed.
+pub fn preset_with(category: Category, rng: &mut SfxrRand) -> Sample {
+    let mut s = reset_params();
+    // The original stores parameters as floats
+    let mut base_freq: f32;
+    let mut freq_limit = 0.0;
+    let mut freq_ramp = 0.0;
+    let mut vib_strength = 0.0;
+    let mut vib_speed = 0.0;
+    let mut arp_mod = 0.0;
+    let mut wave = 0;
+
+    match category {
+        Category::Pickup => {
+            base_freq = 0.4 + rng.frnd(0.5);
+            s.env_attack = 0.0;
+            s.env_sustain = rng.frnd(0.1);
+            s.env_decay = 0.1 + rng.frnd(0.4);
+            s.env_punch = 0.3 + rng.frnd(0.3);
+            if rng.rnd(1) != 0 {
+                s.arp_speed = 0.5 + rng.frnd(0.2);
+                arp_mod = 0.2 + rng.frnd(0.4);
+            }
+        }
+        Category::Laser => {
+            wave = rng.rnd(2);
+            if wave == 2 && rng.rnd(1) != 0 {
+                wave = rng.rnd(1);
+            }
+            base_freq = 0.5 + rng.frnd(0.5);
+            freq_limit = base_freq - 0.2 - rng.frnd(0.6);
+            if freq_limit < 0.2 {
+                freq_limit = 0.2;
+            }
+            freq_ramp = -0.15 - rng.frnd(0.2);
+            if rng.rnd(2) == 0 {
+                base_freq = 0.3 + rng.frnd(0.6);
+                freq_limit = rng.frnd(0.1);
+                freq_ramp = -0.35 - rng.frnd(0.3);
+            }
+            if rng.rnd(1) != 0 {
+                s.duty = rng.frnd(0.5);
+                s.duty_ramp = rng.frnd(0.2);
+            } else {
+                s.duty = 0.4 + rng.frnd(0.5);
+                s.duty_ramp = -rng.frnd(0.7);
+            }
+            s.env_attack = 0.0;
+            s.env_sustain = 0.1 + rng.frnd(0.2);
+            s.env_decay = rng.frnd(0.4);
+            if rng.rnd(1) != 0 {
+                s.env_punch = rng.frnd(0.3);
+            }
+            if rng.rnd(2) == 0 {
+                s.pha_offset = rng.frnd(0.2);
+                s.pha_ramp = -rng.frnd(0.2);
+            }
+            if rng.rnd(1) != 0 {
+                s.hpf_freq = rng.frnd(0.3);
+            }
+        }
+        Category::Explosion => {
+            wave = 3;
+            if rng.rnd(1) != 0 {
+                base_freq = 0.1 + rng.frnd(0.4);
+                freq_ramp = -0.1 + rng.frnd(0.4);
+            } else {
+                base_freq = 0.2 + rng.frnd(0.7);
+                freq_ramp = -0.2 - rng.frnd(0.2);
+            }
+            base_freq *= base_freq;
+            if rng.rnd(4) == 0 {
+                freq_ramp = 0.0;
+            }
+            if rng.rnd(2) == 0 {
+                s.repeat_speed = 0.3 + rng.frnd(0.5);
+            }
+            s.env_attack = 0.0;
+            s.env_sustain = 0.1 + rng.frnd(0.3);
+            s.env_decay = rng.frnd(0.5);
+            if rng.rnd(1) == 0 {
+                s.pha_offset = -0.3 + rng.frnd(0.9);
+                s.pha_ramp = -rng.frnd(0.3);
+            }
+            s.env_punch = 0.2 + rng.frnd(0.6);
+            if rng.rnd(1) != 0 {
+                vib_strength = rng.frnd(0.7);
+                vib_speed = rng.frnd(0.6);
+            }
+            if rng.rnd(2) == 0 {
+                s.arp_speed = 0.6 + rng.frnd(0.3);
+                arp_mod = 0.8 - rng.frnd(1.6);
+            }
+        }
+        Category::Powerup => {
+            if rng.rnd(1) != 0 {
+                wave = 1;
+            } else {
+                s.duty = rng.frnd(0.6);
+            }
+            if rng.rnd(1) != 0 {
+                base_freq = 0.2 + rng.frnd(0.3);
+                freq_ramp = 0.1 + rng.frnd(0.4);
+                s.repeat_speed = 0.4 + rng.frnd(0.4);
+            } else {
+                base_freq = 0.2 + rng.frnd(0.3);
+                freq_ramp = 0.05 + rng.frnd(0.2);
+                if rng.rnd(1) != 0 {
+                    vib_strength = rng.frnd(0.7);
+                    vib_speed = rng.frnd(0.6);
+                }
+            }
+            s.env_attack = 0.0;
+            s.env_sustain = rng.frnd(0.4);
+            s.env_decay = 0.1 + rng.frnd(0.4);
+        }
+        Category::Hit => {
+            wave = rng.rnd(2);
+            if wave == 2 {
+                wave = 3;
+            }
+            if wave == 0 {
+                s.duty = rng.frnd(0.6);
+            }
+            base_freq = 0.2 + rng.frnd(0.6);
+            freq_ramp = -0.3 - rng.frnd(0.4);
+            s.env_attack = 0.0;
+            s.env_sustain = rng.frnd(0.1);
+            s.env_decay = 0.1 + rng.frnd(0.2);
+            if rng.rnd(1) != 0 {
+                s.hpf_freq = rng.frnd(0.3);
+            }
+        }
+        Category::Jump => {
+            wave = 0;
+            s.duty = rng.frnd(0.6);
+            base_freq = 0.3 + rng.frnd(0.3);
+            freq_ramp = 0.1 + rng.frnd(0.2);
+            s.env_attack = 0.0;
+            s.env_sustain = 0.1 + rng.frnd(0.3);
+            s.env_decay = 0.1 + rng.frnd(0.2);
+            if rng.rnd(1) != 0 {
+                s.hpf_freq = rng.frnd(0.3);
+            }
+            if rng.rnd(1) != 0 {
+                s.lpf_freq = 1.0 - rng.frnd(0.6);
+            }
+        }
+        Category::Blip => {
+            wave = rng.rnd(1);
+            if wave == 0 {
+                s.duty = rng.frnd(0.6);
+            }
+            base_freq = 0.2 + rng.frnd(0.4);
+            s.env_attack = 0.0;
+            s.env_sustain = 0.1 + rng.frnd(0.1);
+            s.env_decay = rng.frnd(0.2);
+            s.hpf_freq = 0.1;
+        }
+        Category::Alien => {
+            wave = rng.rnd(3);
+            if wave == 1 {
+                wave = 2;
+            } else if wave == 3 {
+                wave = 4;
+            }
+            if wave == 0 {
+                s.duty = 0.2 + rng.frnd(0.4);
+            }
+            base_freq = 0.4 + rng.frnd(0.4);
+            freq_ramp = -0.2 + rng.frnd(0.5);
+            if rng.rnd(1) != 0 {
+                s.freq_dramp = f64::from(-0.2 + rng.frnd(0.4));
+            }
+            vib_strength = 0.3 + rng.frnd(0.5);
+            vib_speed = 0.4 + rng.frnd(0.5);
+            s.env_attack = rng.frnd(0.1);
+            s.env_sustain = 0.1 + rng.frnd(0.2);
+            s.env_decay = 0.1 + rng.frnd(0.3);
+            if rng.rnd(1) != 0 {
+                s.repeat_speed = 0.3 + rng.frnd(0.4);
+            }
+            if rng.rnd(2) == 0 {
+                s.arp_speed = 0.4 + rng.frnd(0.4);
+                arp_mod = -0.5 + rng.frnd(1.0);
+            }
+        }
+    }
+
+    s.wave_type = WaveType::from_index(wave as u8).expect("original wave type");
+    s.base_freq = f64::from(base_freq);
+    s.freq_limit = f64::from(freq_limit);
+    s.freq_ramp = f64::from(freq_ramp);
+    s.vib_strength = f64::from(vib_strength);
+    s.vib_speed = f64::from(vib_speed);
+    s.arp_mod = f64::from(arp_mod);
+    s
+}
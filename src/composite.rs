@@ -0,0 +1,227 @@
+//! Sound effects made of a few layered Samples
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "rand")]
+use crate::{seed_or_random, Prng, WaveType};
+use crate::{Generator, Sample, SAMPLE_RATE};
+
+/// Samples generated at a time by a layer of a CompositeGenerator
+const CHUNK: usize = 256;
+
+/// One Sample of a CompositeSample, started after a delay and scaled by a gain
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Layer {
+    /// Sound of the layer
+    pub sample: Sample,
+    /// Time in seconds from the start of the composite until the layer starts. Must not be
+    /// negative. Default is `0.0`.
+    pub delay_secs: f32,
+    /// Linear gain of the layer in the mix. Default is `1.0`.
+    pub gain: f32,
+}
+
+impl Layer {
+    /// Constructs a Layer playing `sample` immediately at full gain
+    pub const fn new(sample: Sample) -> Layer {
+        Layer {
+            sample,
+            delay_secs: 0.0,
+            gain: 1.0,
+        }
+    }
+    /// Returns the delay in samples at the standard sample rate
+    fn delay_samples(&self) -> usize {
+        (self.delay_secs * SAMPLE_RATE as f32).round() as usize
+    }
+}
+
+/// A sound effect of up to three Samples played on top of each other
+///
+/// Big sounds are usually built from layers with distinct jobs, such as the low thump, noise
+/// burst and debris tail of an explosion. A CompositeSample keeps them together, and a
+/// `CompositeGenerator` mixes them into a single mono output.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{CompositeGenerator, CompositeSample, Layer, Sample, WaveType};
+/// let mut thump = Sample::new();
+/// thump.wave_type = WaveType::Sine;
+/// thump.base_freq = 0.1;
+/// thump.freq_ramp = -0.3;
+///
+/// let mut impact = CompositeSample::new();
+/// impact.add(Layer::new(thump));
+/// impact.add(Layer {
+///     delay_secs: 0.05,
+///     gain: 0.7,
+///     ..Layer::new(Sample::hit(Some(4)))
+/// });
+///
+/// let length = impact.len();
+/// let mut generator = CompositeGenerator::new(impact);
+/// let audio = generator.render();
+/// assert_eq!(audio.len(), length);
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompositeSample {
+    /// Layers of the sound, unused slots are `None`
+    pub layers: [Option<Layer>; CompositeSample::MAX_LAYERS],
+}
+
+#[allow(clippy::new_without_default)]
+impl CompositeSample {
+    /// Largest number of layers of a CompositeSample
+    pub const MAX_LAYERS: usize = 3;
+
+    /// Constructs a CompositeSample without layers
+    pub const fn new() -> CompositeSample {
+        CompositeSample {
+            layers: [None, None, None],
+        }
+    }
+    /// Constructs a random explosion of a pitch dropping sine thump, a noise burst and a
+    /// delayed low passed debris tail, using optional random seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn big_explosion(seed: Option<u64>) -> CompositeSample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+
+        let mut thump = Sample::new();
+        thump.wave_type = WaveType::Sine;
+        thump.base_freq = 0.06 + f64::from(rng.next_f32()) * 0.04;
+        thump.freq_ramp = -0.2 - f64::from(rng.next_f32()) * 0.1;
+        thump.env_attack = 0.0;
+        thump.env_sustain = 0.1 + rng.next_f32() * 0.1;
+        thump.env_decay = 0.3 + rng.next_f32() * 0.1;
+        thump.env_punch = 0.5;
+
+        let mut debris = Sample::new();
+        debris.wave_type = WaveType::Noise;
+        debris.base_freq = 0.15 + f64::from(rng.next_f32()) * 0.1;
+        debris.env_attack = 0.0;
+        debris.env_sustain = 0.2 + rng.next_f32() * 0.1;
+        debris.env_decay = 0.5 + rng.next_f32() * 0.2;
+        debris.lpf_freq = 0.3 + rng.next_f32() * 0.1;
+        debris.noise_seed = Some(rng.next_u64());
+
+        let mut explosion = CompositeSample::new();
+        explosion.add(Layer::new(thump));
+        explosion.add(Layer {
+            gain: 0.8,
+            ..Layer::new(Sample::explosion(Some(rng.next_u64())))
+        });
+        explosion.add(Layer {
+            delay_secs: 0.08 + rng.next_f32() * 0.07,
+            gain: 0.5,
+            ..Layer::new(debris)
+        });
+        explosion
+    }
+    /// Adds `layer` in the first unused slot. Panics if all `MAX_LAYERS` slots are used.
+    pub fn add(&mut self, layer: Layer) {
+        assert!(layer.delay_secs >= 0.0, "delay_secs must not be negative");
+        let slot = self
+            .layers
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .expect("a CompositeSample holds at most 3 layers");
+        *slot = Some(layer);
+    }
+    /// Returns the used layers
+    pub fn iter(&self) -> impl Iterator<Item = &Layer> {
+        self.layers.iter().flatten()
+    }
+    /// Returns the length of the composite in samples, until the envelope of the last layer
+    /// ends
+    pub fn len(&self) -> usize {
+        self.iter()
+            .map(|layer| layer.delay_samples() + layer.sample.envelope_length())
+            .max()
+            .unwrap_or(0)
+    }
+    /// Returns `true` if the composite has no layers
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// Generates the mix of the layers of a CompositeSample
+///
+/// Like Generator, generation doesn't allocate, so it can run in an audio callback.
+pub struct CompositeGenerator {
+    /// Composite being generated
+    pub composite: CompositeSample,
+    voices: [Option<LayerVoice>; CompositeSample::MAX_LAYERS],
+    position: usize,
+}
+
+struct LayerVoice {
+    delay: usize,
+    gain: f32,
+    generator: Generator,
+}
+
+impl CompositeGenerator {
+    /// Constructs a CompositeGenerator playing `composite` from the beginning
+    pub fn new(composite: CompositeSample) -> CompositeGenerator {
+        let mut voices = [None, None, None];
+        for (voice, layer) in voices.iter_mut().zip(composite.layers.iter()) {
+            *voice = layer.as_ref().map(|layer| LayerVoice {
+                delay: layer.delay_samples(),
+                gain: layer.gain,
+                generator: Generator::new(layer.sample.clone()),
+            });
+        }
+        CompositeGenerator {
+            composite,
+            voices,
+            position: 0,
+        }
+    }
+    /// Fills `buffer` with the next samples of the mix. Subsequent calls continue where the
+    /// last left off. Call `reset` first to start generating from the beginning.
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        buffer.iter_mut().for_each(|v| *v = 0.0);
+
+        let start = self.position;
+        let mut scratch = [0.0; CHUNK];
+        for voice in self.voices.iter_mut().flatten() {
+            let from = voice.delay.saturating_sub(start).min(buffer.len());
+            for chunk in buffer[from..].chunks_mut(CHUNK) {
+                let scratch = &mut scratch[..chunk.len()];
+                voice.generator.generate(scratch);
+                for (v, &layer) in chunk.iter_mut().zip(scratch.iter()) {
+                    *v += layer * voice.gain;
+                }
+            }
+        }
+
+        self.position += buffer.len();
+    }
+    /// Resets all layers to the beginning of the composite
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.voices
+            .iter_mut()
+            .flatten()
+            .for_each(|voice| voice.generator.reset());
+    }
+    /// Returns `true` once every layer has started and its envelope has ended
+    pub fn is_finished(&self) -> bool {
+        self.voices
+            .iter()
+            .flatten()
+            .all(|voice| self.position >= voice.delay && voice.generator.is_finished())
+    }
+    /// Renders the whole composite from the beginning, `CompositeSample::len` samples long
+    pub fn render(&mut self) -> Vec<f32> {
+        let mut buffer = vec![0.0; self.composite.len()];
+        self.reset();
+        self.generate(&mut buffer);
+        buffer
+    }
+}
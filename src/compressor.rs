@@ -0,0 +1,110 @@
+//! Dynamics compressor at the end of the synthesis chain
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::generator::Filter;
+use crate::{db_to_gain, SAMPLE_RATE};
+
+/// Settings of a dynamics compressor
+///
+/// Applied as the last stage of synthesis, after the `Equalizer`, to make punchy sounds
+/// punchier and tame peaks. Levels are measured on the synthesized sound before any volume is
+/// applied, where a square wave at full envelope peaks at `0.0` dB. With `ratio` set to
+/// `f32::INFINITY` it acts as a limiter.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Compressor, Sample};
+/// let mut hit = Sample::hit(Some(2));
+/// hit.compressor = Some(Compressor {
+///     threshold_db: -20.0,
+///     ratio: 4.0,
+///     makeup_db: 6.0,
+///     ..Compressor::new()
+/// });
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Compressor {
+    /// Level above which the signal is compressed, in dB. Default is `-12.0`.
+    pub threshold_db: f32,
+    /// Input level change above the threshold per dB of output level change. Values below
+    /// `1.0` are treated as `1.0`, no compression. Default is `4.0`.
+    pub ratio: f32,
+    /// Time for the compression to take effect when the level rises. Default is `0.005`.
+    pub attack_secs: f32,
+    /// Time for the compression to wear off when the level falls. Default is `0.1`.
+    pub release_secs: f32,
+    /// Gain applied after compression in dB, to make up for the lost level. Default is
+    /// `0.0`.
+    pub makeup_db: f32,
+}
+
+#[allow(clippy::new_without_default)]
+impl Compressor {
+    /// Constructs a Compressor with moderate default settings
+    pub const fn new() -> Compressor {
+        Compressor {
+            threshold_db: -12.0,
+            ratio: 4.0,
+            attack_secs: 0.005,
+            release_secs: 0.1,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+/// Compressor state during generation, running at `SAMPLE_RATE`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct CompressorFilter {
+    threshold_db: f32,
+    slope: f32,
+    attack: f32,
+    release: f32,
+    makeup_db: f32,
+    /// Current gain reduction in dB
+    reduction_db: f32,
+}
+
+impl CompressorFilter {
+    pub fn new(compressor: &Compressor) -> CompressorFilter {
+        CompressorFilter {
+            threshold_db: compressor.threshold_db,
+            slope: 1.0 - 1.0 / compressor.ratio.max(1.0),
+            attack: CompressorFilter::coefficient(compressor.attack_secs),
+            release: CompressorFilter::coefficient(compressor.release_secs),
+            makeup_db: compressor.makeup_db,
+            reduction_db: 0.0,
+        }
+    }
+    /// Current gain, including the makeup gain
+    pub fn gain(&self) -> f32 {
+        db_to_gain(self.makeup_db - self.reduction_db)
+    }
+    /// One pole smoothing coefficient reaching about 63% of a change in `secs` seconds
+    fn coefficient(secs: f32) -> f32 {
+        let samples = secs * SAMPLE_RATE as f32;
+        if samples > 1.0 {
+            (-1.0 / samples).exp()
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for CompressorFilter {
+    fn filter(&mut self, sample: f32) -> f32 {
+        let level_db = 20.0 * sample.abs().max(1e-6).log10();
+        let target = (level_db - self.threshold_db).max(0.0) * self.slope;
+        let coefficient = if target > self.reduction_db {
+            self.attack
+        } else {
+            self.release
+        };
+        self.reduction_db = target + (self.reduction_db - target) * coefficient;
+        sample * self.gain()
+    }
+}
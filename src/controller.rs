@@ -0,0 +1,107 @@
+//! Continuous controllers for playing a Generator live
+
+use crate::{Generator, SAMPLE_RATE};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Time constant controller values ease towards their targets with, short enough to follow a
+/// wheel closely but long enough to avoid zipper noise
+const SMOOTHING_SECS: f32 = 0.01;
+/// Fraction of the remaining distance to the target moved each sample
+const SMOOTHING: f32 = 1.0 / (SMOOTHING_SECS * SAMPLE_RATE as f32);
+/// Vibrato depth added by a full modulation amount, in `Sample::vib_strength` units. About a
+/// semitone from peak to peak.
+const MOD_VIB_STRENGTH: f32 = 0.06;
+
+/// A controller value eased towards its target one sample at a time
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Smoothed {
+    value: f32,
+    target: f32,
+}
+
+impl Smoothed {
+    fn new(value: f32) -> Smoothed {
+        Smoothed {
+            value,
+            target: value,
+        }
+    }
+    /// Moves the value one sample closer to the target, returning it if it changed
+    fn advance(&mut self) -> Option<f32> {
+        if self.value == self.target {
+            return None;
+        }
+        self.value += (self.target - self.value) * SMOOTHING;
+        if (self.target - self.value).abs() < 1.0e-4 {
+            self.value = self.target;
+        }
+        Some(self.value)
+    }
+}
+
+/// Pitch bend and modulation amount of a Generator
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Controllers {
+    pitch_bend: Smoothed,
+    mod_amount: Smoothed,
+}
+
+impl Controllers {
+    pub(crate) fn new() -> Controllers {
+        Controllers {
+            pitch_bend: Smoothed::new(0.0),
+            mod_amount: Smoothed::new(0.0),
+        }
+    }
+}
+
+impl Generator {
+    /// Bends the pitch by `semitones`, up if positive and down if negative, like the pitch
+    /// wheel of a keyboard. The pitch glides to the new bend over about 10 ms, and the bend is
+    /// kept across `reset` and `note_on`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// generator.note_on(60, 100);
+    /// // A whole tone up, as a pitch wheel at full range commonly goes
+    /// generator.set_pitch_bend(2.0);
+    /// let mut buffer = [0.0; 512];
+    /// generator.generate(&mut buffer);
+    /// assert_eq!(generator.pitch_bend(), 2.0);
+    /// ```
+    pub fn set_pitch_bend(&mut self, semitones: f32) {
+        self.controllers.pitch_bend.target = semitones;
+    }
+    /// Returns the pitch bend set with `set_pitch_bend`
+    pub fn pitch_bend(&self) -> f32 {
+        self.controllers.pitch_bend.target
+    }
+    /// Sets the modulation amount between `0.0` and `1.0`, like the modulation wheel of a
+    /// keyboard. It deepens the vibrato by up to about a semitone, played at 5.5 Hz if the
+    /// Sample has no `vib_speed`. Changes are smoothed like `set_pitch_bend`, and the amount is
+    /// kept across `reset` and `note_on`.
+    pub fn set_mod_amount(&mut self, amount: f32) {
+        self.controllers.mod_amount.target = amount.clamp(0.0, 1.0);
+    }
+    /// Returns the modulation amount set with `set_mod_amount`
+    pub fn mod_amount(&self) -> f32 {
+        self.controllers.mod_amount.target
+    }
+    /// Moves the controllers one sample closer to their targets and applies them
+    pub(crate) fn advance_controllers(&mut self) {
+        if let Some(semitones) = self.controllers.pitch_bend.advance() {
+            self.oscillator
+                .set_bend(2f64.powf(f64::from(semitones) / 12.0));
+        }
+        if let Some(amount) = self.controllers.mod_amount.advance() {
+            self.oscillator
+                .set_extra_vibrato(f64::from(amount * MOD_VIB_STRENGTH));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::generator::Oscillator;
+use crate::{
+    ClipMode, GeneratorConfig, NoiseMode, Sample, SampleHold, SlideMode, WaveType, SAMPLE_RATE,
+};
+
+impl Sample {
+    /// Returns a rough estimate of the CPU time needed to generate the whole sound, in units
+    /// of one second of a plain square wave at the default settings. Nothing is rendered.
+    ///
+    /// The estimate counts the processing stages that are active, such as unison voices,
+    /// the low pass filter and noise generation, multiplied by the envelope duration. Repeats
+    /// don't add to it, as they restart within that duration. Useful for voice budgets and for
+    /// flagging unusually expensive sounds in asset pipelines; actual costs vary by platform.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::Sample;
+    /// let mut thick = Sample::new();
+    /// thick.unison_voices = 8;
+    /// assert!(thick.estimated_cost() > Sample::new().estimated_cost());
+    /// ```
+    pub fn estimated_cost(&self) -> f32 {
+        self.estimated_cost_with(&GeneratorConfig::new())
+    }
+    /// Returns the estimated cost like `estimated_cost`, when rendered according to `config`
+    pub fn estimated_cost_with(&self, config: &GeneratorConfig) -> f32 {
+        let seconds = self.envelope_length() as f32 / SAMPLE_RATE as f32;
+        let reference = Sample::new().cost_per_second(&GeneratorConfig::new());
+        seconds * self.cost_per_second(config) / reference
+    }
+
+    /// Cost of one second of sound in arbitrary units
+    fn cost_per_second(&self, config: &GeneratorConfig) -> f32 {
+        let legacy = config.legacy_compat;
+        let antialiasing = config.antialiasing && !legacy;
+
+        let wave = match self.wave_type {
+            WaveType::Sine => 1.5,
+            WaveType::Square | WaveType::Triangle if antialiasing => 1.5,
+            WaveType::Noise => match self.noise_mode {
+                // A table refill of 32 random values every period
+                NoiseMode::Pitched => 1.0 + 8.0 / Oscillator::period(self.base_freq) as f32,
+                NoiseMode::Continuous => 1.25,
+            },
+            _ => 1.0,
+        };
+        let voices = f32::from(self.unison_voices.clamp(1, Sample::MAX_UNISON_VOICES));
+        // Extra unison voices also wrap their own phase
+        let oscillator = wave + (voices - 1.0) * (wave + 0.5);
+        // The low pass filter is bypassed when its cutoff is zero
+        let lowpass = if self.lpf_freq > 0.0 || self.lpf_target.is_some() {
+            1.0
+        } else {
+            0.0
+        };
+        // Envelope, high pass filter and phaser always run
+        let supersample = oscillator + lowpass + 1.5;
+
+        let slide = match self.slide_mode {
+            SlideMode::Legacy => 0.0,
+            SlideMode::LinearHz | SlideMode::LinearSemitones => 0.5,
+        };
+        let ramps = [self.duty_target, self.lpf_target]
+            .iter()
+            .filter(|target| target.is_some())
+            .count() as f32
+            * 0.5;
+        // Three biquad sections
+        let equalizer = if self.equalizer.is_some() { 1.5 } else { 0.0 };
+        // A logarithm and an exponential per sample
+        let compressor = if self.compressor.is_some() { 2.0 } else { 0.0 };
+        let sample_hold = match self.sample_hold {
+            SampleHold::Off => 0.0,
+            SampleHold::Pitch(_) | SampleHold::Filter(_) => 0.25,
+        };
+        // Envelope, slide, vibrato and repeat bookkeeping
+        let synthesized = config.effective_oversampling() as f32 * supersample
+            + 2.0
+            + slide
+            + ramps
+            + equalizer
+            + compressor
+            + sample_hold;
+
+        let resampling = if config.sample_rate == SAMPLE_RATE {
+            0.0
+        } else {
+            1.0
+        };
+        let dc_blocker = if config.dc_blocker && !legacy {
+            0.5
+        } else {
+            0.0
+        };
+        let clipping = match config.clip_mode {
+            ClipMode::Soft if !legacy => 2.0,
+            _ => 0.5,
+        };
+        let output = resampling + dc_blocker + clipping;
+
+        SAMPLE_RATE as f32 * synthesized + config.sample_rate as f32 * output
+    }
+}
@@ -0,0 +1,97 @@
+use crate::generator::HighLowPassFilter;
+use crate::{Generator, Sample, OVERSAMPLING, SAMPLE_RATE};
+
+impl Sample {
+    /// Returns `resolution` points of the amplitude envelope, evenly spaced over the length of
+    /// the sound, as `(seconds, amplitude)` pairs. Amplitude is between `0.0` and `1.0`, except
+    /// for punch in the sustain stage. No audio is rendered.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::Sample;
+    /// let curve = Sample::new().envelope_curve(64);
+    /// assert_eq!(curve.len(), 64);
+    /// assert_eq!(curve[63].1, 0.0);
+    /// ```
+    pub fn envelope_curve(&self, resolution: usize) -> Vec<(f32, f32)> {
+        self.curve(resolution, |generator| generator.envelope.volume())
+    }
+
+    /// Returns `resolution` points of the oscillator frequency, evenly spaced over the length
+    /// of the sound, as `(seconds, hz)` pairs. Includes slide, vibrato, arpeggio and repeats.
+    /// No audio is rendered.
+    pub fn pitch_curve(&self, resolution: usize) -> Vec<(f32, f32)> {
+        self.curve(resolution, |generator| {
+            (SAMPLE_RATE as usize * OVERSAMPLING) as f32
+                / generator.oscillator.current_period() as f32
+        })
+    }
+
+    /// Returns the magnitude response of the low and high pass filters at `resolution`
+    /// frequencies, logarithmically spaced from 20 Hz to half the sample rate, as `(hz, gain)`
+    /// pairs. Uses the cutoffs at the start of the sound, before `lpf_ramp` and `hpf_ramp`
+    /// move them.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::Sample;
+    /// let mut sample = Sample::new();
+    /// sample.lpf_freq = 0.3;
+    /// let response = sample.filter_response(32);
+    /// assert!(response[31].1 < response[16].1);
+    /// ```
+    pub fn filter_response(&self, resolution: usize) -> Vec<(f32, f32)> {
+        let low = 20f32.ln();
+        let high = (SAMPLE_RATE as f32 / 2.0).ln();
+        (0..resolution)
+            .map(|point| {
+                let t = if resolution > 1 {
+                    point as f32 / (resolution - 1) as f32
+                } else {
+                    0.0
+                };
+                let hz = (low + (high - low) * t).exp();
+                (hz, self.filter_gain(hz))
+            })
+            .collect()
+    }
+
+    /// Returns the linear gain of the low and high pass filters at `hz` at the start of the
+    /// sound. `lpf_freq` maps to a cutoff cubically and `hpf_freq` quadratically, so the
+    /// upper half of either range covers most of the audible effect.
+    pub fn filter_gain(&self, hz: f32) -> f32 {
+        let mut filter = HighLowPassFilter::new();
+        filter.reset(
+            self.lpf_resonance,
+            self.lpf_freq,
+            self.lpf_ramp,
+            self.hpf_freq,
+            self.hpf_ramp,
+        );
+        filter.response(f64::from(hz) / (SAMPLE_RATE as usize * OVERSAMPLING) as f64) as f32
+    }
+
+    fn curve<F: Fn(&Generator) -> f32>(&self, resolution: usize, value: F) -> Vec<(f32, f32)> {
+        let last = self.envelope_length().saturating_sub(1);
+        let mut generator = Generator::new(self.clone());
+        let mut advanced = 0;
+
+        (0..resolution)
+            .map(|point| {
+                let position = if resolution > 1 {
+                    point * last / (resolution - 1)
+                } else {
+                    0
+                };
+                // Sample `n` is generated after advancing `n + 1` times
+                while advanced <= position {
+                    generator.advance(0);
+                    advanced += 1;
+                }
+                (position as f32 / SAMPLE_RATE as f32, value(&generator))
+            })
+            .collect()
+    }
+}
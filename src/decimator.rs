@@ -0,0 +1,91 @@
+//! Reduction of the oscillator supersamples to the synthesis rate
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[cfg(feature = "serde")]
+use crate::generator::float_array;
+use crate::OVERSAMPLING;
+
+/// Filter taps per supersample of decimation
+const TAPS_PER_FACTOR: usize = 16;
+/// Filter taps at the largest decimation factor
+const MAX_TAPS: usize = TAPS_PER_FACTOR * OVERSAMPLING;
+
+/// Windowed sinc FIR low pass filter for `Decimation::Fir`, with its cutoff at half the rate
+/// after decimation
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Decimator {
+    len: usize,
+    #[cfg_attr(feature = "serde", serde(with = "float_array"))]
+    taps: [f32; MAX_TAPS],
+    #[cfg_attr(feature = "serde", serde(with = "float_array"))]
+    main: [f32; MAX_TAPS],
+    #[cfg_attr(feature = "serde", serde(with = "float_array"))]
+    side: [f32; MAX_TAPS],
+    position: usize,
+}
+
+impl Decimator {
+    /// Constructs a Decimator reducing the rate by `factor`, at most `OVERSAMPLING`
+    pub fn new(factor: usize) -> Decimator {
+        let len = TAPS_PER_FACTOR * factor.clamp(1, OVERSAMPLING);
+        let cutoff = 0.5 / factor as f32;
+        let center = (len - 1) as f32 / 2.0;
+        let mut taps = [0.0; MAX_TAPS];
+        for (n, tap) in taps.iter_mut().take(len).enumerate() {
+            let x = n as f32 - center;
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                (2.0 * PI * cutoff * x).sin() / (2.0 * PI * cutoff * x)
+            };
+            // Blackman window
+            let phase = 2.0 * PI * n as f32 / (len - 1) as f32;
+            *tap = sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos());
+        }
+        // Unity gain at DC
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
+
+        Decimator {
+            len,
+            taps,
+            main: [0.0; MAX_TAPS],
+            side: [0.0; MAX_TAPS],
+            position: 0,
+        }
+    }
+    /// Clears the history of past supersamples
+    pub fn clear(&mut self) {
+        self.main = [0.0; MAX_TAPS];
+        self.side = [0.0; MAX_TAPS];
+    }
+    /// Adds one supersample and its stereo side signal
+    pub fn push(&mut self, main: f32, side: f32) {
+        self.position = (self.position + 1) % self.len;
+        self.main[self.position] = main;
+        self.side[self.position] = side;
+    }
+    /// Returns the filtered signal, and the side signal if `stereo`, as of the latest `push`
+    pub fn output(&self, stereo: bool) -> (f32, f32) {
+        let side = if stereo {
+            self.convolve(&self.side)
+        } else {
+            0.0
+        };
+        (self.convolve(&self.main), side)
+    }
+    fn convolve(&self, history: &[f32; MAX_TAPS]) -> f32 {
+        // The taps are symmetric, so their order relative to the history doesn't matter
+        let (newer, older) = history[..self.len].split_at(self.position + 1);
+        older
+            .iter()
+            .chain(newer.iter())
+            .zip(self.taps.iter())
+            .map(|(value, tap)| value * tap)
+            .sum()
+    }
+}
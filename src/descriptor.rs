@@ -0,0 +1,81 @@
+use crate::{seed_or_random, Param, ParamRanges, Prng, Sample, WaveType};
+
+impl Sample {
+    /// Words understood by `Sample::from_descriptor`
+    pub const DESCRIPTOR_WORDS: [&'static str; 10] = [
+        "short", "long", "high", "low", "noisy", "tonal", "rising", "falling", "metallic", "soft",
+    ];
+
+    /// Constructs a new random sample matching a text description using optional random seed
+    /// (random if `None`).
+    ///
+    /// The description is a list of words from `Sample::DESCRIPTOR_WORDS`, each narrowing the
+    /// ranges the sample is randomized from. Words are case insensitive, other words are
+    /// ignored, and when words contradict each other the last one wins.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::Sample;
+    /// let sample = Sample::from_descriptor("short high metallic hit", Some(1));
+    /// assert!(sample.base_freq >= 0.6);
+    /// ```
+    pub fn from_descriptor(description: &str, seed: Option<u64>) -> Sample {
+        let mut ranges = ParamRanges::new();
+        ranges.wave_types = vec![
+            WaveType::Square,
+            WaveType::Sawtooth,
+            WaveType::Sine,
+            WaveType::Triangle,
+        ];
+        ranges.set(Param::BaseFreq, 0.3, 0.6);
+        ranges.set(Param::Duty, 0.0, 0.6);
+        ranges.set(Param::EnvAttack, 0.0, 0.0);
+        ranges.set(Param::EnvSustain, 0.1, 0.3);
+        ranges.set(Param::EnvDecay, 0.2, 0.5);
+        ranges.set(Param::EnvPunch, 0.0, 0.3);
+
+        for word in description
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+        {
+            match word.as_str() {
+                "short" => {
+                    ranges.set(Param::EnvSustain, 0.0, 0.1);
+                    ranges.set(Param::EnvDecay, 0.05, 0.2);
+                }
+                "long" => {
+                    ranges.set(Param::EnvSustain, 0.3, 0.6);
+                    ranges.set(Param::EnvDecay, 0.4, 0.8);
+                }
+                "high" => ranges.set(Param::BaseFreq, 0.6, 0.9),
+                "low" => ranges.set(Param::BaseFreq, 0.1, 0.3),
+                "noisy" => ranges.wave_types = vec![WaveType::Noise],
+                "tonal" => {
+                    ranges.wave_types = vec![
+                        WaveType::Square,
+                        WaveType::Sawtooth,
+                        WaveType::Sine,
+                        WaveType::Triangle,
+                    ]
+                }
+                "rising" => ranges.set(Param::FreqRamp, 0.1, 0.4),
+                "falling" => ranges.set(Param::FreqRamp, -0.4, -0.1),
+                "metallic" => {
+                    ranges.set(Param::LpfResonance, 0.5, 0.9);
+                    ranges.set(Param::LpfFreq, 0.6, 0.9);
+                    ranges.set(Param::PhaOffset, 0.02, 0.15);
+                }
+                "soft" => {
+                    ranges.set(Param::EnvAttack, 0.05, 0.2);
+                    ranges.set(Param::EnvPunch, 0.0, 0.0);
+                    ranges.set(Param::LpfFreq, 0.2, 0.5);
+                }
+                _ => {}
+            }
+        }
+
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Sample::random_in(&ranges, rng)
+    }
+}
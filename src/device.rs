@@ -0,0 +1,474 @@
+//! Playing Generators on `cpal` output devices
+//!
+//! Generators produce mono `f32` samples at `GeneratorConfig::sample_rate`, 44.1 kHz by
+//! default, but many devices refuse that format. `best_config` picks the closest format a
+//! device supports, and `OutputAdapter` converts the generator output to it, resampling and
+//! copying it to every channel. `Player` does all of this on a background thread.
+//!
+//! # Examples
+//!
+//! ``` rust no_run
+//! use cpal::traits::{EventLoopTrait, HostTrait};
+//! use sfxr::device::{best_config, OutputAdapter};
+//! use sfxr::{Generator, Sample};
+//!
+//! let host = cpal::default_host();
+//! let event_loop = host.event_loop();
+//! let device = host.default_output_device().expect("no output device");
+//! let format = best_config(&device).expect("no usable output format");
+//! let stream = event_loop.build_output_stream(&device, &format).unwrap();
+//! event_loop.play_stream(stream).unwrap();
+//!
+//! let mut adapter = OutputAdapter::new(&format);
+//! let mut generator = Generator::new(Sample::pickup(None));
+//! event_loop.run(move |_, data| {
+//!     if let Ok(cpal::StreamData::Output { mut buffer }) = data {
+//!         adapter.fill(Some(&mut generator), &mut buffer);
+//!     }
+//! });
+//! ```
+
+use cpal::traits::{DeviceTrait, EventLoopTrait, HostTrait};
+use cpal::{Format, SampleFormat, SampleRate, StreamData, UnknownTypeOutputBuffer};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{mpsc, Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Error, Generator, SAMPLE_RATE};
+
+/// Whether the audio thread of a Player can leave `EventLoop::run`, which never returns, by
+/// unwinding. Not where the backend calls back from its own threads through C code, which
+/// unwinding would abort.
+const CAN_STOP: bool = cfg!(all(
+    panic = "unwind",
+    not(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "emscripten"
+    ))
+));
+
+/// How often the housekeeping thread of a Player completes and drops finished sounds
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Returns the output format of `device` closest to the generator output: 44.1 kHz if
+/// supported, then `f32` samples over `i16` and `u16`, then the fewest channels. Falls back to
+/// the default output format if the supported formats can't be listed. Returns `None` if the
+/// device has no output format.
+pub fn best_config(device: &cpal::Device) -> Option<Format> {
+    let supported = match device.supported_output_formats() {
+        Ok(formats) => formats,
+        Err(_) => return device.default_output_format().ok(),
+    };
+
+    supported
+        .filter(|format| format.channels > 0)
+        .map(|format| {
+            let rate = SAMPLE_RATE.clamp(format.min_sample_rate.0, format.max_sample_rate.0);
+            Format {
+                channels: format.channels,
+                sample_rate: SampleRate(rate),
+                data_type: format.data_type,
+            }
+        })
+        .max_by_key(|format| {
+            let data_type = match format.data_type {
+                SampleFormat::F32 => 2,
+                SampleFormat::I16 => 1,
+                SampleFormat::U16 => 0,
+            };
+            (
+                format.sample_rate.0 == SAMPLE_RATE,
+                data_type,
+                std::cmp::Reverse(format.channels),
+            )
+        })
+        .or_else(|| device.default_output_format().ok())
+}
+
+/// Converts mono Generator output to the format of an output stream
+///
+/// Resamples linearly from the generator's `GeneratorConfig::sample_rate` to the stream rate,
+/// writes the same signal to every channel and converts it to the stream sample type. Doesn't
+/// allocate.
+#[derive(Clone, Debug)]
+pub struct OutputAdapter {
+    channels: usize,
+    sample_rate: u32,
+    position: f64,
+    previous: f32,
+    next: f32,
+}
+
+impl OutputAdapter {
+    /// Constructs an adapter for a stream in `format`
+    pub fn new(format: &Format) -> OutputAdapter {
+        assert!(format.channels > 0, "format must have channels");
+        assert!(
+            format.sample_rate.0 > 0,
+            "format sample rate must be greater than zero"
+        );
+        OutputAdapter {
+            channels: usize::from(format.channels),
+            sample_rate: format.sample_rate.0,
+            position: 0.0,
+            previous: 0.0,
+            next: 0.0,
+        }
+    }
+    /// Fills `buffer` with the output of `generator`, or with silence if it is `None`
+    pub fn fill(
+        &mut self,
+        generator: Option<&mut Generator>,
+        buffer: &mut UnknownTypeOutputBuffer<'_>,
+    ) {
+        match generator {
+            Some(generator) => {
+                let rate = generator.config().sample_rate;
+                self.fill_with(rate, || generator.tick(), buffer);
+            }
+            None => self.fill_with(self.sample_rate, || 0.0, buffer),
+        }
+    }
+    /// Fills `buffer` with samples at `source_rate` Hz returned by `next_sample`
+    pub fn fill_with<F: FnMut() -> f32>(
+        &mut self,
+        source_rate: u32,
+        next_sample: F,
+        buffer: &mut UnknownTypeOutputBuffer<'_>,
+    ) {
+        match buffer {
+            UnknownTypeOutputBuffer::F32(buffer) => self.write(source_rate, next_sample, buffer),
+            UnknownTypeOutputBuffer::I16(buffer) => self.write(source_rate, next_sample, buffer),
+            UnknownTypeOutputBuffer::U16(buffer) => self.write(source_rate, next_sample, buffer),
+        }
+    }
+    fn write<T: cpal::Sample, F: FnMut() -> f32>(
+        &mut self,
+        source_rate: u32,
+        mut next_sample: F,
+        buffer: &mut [T],
+    ) {
+        let step = f64::from(source_rate) / f64::from(self.sample_rate);
+        for frame in buffer.chunks_mut(self.channels) {
+            let value = if (step - 1.0).abs() < f64::EPSILON {
+                next_sample()
+            } else {
+                self.position += step;
+                while self.position >= 1.0 {
+                    self.position -= 1.0;
+                    self.previous = self.next;
+                    self.next = next_sample();
+                }
+                self.previous + (self.next - self.previous) * self.position as f32
+            };
+            let value = T::from(&value);
+            frame.iter_mut().for_each(|v| *v = value);
+        }
+    }
+}
+
+/// Plays Generators on the default output device from a background thread
+///
+/// Playing sounds are resampled from their `GeneratorConfig::sample_rate` to the device rate,
+/// summed and clipped to `-1.0..=1.0`, and the mix is converted to the device format with an
+/// `OutputAdapter`.
+///
+/// The audio thread only moves finished sounds aside. A housekeeping thread drops them and
+/// completes their handles every 10 ms, so the audio callback never frees memory or wakes
+/// tasks.
+///
+/// # Examples
+///
+/// Sequencing sounds from async code
+///
+/// ``` rust no_run
+/// use sfxr::device::Player;
+/// use sfxr::{Generator, Sample};
+///
+/// async fn fanfare(player: &Player) {
+///     for seed in 0..3 {
+///         player.play(Generator::new(Sample::pickup(Some(seed)))).finished().await;
+///     }
+/// }
+/// ```
+pub struct Player {
+    shared: Arc<Mutex<Shared>>,
+    thread: Option<JoinHandle<()>>,
+    housekeeping: Option<JoinHandle<()>>,
+}
+
+/// State shared with the audio and housekeeping threads
+struct Shared {
+    voices: Vec<Voice>,
+    /// Voices the audio thread found finished, for the housekeeping thread to complete and
+    /// drop. Has room for all `voices`, so moving them here doesn't allocate.
+    finished: Vec<Voice>,
+    /// Set when the Player is dropped, to stop the audio and housekeeping threads
+    stopping: bool,
+}
+
+/// Unwinds the audio thread out of `EventLoop::run`
+struct Stopped;
+
+impl Shared {
+    /// Fills `buffer` of a stream at `sample_rate` Hz with the mix of the voices
+    fn fill(
+        &mut self,
+        adapter: &mut OutputAdapter,
+        sample_rate: u32,
+        buffer: &mut UnknownTypeOutputBuffer<'_>,
+    ) {
+        let voices = &mut self.voices;
+        adapter.fill_with(
+            sample_rate,
+            || {
+                let sum: f32 = voices.iter_mut().map(|voice| voice.tick(sample_rate)).sum();
+                sum.clamp(-1.0, 1.0)
+            },
+            buffer,
+        );
+        let mut i = 0;
+        while i < self.voices.len() {
+            if self.voices[i].generator.is_finished() {
+                self.finished.push(self.voices.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Completes and drops the voices the audio thread found finished, until the Player stops
+fn housekeeping(shared: &Mutex<Shared>) {
+    loop {
+        let (finished, stopping) = {
+            let mut shared = shared.lock().unwrap();
+            let finished: Vec<Voice> = shared.finished.drain(..).collect();
+            (finished, shared.stopping)
+        };
+        for voice in finished {
+            voice.completion.complete();
+        }
+        if stopping {
+            return;
+        }
+        thread::park_timeout(HOUSEKEEPING_INTERVAL);
+    }
+}
+
+/// A sound played by a Player
+struct Voice {
+    generator: Generator,
+    completion: Arc<Completion>,
+    position: f64,
+    previous: f32,
+    next: f32,
+}
+
+impl Voice {
+    /// Returns the next output sample at `sample_rate` Hz, resampling linearly from the
+    /// generator rate like `OutputAdapter`
+    fn tick(&mut self, sample_rate: u32) -> f32 {
+        let source_rate = self.generator.config().sample_rate;
+        if source_rate == sample_rate {
+            return self.generator.tick();
+        }
+        self.position += f64::from(source_rate) / f64::from(sample_rate);
+        while self.position >= 1.0 {
+            self.position -= 1.0;
+            self.previous = self.next;
+            self.next = self.generator.tick();
+        }
+        self.previous + (self.next - self.previous) * self.position as f32
+    }
+}
+
+impl Player {
+    /// Opens the default output device and starts playing silence on it. Fails if there is no
+    /// output device or it can't be opened.
+    ///
+    /// Dropping the Player stops its sounds, closes the device and waits for the audio thread
+    /// to end. On macOS, iOS and the web, and when panics abort, the audio thread can't be
+    /// stopped and keeps playing silence.
+    pub fn new() -> Result<Player, Error> {
+        let shared = Arc::new(Mutex::new(Shared {
+            voices: Vec::new(),
+            finished: Vec::new(),
+            stopping: false,
+        }));
+        let thread_shared = shared.clone();
+        let (ready_sender, ready) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            let host = cpal::default_host();
+            let event_loop = host.event_loop();
+            let format = host
+                .default_output_device()
+                .ok_or("no output device")
+                .and_then(|device| {
+                    let format = best_config(&device).ok_or("no usable output format")?;
+                    let stream = event_loop
+                        .build_output_stream(&device, &format)
+                        .map_err(|_| "opening the output stream failed")?;
+                    event_loop
+                        .play_stream(stream)
+                        .map_err(|_| "starting the output stream failed")?;
+                    Ok(format)
+                });
+            let format = match format {
+                Ok(format) => format,
+                Err(e) => {
+                    let _ = ready_sender.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_sender.send(Ok(()));
+
+            let mut adapter = OutputAdapter::new(&format);
+            let sample_rate = format.sample_rate.0;
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+                event_loop.run(move |_, data| {
+                    let mut shared = thread_shared.lock().unwrap();
+                    // Also stop when the stream failed, as it won't call back again
+                    let stop = match data {
+                        Ok(StreamData::Output { mut buffer }) => {
+                            shared.fill(&mut adapter, sample_rate, &mut buffer);
+                            shared.stopping
+                        }
+                        _ => true,
+                    };
+                    if stop && CAN_STOP {
+                        drop(shared);
+                        panic::resume_unwind(Box::new(Stopped));
+                    }
+                })
+            }));
+        });
+
+        match ready.recv() {
+            Ok(Ok(())) => {
+                let housekeeping_shared = shared.clone();
+                let housekeeping = thread::spawn(move || housekeeping(&housekeeping_shared));
+                Ok(Player {
+                    shared,
+                    thread: Some(thread).filter(|_| CAN_STOP),
+                    housekeeping: Some(housekeeping),
+                })
+            }
+            Ok(Err(e)) => Err(Error::Unsupported(e)),
+            Err(_) => Err(Error::Unsupported("the audio thread failed")),
+        }
+    }
+    /// Starts playing `generator` from its current position. The returned handle tracks the
+    /// sound until its envelope ends.
+    pub fn play(&self, generator: Generator) -> PlaybackHandle {
+        let completion = Arc::new(Completion::default());
+        let mut shared = self.shared.lock().unwrap();
+        shared.voices.push(Voice {
+            generator,
+            completion: completion.clone(),
+            position: 0.0,
+            previous: 0.0,
+            next: 0.0,
+        });
+        let playing = shared.voices.len();
+        shared.finished.reserve(playing);
+        drop(shared);
+        PlaybackHandle {
+            shared: self.shared.clone(),
+            completion,
+        }
+    }
+    /// Stops all playing sounds
+    pub fn stop_all(&self) {
+        for voice in self.shared.lock().unwrap().voices.drain(..) {
+            voice.completion.complete();
+        }
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.stop_all();
+        self.shared.lock().unwrap().stopping = true;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Some(housekeeping) = self.housekeeping.take() {
+            housekeeping.thread().unpark();
+            let _ = housekeeping.join();
+        }
+    }
+}
+
+/// A sound started with `Player::play`
+pub struct PlaybackHandle {
+    shared: Arc<Mutex<Shared>>,
+    completion: Arc<Completion>,
+}
+
+impl PlaybackHandle {
+    /// Returns `true` once the sound has ended or was stopped
+    pub fn is_finished(&self) -> bool {
+        self.completion.state.lock().unwrap().0
+    }
+    /// Stops the sound
+    pub fn stop(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        shared
+            .voices
+            .retain(|voice| !Arc::ptr_eq(&voice.completion, &self.completion));
+        self.completion.complete();
+    }
+    /// Returns a future that resolves when the sound has ended or was stopped, or when the
+    /// Player is dropped
+    pub fn finished(&self) -> Finished {
+        Finished {
+            completion: self.completion.clone(),
+        }
+    }
+}
+
+/// Future returned by `PlaybackHandle::finished`
+#[derive(Clone)]
+pub struct Finished {
+    completion: Arc<Completion>,
+}
+
+impl Future for Finished {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.completion.state.lock().unwrap();
+        if state.0 {
+            Poll::Ready(())
+        } else {
+            if !state.1.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.1.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+}
+
+/// Whether a sound has finished, and the tasks to wake when it does
+#[derive(Default)]
+struct Completion {
+    state: Mutex<(bool, Vec<Waker>)>,
+}
+
+impl Completion {
+    fn complete(&self) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.0 = true;
+            std::mem::take(&mut state.1)
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
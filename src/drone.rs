@@ -0,0 +1,143 @@
+//! Long, slowly evolving sounds for ambient beds
+
+use crate::{Generator, Prng, Sample, SampleHold};
+
+/// Output samples between updates of the random walks
+const BLOCK: usize = 64;
+
+/// How far and how fast the parameters of a Drone wander
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct DroneConfig {
+    /// Largest pitch deviation in semitones. Default is `2.0`.
+    pub pitch_semitones: f32,
+    /// Largest low pass cutoff deviation in octaves. Default is `1.0`.
+    pub cutoff_octaves: f32,
+    /// Largest square duty cycle deviation in `Sample::duty` units. Default is `0.2`.
+    pub duty: f32,
+    /// Typical time in seconds for a parameter to wander across its whole range. Must be
+    /// greater than `0.0`. Default is `60.0`.
+    pub drift_secs: f32,
+    /// Seed of the random walks. Default is `0`.
+    pub seed: u64,
+}
+
+#[allow(clippy::new_without_default)]
+impl DroneConfig {
+    /// Constructs a DroneConfig with gentle default drift
+    pub const fn new() -> DroneConfig {
+        DroneConfig {
+            pitch_semitones: 2.0,
+            cutoff_octaves: 1.0,
+            duty: 0.2,
+            drift_secs: 60.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Plays a Sample indefinitely while its pitch, low pass cutoff and duty cycle random walk
+///
+/// The envelope is held at full volume once it reaches the sustain stage, until `release`
+/// lets it decay. `Sample::sample_hold` is turned off, as the walks take its place. Samples
+/// with `NoiseMode::Continuous` noise and a `repeat_speed` for pulsing make good beds.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Drone, DroneConfig, Sample, WaveType};
+/// let mut pad = Sample::new();
+/// pad.wave_type = WaveType::Sawtooth;
+/// pad.base_freq = 0.2;
+/// pad.lpf_freq = 0.4;
+/// pad.unison_voices = 4;
+/// pad.unison_detune = 0.3;
+///
+/// let mut drone = Drone::new(pad, DroneConfig { drift_secs: 20.0, ..DroneConfig::new() });
+/// let mut buffer = [0.0; 4_096];
+/// for _ in 0..100 {
+///     drone.generate(&mut buffer);
+/// }
+/// drone.release();
+/// ```
+pub struct Drone {
+    generator: Generator,
+    config: DroneConfig,
+    rng: Prng,
+    /// Positions of the pitch, cutoff and duty walks, between `-1.0` and `1.0`
+    walks: [f32; 3],
+    block_left: usize,
+}
+
+impl Drone {
+    /// Constructs a Drone playing `sample`
+    pub fn new(sample: Sample, config: DroneConfig) -> Drone {
+        Drone::from_generator(Generator::new(sample), config)
+    }
+    /// Constructs a Drone playing `generator` from its current position, keeping its volume,
+    /// options and config
+    pub fn from_generator(mut generator: Generator, config: DroneConfig) -> Drone {
+        assert!(
+            config.drift_secs > 0.0,
+            "drift_secs must be greater than zero"
+        );
+        generator.sample.sample_hold = SampleHold::Off;
+        generator.set_envelope_hold(true);
+        Drone {
+            generator,
+            config,
+            rng: Prng::new(config.seed),
+            walks: [0.0; 3],
+            block_left: 0,
+        }
+    }
+    /// Fills `buffer` with the next samples of the drone
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        let mut rest = buffer;
+        while !rest.is_empty() {
+            if self.block_left == 0 {
+                self.step();
+                self.block_left = BLOCK;
+            }
+            let len = self.block_left.min(rest.len());
+            let (block, tail) = rest.split_at_mut(len);
+            self.generator.generate(block);
+            self.block_left -= len;
+            rest = tail;
+        }
+    }
+    /// Lets the envelope continue to its decay, after which the drone is silent
+    pub fn release(&mut self) {
+        self.generator.set_envelope_hold(false);
+    }
+    /// Returns the Generator playing the drone
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+    /// Returns the Generator playing the drone, for example to change its volume
+    pub fn generator_mut(&mut self) -> &mut Generator {
+        &mut self.generator
+    }
+    /// Moves the random walks by one block and applies them
+    fn step(&mut self) {
+        let block_secs = BLOCK as f32 / self.generator.config().sample_rate as f32;
+        // Uniform steps with the variance to cross the range of `2.0` in about `drift_secs`
+        let size = 2.0 * (3.0 * block_secs / self.config.drift_secs).sqrt();
+        for walk in self.walks.iter_mut() {
+            let mut value = *walk + (self.rng.next_f32() * 2.0 - 1.0) * size;
+            // Reflect at the ends of the range
+            if value > 1.0 {
+                value = 2.0 - value;
+            } else if value < -1.0 {
+                value = -2.0 - value;
+            }
+            *walk = value.clamp(-1.0, 1.0);
+        }
+
+        let [pitch, cutoff, duty] = self.walks;
+        self.generator.set_modulation(
+            2f64.powf(f64::from(pitch * self.config.pitch_semitones) / 12.0),
+            2f32.powf(cutoff * self.config.cutoff_octaves),
+            duty * self.config.duty,
+        );
+    }
+}
@@ -0,0 +1,214 @@
+//! Simple editing operations on rendered sound data
+//!
+//! These work on mono buffers such as those produced by `Generator::render_to_vec`, for
+//! example to vary pre-rendered sounds at load time without regenerating them.
+//!
+//! # Examples
+//!
+//! A reversed explosion makes a "swoosh" leading into an impact
+//!
+//! ``` rust
+//! use sfxr::{edit, Generator, Sample};
+//! let mut buffer = Generator::new(Sample::explosion(None)).render_to_vec();
+//! edit::reverse(&mut buffer);
+//! edit::fade_in(&mut buffer, 2_000);
+//! let trimmed = edit::trim_silence(&buffer, 0.001);
+//! ```
+
+/// Returns the part of `buffer` between the first and last samples whose magnitude is at least
+/// `threshold`. Returns an empty slice if the whole buffer is below `threshold`.
+pub fn trim_silence(buffer: &[f32], threshold: f32) -> &[f32] {
+    let loud = |v: &f32| v.abs() >= threshold;
+    match buffer.iter().position(loud) {
+        Some(start) => {
+            let end = buffer.iter().rposition(loud).unwrap_or(start) + 1;
+            &buffer[start..end]
+        }
+        None => &buffer[..0],
+    }
+}
+
+/// Fades in the first `samples` samples of `buffer` linearly from silence
+pub fn fade_in(buffer: &mut [f32], samples: usize) {
+    let samples = samples.min(buffer.len());
+    buffer[..samples]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, v)| *v *= i as f32 / samples as f32);
+}
+
+/// Fades out the last `samples` samples of `buffer` linearly to silence
+pub fn fade_out(buffer: &mut [f32], samples: usize) {
+    let samples = samples.min(buffer.len());
+    let start = buffer.len() - samples;
+    buffer[start..]
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, v)| *v *= (samples - i - 1) as f32 / samples as f32);
+}
+
+/// Reverses `buffer` in place
+pub fn reverse(buffer: &mut [f32]) {
+    buffer.reverse();
+}
+
+/// Concatenates `parts` into a single buffer, overlapping consecutive parts by
+/// `crossfade_samples` samples with a linear crossfade. The overlap is shortened for parts
+/// shorter than `crossfade_samples`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::edit;
+/// let charge = [0.5; 100];
+/// let fire = [1.0; 100];
+/// let joined = edit::concat_with_crossfade(&[&charge, &fire], 10);
+/// assert_eq!(joined.len(), 190);
+/// ```
+pub fn concat_with_crossfade(parts: &[&[f32]], crossfade_samples: usize) -> Vec<f32> {
+    let mut result: Vec<f32> = Vec::with_capacity(parts.iter().map(|part| part.len()).sum());
+
+    for part in parts.iter() {
+        let overlap = crossfade_samples.min(result.len()).min(part.len());
+        let start = result.len() - overlap;
+
+        result[start..]
+            .iter_mut()
+            .zip(part[..overlap].iter())
+            .enumerate()
+            .for_each(|(i, (v, &next))| {
+                let t = (i + 1) as f32 / (overlap + 1) as f32;
+                *v = *v * (1.0 - t) + next * t;
+            });
+        result.extend_from_slice(&part[overlap..]);
+    }
+
+    result
+}
+
+/// Returns the amplitude envelope of `buffer`. The envelope follows rising peaks immediately
+/// and falls exponentially by 60 dB over `release_samples` samples.
+pub fn envelope(buffer: &[f32], release_samples: usize) -> Vec<f32> {
+    let release = release_coefficient(release_samples);
+    let mut level = 0.0;
+    buffer
+        .iter()
+        .map(|v| {
+            level = v.abs().max(level * release);
+            level
+        })
+        .collect()
+}
+
+/// Per sample multiplier of a level falling by 60 dB over `release_samples` samples
+pub(crate) fn release_coefficient(release_samples: usize) -> f32 {
+    if release_samples == 0 {
+        0.0
+    } else {
+        0.001f32.powf(1.0 / release_samples as f32)
+    }
+}
+
+/// Lowers the level of `main` while `trigger_envelope` is high, like a sidechain compressor,
+/// so that for example music dips under a big explosion. The gain of each sample is
+/// `1.0 - amount * level`, with the envelope level clamped to `0.0..=1.0`. Samples past the end
+/// of `trigger_envelope` are unchanged.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{edit, Generator, Sample};
+/// let explosion = Generator::new(Sample::explosion(Some(1))).render_to_vec();
+/// let mut music = vec![0.5; explosion.len()];
+/// edit::duck(&mut music, &edit::envelope(&explosion, 22_050), 0.8);
+/// assert!(music[100] < 0.5);
+/// ```
+pub fn duck(main: &mut [f32], trigger_envelope: &[f32], amount: f32) {
+    main.iter_mut()
+        .zip(trigger_envelope.iter())
+        .for_each(|(v, level)| *v *= 1.0 - amount * level.clamp(0.0, 1.0));
+}
+
+/// Resamples `buffer` so that it plays back `ratio` times faster, changing both pitch and
+/// duration like a tape played at a different speed. Uses linear interpolation.
+pub fn resample(buffer: &[f32], ratio: f32) -> Vec<f32> {
+    assert!(ratio > 0.0, "ratio must be greater than zero");
+    let length = (buffer.len() as f32 / ratio) as usize;
+    (0..length)
+        .map(|i| {
+            let position = i as f32 * ratio;
+            let index = position as usize;
+            let t = position - index as f32;
+            let a = buffer[index];
+            let b = buffer.get(index + 1).copied().unwrap_or(a);
+            a + (b - a) * t
+        })
+        .collect()
+}
+
+/// Changes the duration of `buffer` by `factor` without changing its pitch. A `factor` of `2.0`
+/// makes the sound twice as long.
+///
+/// Uses waveform-similarity overlap-add: each windowed frame is taken from near its nominal
+/// position in the input, at the offset that best continues the previous frame, which avoids
+/// most of the phase cancellation of plain overlap-add on tonal sounds.
+pub fn time_stretch(buffer: &[f32], factor: f32) -> Vec<f32> {
+    const WINDOW: usize = 1_024;
+    const SYNTHESIS_HOP: usize = WINDOW / 4;
+    const TOLERANCE: usize = 128;
+
+    assert!(factor > 0.0, "factor must be greater than zero");
+    let length = (buffer.len() as f32 * factor) as usize;
+    let analysis_hop = SYNTHESIS_HOP as f32 / factor;
+    let at = |i: usize| buffer.get(i).copied().unwrap_or(0.0);
+
+    let window: Vec<f32> = (0..WINDOW)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / WINDOW as f32).cos())
+        .collect();
+
+    let mut result = vec![0.0; length + WINDOW];
+    let mut weights = vec![0.0; length + WINDOW];
+    let mut previous = 0;
+
+    for frame in 0..=length / SYNTHESIS_HOP {
+        let nominal = (frame as f32 * analysis_hop) as usize;
+        let input = if frame == 0 {
+            nominal
+        } else {
+            let continuation = previous + SYNTHESIS_HOP;
+            (nominal.saturating_sub(TOLERANCE)..=nominal + TOLERANCE)
+                .map(|candidate| {
+                    let similarity: f32 = (0..WINDOW / 2)
+                        .map(|i| at(continuation + i) * at(candidate + i))
+                        .sum();
+                    (candidate, similarity)
+                })
+                .fold(
+                    (nominal, f32::MIN),
+                    |best, c| if c.1 > best.1 { c } else { best },
+                )
+                .0
+        };
+        previous = input;
+
+        let output = frame * SYNTHESIS_HOP;
+        for (i, w) in window.iter().enumerate() {
+            result[output + i] += at(input + i) * w;
+            weights[output + i] += w;
+        }
+    }
+
+    result.truncate(length);
+    result
+        .iter_mut()
+        .zip(weights.iter())
+        .filter(|(_, &w)| w > 1.0e-3)
+        .for_each(|(v, w)| *v /= w);
+    result
+}
+
+/// Shifts the pitch of `buffer` by `semitones` without changing its duration
+pub fn pitch_shift(buffer: &[f32], semitones: f32) -> Vec<f32> {
+    let ratio = 2f32.powf(semitones / 12.0);
+    time_stretch(&resample(buffer, ratio), ratio)
+}
@@ -0,0 +1,167 @@
+//! Three band equalizer at the end of the synthesis chain
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::generator::Filter;
+use crate::SAMPLE_RATE;
+
+/// Largest boost or cut of an Equalizer band in dB
+const MAX_GAIN_DB: f32 = 24.0;
+
+/// Settings of a three band equalizer: a low shelf, a mid peak and a high shelf
+///
+/// Applied to the synthesized sound after the phaser, to brighten or fatten a Sample without
+/// changing its other fields. Gains and frequencies are clamped to `-24.0..=24.0` dB and
+/// `20.0..=20_000.0` Hz when played.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Equalizer, Sample};
+/// let mut explosion = Sample::explosion(Some(7));
+/// explosion.equalizer = Some(Equalizer {
+///     low_gain_db: 6.0,
+///     high_gain_db: -3.0,
+///     ..Equalizer::new()
+/// });
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Equalizer {
+    /// Gain of the low shelf in dB. Default is `0.0`.
+    pub low_gain_db: f32,
+    /// Corner frequency of the low shelf in Hz. Default is `200.0`.
+    pub low_hz: f32,
+    /// Gain of the mid peak in dB. Default is `0.0`.
+    pub mid_gain_db: f32,
+    /// Center frequency of the mid peak in Hz. Default is `1_000.0`.
+    pub mid_hz: f32,
+    /// Width of the mid peak as its quality factor, higher is narrower. Must be greater than
+    /// `0.0`. Default is `0.7`.
+    pub mid_q: f32,
+    /// Gain of the high shelf in dB. Default is `0.0`.
+    pub high_gain_db: f32,
+    /// Corner frequency of the high shelf in Hz. Default is `5_000.0`.
+    pub high_hz: f32,
+}
+
+#[allow(clippy::new_without_default)]
+impl Equalizer {
+    /// Constructs a flat Equalizer that leaves the sound unchanged
+    pub const fn new() -> Equalizer {
+        Equalizer {
+            low_gain_db: 0.0,
+            low_hz: 200.0,
+            mid_gain_db: 0.0,
+            mid_hz: 1_000.0,
+            mid_q: 0.7,
+            high_gain_db: 0.0,
+            high_hz: 5_000.0,
+        }
+    }
+}
+
+/// Second order IIR section in direct form I, with coefficients normalized by `a0`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+/// Filter shapes from the Audio EQ Cookbook by Robert Bristow-Johnson
+enum Shape {
+    LowShelf,
+    Peak(f32),
+    HighShelf,
+}
+
+impl Biquad {
+    fn new(shape: Shape, gain_db: f32, hz: f32) -> Biquad {
+        let a = 10f32.powf(gain_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB) / 40.0);
+        let w = 2.0 * PI * hz.clamp(20.0, 20_000.0) / SAMPLE_RATE as f32;
+        let (cos, sin) = (w.cos(), w.sin());
+
+        let (b, a) = match shape {
+            Shape::Peak(q) => {
+                let alpha = sin / (2.0 * q.max(0.01));
+                (
+                    [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                    [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+                )
+            }
+            Shape::LowShelf | Shape::HighShelf => {
+                // Shelf slope of 1, the steepest without overshoot
+                let alpha = sin / 2.0 * 2f32.sqrt();
+                let root = 2.0 * a.sqrt() * alpha;
+                let sign = if let Shape::LowShelf = shape {
+                    1.0
+                } else {
+                    -1.0
+                };
+                let (p, m) = (a + 1.0, a - 1.0);
+                (
+                    [
+                        a * (p - sign * m * cos + root),
+                        sign * 2.0 * a * (m - sign * p * cos),
+                        a * (p - sign * m * cos - root),
+                    ],
+                    [
+                        p + sign * m * cos + root,
+                        -sign * 2.0 * (m + sign * p * cos),
+                        p + sign * m * cos - root,
+                    ],
+                )
+            }
+        };
+
+        Biquad {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+}
+
+impl Filter for Biquad {
+    fn filter(&mut self, sample: f32) -> f32 {
+        let result = self.b[0] * sample + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [sample, self.x[0]];
+        self.y = [result, self.y[0]];
+        result
+    }
+}
+
+/// Equalizer state during generation, running at `SAMPLE_RATE`
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct EqualizerFilter {
+    bands: [Biquad; 3],
+}
+
+impl EqualizerFilter {
+    pub fn new(eq: &Equalizer) -> EqualizerFilter {
+        EqualizerFilter {
+            bands: [
+                Biquad::new(Shape::LowShelf, eq.low_gain_db, eq.low_hz),
+                Biquad::new(Shape::Peak(eq.mid_q), eq.mid_gain_db, eq.mid_hz),
+                Biquad::new(Shape::HighShelf, eq.high_gain_db, eq.high_hz),
+            ],
+        }
+    }
+}
+
+impl Filter for EqualizerFilter {
+    fn filter(&mut self, sample: f32) -> f32 {
+        self.bands
+            .iter_mut()
+            .fold(sample, |sample, band| band.filter(sample))
+    }
+}
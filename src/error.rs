@@ -0,0 +1,81 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use crate::RangeError;
+
+/// Errors returned by fallible operations in this crate
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A value was outside its valid range
+    Invalid(RangeError),
+    /// Data could not be parsed
+    Parse {
+        /// Name of the data format, like `"JSON"`
+        format: &'static str,
+        /// Description of the problem
+        message: String,
+    },
+    /// Reading or writing data failed
+    Io(io::Error),
+    /// Encoding audio data failed
+    Encode(String),
+    /// The operation is not supported, for example because a feature is disabled
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Invalid(e) => write!(f, "invalid value: {}", e),
+            Error::Parse { format, message } => write!(f, "invalid {} data: {}", format, message),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Encode(message) => write!(f, "encoding failed: {}", message),
+            Error::Unsupported(what) => write!(f, "unsupported: {}", what),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Invalid(e) => Some(e),
+            Error::Io(e) => Some(e),
+            Error::Parse { .. } | Error::Encode(_) | Error::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<RangeError> for Error {
+    fn from(e: RangeError) -> Error {
+        Error::Invalid(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Error {
+        if e.is_io() {
+            Error::Io(e.into())
+        } else {
+            Error::Parse {
+                format: "JSON",
+                message: e.to_string(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "opus")]
+impl From<audiopus::Error> for Error {
+    fn from(e: audiopus::Error) -> Error {
+        Error::Encode(e.to_string())
+    }
+}
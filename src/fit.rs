@@ -0,0 +1,129 @@
+//! Experimental approximation of existing sounds with Sample parameters
+//!
+//! The fitting is a coarse random search comparing loudness, brightness and zero crossing
+//! rate over time between the target and candidate renders. It finds Samples in the right
+//! ballpark, which can then be refined by hand, rather than exact reproductions.
+
+use rand::Rng;
+
+use crate::analysis::{frame_brightness, rms};
+use crate::SAMPLE_RATE;
+use crate::{edit, rand_bool, rand_f64, Category, Generator, Param, ParamRanges, Prng, Sample};
+
+/// Length of the analysis frames in samples
+const FRAME: usize = 1_024;
+
+/// Finds a Sample whose sound approximates `target`, recorded at `sample_rate` Hz.
+///
+/// Equivalent to `approximate_with(target, sample_rate, 400, 0)`.
+///
+/// # Examples
+///
+/// ``` rust,no_run
+/// use sfxr::{fit, Generator, Sample};
+/// let target = Generator::new(Sample::laser(Some(3))).render_to_vec();
+/// let sample = fit::approximate(&target, 44_100);
+/// ```
+pub fn approximate(target: &[f32], sample_rate: u32) -> Sample {
+    approximate_with(target, sample_rate, 400, 0)
+}
+
+/// Finds a Sample whose sound approximates `target`, recorded at `sample_rate` Hz, by
+/// rendering and comparing `evaluations` candidates. Results are deterministic for a given
+/// `seed`.
+///
+/// About a third of the candidates are drawn from the built-in categories and the full
+/// parameter space, and the rest refine the best match found so far.
+pub fn approximate_with(target: &[f32], sample_rate: u32, evaluations: usize, seed: u64) -> Sample {
+    assert!(sample_rate > 0, "sample_rate must be greater than zero");
+    let target = if sample_rate == SAMPLE_RATE {
+        target.to_vec()
+    } else {
+        edit::resample(target, sample_rate as f32 / SAMPLE_RATE as f32)
+    };
+    let target_features = features(&target);
+
+    let rng = &mut Prng::new(seed);
+    let full = ParamRanges::full();
+    let explore = evaluations / 3;
+
+    let mut best = Sample::new();
+    let mut best_loss = loss(&target_features, &render(&best, target.len()));
+
+    for evaluation in 0..evaluations {
+        let candidate = if evaluation < explore {
+            if evaluation % 2 == 0 {
+                let category = Category::ALL[evaluation / 2 % Category::ALL.len()];
+                category.def().generate(rng)
+            } else {
+                Sample::random_in(&full, rng)
+            }
+        } else {
+            // Narrow the search as the refinement progresses
+            let progress = (evaluation - explore) as f64 / (evaluations - explore) as f64;
+            refine(&best, 0.2 * (1.0 - progress) + 0.01, rng)
+        };
+
+        let candidate_loss = loss(&target_features, &render(&candidate, target.len()));
+        if candidate_loss < best_loss {
+            best = candidate;
+            best_loss = candidate_loss;
+        }
+    }
+
+    best
+}
+
+/// Returns a copy of `sample` with a few fields moved randomly by up to `±amount`
+fn refine<R: Rng + ?Sized>(sample: &Sample, amount: f64, rng: &mut R) -> Sample {
+    let mut s = sample.clone();
+    for &param in Param::RANDOMIZED.iter() {
+        if rand_bool(rng, 1, 3) {
+            let (min, max) = param.range();
+            let value = s.param(param) + amount * rand_f64(rng, -1.0, 1.0);
+            s.set_param(param, value.clamp(min, max));
+        }
+    }
+    s
+}
+
+fn render(sample: &Sample, length: usize) -> Vec<f32> {
+    let mut buffer = vec![0.0; length];
+    Generator::new(sample.clone()).generate(&mut buffer);
+    buffer
+}
+
+/// Per-frame loudness, brightness and zero crossing rate, loudness normalized to the loudest
+/// frame
+fn features(buffer: &[f32]) -> Vec<(f32, f32, f32)> {
+    let frames: Vec<(f32, f32, f32)> = buffer
+        .chunks(FRAME)
+        .map(|chunk| {
+            let amplitude = rms(chunk.iter().copied());
+            let brightness = frame_brightness(chunk, amplitude);
+            let crossings = chunk
+                .windows(2)
+                .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+                .count();
+            (amplitude, brightness, crossings as f32 / chunk.len() as f32)
+        })
+        .collect();
+
+    let loudest = frames.iter().map(|f| f.0).fold(0.0, f32::max);
+    frames
+        .into_iter()
+        .map(|(a, b, z)| (if loudest > 0.0 { a / loudest } else { 0.0 }, b, z))
+        .collect()
+}
+
+/// Difference between target features and the features of `candidate`
+fn loss(target: &[(f32, f32, f32)], candidate: &[f32]) -> f32 {
+    target
+        .iter()
+        .zip(features(candidate).iter())
+        .map(|(t, c)| {
+            // Timbre only matters where the target is audible
+            (t.0 - c.0).powi(2) + t.0 * ((t.1 - c.1).powi(2) + 4.0 * (t.2 - c.2).powi(2))
+        })
+        .sum()
+}
@@ -1,65 +1,529 @@
-use rand::rngs::SmallRng;
-use rand::{Rng, SeedableRng};
+use crate::prng::Prng;
+use crate::{ConsoleMode, Key};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::f32::consts::PI;
+use std::fmt;
+use std::sync::Arc;
 
+/// Oscillator wave shape
 #[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum WaveType {
+    /// Square wave with adjustable duty cycle. Index `0` in original sfxr.
     Square,
+    /// Sawtooth wave. Index `1` in original sfxr.
     Sawtooth,
+    /// Sine wave. Index `2` in original sfxr.
     Sine,
+    /// Pitched white noise. Index `3` in original sfxr.
     Noise,
+    /// Triangle wave. Not present in original sfxr, index `4` as used by bfxr.
     Triangle,
 }
 
+impl WaveType {
+    /// Converts a wave type index as used by original sfxr (and `.sfs` files) to a WaveType.
+    /// Returns `None` for unknown indices.
+    pub fn from_index(index: u8) -> Option<WaveType> {
+        match index {
+            0 => Some(WaveType::Square),
+            1 => Some(WaveType::Sawtooth),
+            2 => Some(WaveType::Sine),
+            3 => Some(WaveType::Noise),
+            4 => Some(WaveType::Triangle),
+            _ => None,
+        }
+    }
+    /// Returns the wave type index as used by original sfxr (and `.sfs` files)
+    pub fn as_index(self) -> u8 {
+        match self {
+            WaveType::Square => 0,
+            WaveType::Sawtooth => 1,
+            WaveType::Sine => 2,
+            WaveType::Noise => 3,
+            WaveType::Triangle => 4,
+        }
+    }
+}
+
+/// How `WaveType::Noise` relates to the oscillator frequency
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum NoiseMode {
+    /// A table of 32 random values played once per oscillator period, so the noise has a pitch
+    /// set by the frequency. As in original sfxr.
+    Pitched,
+    /// A new random value every output sample regardless of frequency, giving unpitched white
+    /// noise for wind, rain and static
+    Continuous,
+}
+
+/// Recorded or drawn values played by `WaveType::Noise` in place of random noise
+///
+/// Holds up to `NoiseTable::CAPACITY` values, which are looped. With `NoiseMode::Pitched` each
+/// oscillator period plays the next 32 values, and with `NoiseMode::Continuous` one value is
+/// played per output sample.
+///
+/// The values are shared by reference counting, so cloning a NoiseTable, or a Sample holding
+/// one, doesn't copy or allocate them.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{NoiseTable, Sample, WaveType};
+/// // A sparse crackle
+/// let crackle: Vec<f32> = (0..1_000)
+///     .map(|i| if i % 97 == 0 { 1.0 } else { 0.0 })
+///     .collect();
+/// let mut sample = Sample::new();
+/// sample.wave_type = WaveType::Noise;
+/// sample.custom_noise = Some(NoiseTable::new(&crackle));
+/// ```
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "Vec<f32>", try_from = "Vec<f32>")
+)]
+pub struct NoiseTable {
+    values: Arc<[f32]>,
+}
+
+impl NoiseTable {
+    /// Largest number of values, about 23 ms of audio at `SAMPLE_RATE`
+    pub const CAPACITY: usize = 1_024;
+
+    /// Constructs a NoiseTable from the first `NoiseTable::CAPACITY` of `values`, clamped to
+    /// `-1.0..=1.0`. Panics if `values` is empty.
+    pub fn new(values: &[f32]) -> NoiseTable {
+        assert!(!values.is_empty(), "noise table must not be empty");
+        NoiseTable {
+            values: values
+                .iter()
+                .take(NoiseTable::CAPACITY)
+                .map(|v| v.clamp(-1.0, 1.0))
+                .collect(),
+        }
+    }
+    /// Returns the values
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+    /// Returns the value at `index`, wrapping around at the end
+    fn get(&self, index: usize) -> f32 {
+        self.values[index % self.values.len()]
+    }
+}
+
+impl PartialEq for NoiseTable {
+    fn eq(&self, other: &NoiseTable) -> bool {
+        self.values() == other.values()
+    }
+}
+
+impl fmt::Debug for NoiseTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NoiseTable")
+            .field("len", &self.values.len())
+            .finish()
+    }
+}
+
+impl From<NoiseTable> for Vec<f32> {
+    fn from(table: NoiseTable) -> Vec<f32> {
+        table.values().to_vec()
+    }
+}
+
+impl TryFrom<Vec<f32>> for NoiseTable {
+    type Error = &'static str;
+
+    fn try_from(values: Vec<f32>) -> Result<NoiseTable, &'static str> {
+        if values.is_empty() || values.len() > NoiseTable::CAPACITY {
+            return Err("noise table must have between 1 and 1024 values");
+        }
+        Ok(NoiseTable::new(&values))
+    }
+}
+
+/// How `freq_ramp` and `freq_dramp` change the oscillator frequency over time
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SlideMode {
+    /// The period is multiplied by a slide factor every sample, and `freq_dramp` changes the
+    /// factor. As in original sfxr.
+    Legacy,
+    /// The frequency changes by a constant number of Hz per second, accelerated by `freq_dramp`
+    LinearHz,
+    /// The pitch changes by a constant number of semitones per second, accelerated by
+    /// `freq_dramp`
+    LinearSemitones,
+}
+
+/// What happens when a downward slide reaches `Sample::freq_limit`
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum FreqLimitBehavior {
+    /// The sound ends at once, as in original sfxr. Has no effect with a `freq_limit` of `0.0`.
+    EndSound,
+    /// The frequency stays at the limit until the envelope ends
+    Clamp,
+}
+
+/// Destination of the sample and hold modulation, with the modulation depth
+///
+/// A new random value between `-1.0` and `1.0` is drawn every `Sample::sample_hold_secs` and
+/// held until the next, scaled by the depth. Modulating the pitch gives the classic sci-fi
+/// computer bleeps.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{SampleHold, Sample, WaveType};
+/// let mut computer = Sample::new();
+/// computer.wave_type = WaveType::Square;
+/// computer.env_sustain = 0.6;
+/// computer.sample_hold = SampleHold::Pitch(12.0);
+/// computer.sample_hold_secs = 0.06;
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SampleHold {
+    /// No modulation
+    Off,
+    /// Shifts the pitch by up to this many semitones up or down
+    Pitch(f32),
+    /// Shifts the low pass filter cutoff by up to this many octaves up or down
+    Filter(f32),
+}
+
+/// How the arpeggio changes the frequency
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{ArpDirection, ArpMode, Sample};
+/// let mut chime = Sample::new();
+/// chime.arp_speed = 0.7;
+/// chime.arp_mod = 0.4;
+/// chime.arp_mode = ArpMode::Cycle {
+///     direction: ArpDirection::Up,
+///     notes: 3,
+/// };
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ArpMode {
+    /// Step the frequency once by `Sample::arp_mod` after the `Sample::arp_speed` interval, as
+    /// original sfxr
+    Once,
+    /// Move between `notes` pitches every `Sample::arp_speed` interval, starting from the base
+    /// pitch. Each note is one `Sample::arp_mod` step further from the base pitch than the
+    /// last. `notes` below `2` are treated as `2`.
+    Cycle {
+        /// Order in which the notes are played
+        direction: ArpDirection,
+        /// Number of notes, including the base pitch
+        notes: u8,
+    },
+}
+
+/// Order of the notes in `ArpMode::Cycle`
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ArpDirection {
+    /// Step away from the base pitch, wrapping back to it after the last note. Rising if
+    /// `Sample::arp_mod` is positive.
+    Up,
+    /// Step towards the base pitch, wrapping to the last note after it
+    Down,
+    /// Jump to a random note
+    Random,
+}
+
+/// What restarts when a sound repeats due to `Sample::repeat_speed`
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RepeatMode {
+    /// Restart the oscillator and filters while the envelope and phaser continue, as original
+    /// sfxr
+    Partial,
+    /// Also restart the envelope and phaser, so each repeat is a complete copy of the sound.
+    /// The sound still ends after the length of the envelope.
+    Full,
+    /// Restart like `Partial`, then shift the pitch randomly by up to `pitch_semitones` up or
+    /// down and the duty cycle by up to `duty` in `Sample::duty` units, for varied retriggers
+    Randomized {
+        /// Largest pitch shift in semitones
+        pitch_semitones: f32,
+        /// Largest duty cycle change
+        duty: f32,
+    },
+}
+
+/// Shape of a `RampTarget` transition over time
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Easing {
+    /// Constant rate of change
+    Linear,
+    /// Starts slowly and speeds up
+    EaseIn,
+    /// Starts quickly and slows down towards the target
+    EaseOut,
+    /// Starts and ends slowly
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps the fraction `t` of the transition time, between `0.0` and `1.0`, to the fraction
+    /// of the distance covered
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// A time based transition of a Sample field to a target value
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Easing, RampTarget, Sample};
+/// let mut sample = Sample::new();
+/// sample.lpf_freq = 0.2;
+/// // Open the filter fully in 300 ms, quickly at first
+/// sample.lpf_target = Some(RampTarget {
+///     value: 1.0,
+///     duration_secs: 0.3,
+///     easing: Easing::EaseOut,
+/// });
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RampTarget {
+    /// Value reached at the end, in the units of the field. Value must be between `0.0` and
+    /// `1.0`.
+    pub value: f32,
+    /// Time from the start of the sound (or of each repeat) until `value` is reached. Value must
+    /// not be negative.
+    pub duration_secs: f32,
+    /// Shape of the transition
+    pub easing: Easing,
+}
+
+/// Progress of a `RampTarget`, advanced once per sample
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ramp {
+    start: f32,
+    end: f32,
+    length: u32,
+    elapsed: u32,
+    easing: Easing,
+}
+
+impl Ramp {
+    /// Ramp from `start` to `target.value` at `sample_rate` samples per second. Out of range
+    /// targets are clamped.
+    pub fn new(start: f32, target: RampTarget, sample_rate: u32) -> Ramp {
+        Ramp {
+            start,
+            end: target.value.clamp(0.0, 1.0),
+            length: (target.duration_secs.max(0.0) * sample_rate as f32) as u32,
+            elapsed: 0,
+            easing: target.easing,
+        }
+    }
+    /// Advances by one sample and returns the current value
+    pub fn advance(&mut self) -> f32 {
+        if self.elapsed >= self.length {
+            return self.end;
+        }
+        self.elapsed += 1;
+        let t = self.elapsed as f32 / self.length as f32;
+        self.start + (self.end - self.start) * self.easing.apply(t)
+    }
+}
+
+/// Random values held for a fixed number of samples
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SampleHoldLfo {
+    rng: Prng,
+    interval: u32,
+    elapsed: u32,
+}
+
+impl SampleHoldLfo {
+    pub fn new() -> SampleHoldLfo {
+        SampleHoldLfo {
+            rng: Prng::new(0),
+            interval: 1,
+            elapsed: 0,
+        }
+    }
+    /// Restarts with a new value every `interval_secs` at `sample_rate` samples per second,
+    /// drawing the first on the next `advance`
+    pub fn reset(&mut self, interval_secs: f32, sample_rate: u32) {
+        self.interval = ((interval_secs * sample_rate as f32) as u32).max(1);
+        self.elapsed = 0;
+    }
+    /// Restarts the random sequence from `seed`
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = Prng::new(seed);
+    }
+    /// Advances by one sample. Returns the new value between `-1.0` and `1.0` if one was drawn.
+    pub fn advance(&mut self) -> Option<f32> {
+        let value = if self.elapsed == 0 {
+            Some(self.rng.next_f32() * 2.0 - 1.0)
+        } else {
+            None
+        };
+        self.elapsed = (self.elapsed + 1) % self.interval;
+        value
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Oscillator {
     wave_type: WaveType,
-    rng: SmallRng,
+    rng: Prng,
     period: u32,
     phase: u32,
     noise_buffer: [f32; 32],
+    noise_mode: NoiseMode,
+    noise_phase: u32,
+    noise_value: f32,
+    custom_noise: Option<NoiseTable>,
+    noise_position: usize,
     square_duty: f32,
     square_slide: f32,
+    duty_ramp: Option<Ramp>,
     fperiod: f64,
     fmaxperiod: f64,
+    has_limit: bool,
+    limit_reached: bool,
     fslide: f64,
     fdslide: f64,
+    slide_mode: SlideMode,
+    slide_rate: f64,
+    slide_accel: f64,
     vib_phase: f64,
     vib_speed: f64,
     vib_amp: f64,
+    vib_extra: f64,
     arp_time: i32,
     arp_limit: i32,
     arp_mod: f64,
+    arp_mode: ArpMode,
+    arp_index: i32,
+    arp_rng: Prng,
+    pitch: f64,
+    pitch_mod: f64,
+    bend: f64,
+    duty_mod: f32,
+    unison_voices: usize,
+    unison_ratios: [f32; MAX_UNISON_VOICES],
+    unison_phases: [f32; MAX_UNISON_VOICES],
+    antialiasing: bool,
+    console: ConsoleMode,
+    key: Option<Key>,
 }
-pub trait Filter {
-    fn filter(&mut self, sample: f32) -> f32;
-}
-pub struct FilterIterator<'a> {
-    iter: &'a mut dyn Iterator<Item = f32>,
-    filter: &'a mut dyn Filter,
-}
-pub trait Filterable<'a> {
-    fn chain_filter(&'a mut self, filter: &'a mut dyn Filter) -> FilterIterator<'a>;
-}
-impl<'a, T: Iterator<Item = f32>> Filterable<'a> for T {
-    fn chain_filter(&'a mut self, filter: &'a mut dyn Filter) -> FilterIterator<'a> {
-        FilterIterator { iter: self, filter }
+
+/// Largest supported number of unison voices
+pub const MAX_UNISON_VOICES: usize = 8;
+/// Largest supported phaser delay line length in samples
+#[cfg(not(feature = "heapless"))]
+pub const MAX_PHASER_LENGTH: usize = 4096;
+/// Largest supported phaser delay line length in samples, which all fit inline
+#[cfg(feature = "heapless")]
+pub const MAX_PHASER_LENGTH: usize = INLINE_PHASER_LENGTH;
+/// Longest phaser delay line stored in the Phaser itself. Longer lines are allocated.
+pub const INLINE_PHASER_LENGTH: usize = 1024;
+/// Largest low pass coefficient after scaling for fewer supersamples, well within the
+/// stable range of the filter
+const MAX_SCALED_FLTW: f32 = 2.0;
+/// Vibrato phase increment per sample of extra vibrato when the Sample has none, about 5.5 Hz
+const DEFAULT_VIB_SPEED: f64 = 2.0 * std::f64::consts::PI * 5.5 / crate::SAMPLE_RATE as f64;
+/// Magnitude below which filter state is flushed to zero, far below audibility but well above
+/// the denormal range, where arithmetic on x86 is many times slower
+const FLUSH_LIMIT: f32 = 1.0e-15;
+
+/// Returns `value`, or `0.0` if it is tiny or not finite, so filter state decaying towards zero
+/// doesn't sink into denormals, and a NaN or infinity doesn't stay in the state forever
+pub fn flush(value: f32) -> f32 {
+    if value.is_finite() && value.abs() >= FLUSH_LIMIT {
+        value
+    } else {
+        0.0
     }
 }
-impl<'a> Iterator for FilterIterator<'a> {
-    type Item = f32;
-    fn next(&mut self) -> Option<f32> {
-        match self.iter.next() {
-            Some(v) => Some(self.filter.filter(v)),
-            None => None,
-        }
-    }
+pub trait Filter {
+    fn filter(&mut self, sample: f32) -> f32;
 }
-enum EnvelopeStage {
+/// Amplitude envelope stage
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum EnvelopeStage {
+    /// Volume rising from silence
     Attack,
+    /// Full volume, with optional punch
     Sustain,
+    /// Volume falling to silence
     Decay,
+    /// Envelope has finished and the sound is silent
     End,
 }
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Envelope {
     stage: EnvelopeStage,
     stage_left: u32,
@@ -67,8 +531,61 @@ pub struct Envelope {
     sustain: u32,
     decay: u32,
     punch: f32,
+    volume_steps: VolumeSteps,
+    deadline: Option<u32>,
+    hold: bool,
+}
+
+/// Volume levels the envelope is limited to, as on console sound chips
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum VolumeSteps {
+    /// Any volume
+    Continuous,
+    /// 16 evenly spaced levels up to full volume, as the NES and Game Boy pulse and noise
+    /// channels
+    FourBit,
+    /// Silent, 25%, 50% or full volume, as the Game Boy wave channel
+    Quarters,
+    /// Silent or full volume, as the NES triangle channel
+    OnOff,
 }
 
+impl VolumeSteps {
+    /// Volume steps of the channel that plays `wave_type` on `console`
+    pub fn new(console: ConsoleMode, wave_type: WaveType) -> VolumeSteps {
+        match (console, wave_type) {
+            (ConsoleMode::None, _) => VolumeSteps::Continuous,
+            (_, WaveType::Square) | (_, WaveType::Noise) => VolumeSteps::FourBit,
+            (ConsoleMode::Nes, _) => VolumeSteps::OnOff,
+            (ConsoleMode::GameBoy, _) => VolumeSteps::Quarters,
+        }
+    }
+    /// Rounds `volume` to the nearest step
+    fn apply(self, volume: f32) -> f32 {
+        let volume = volume.clamp(0.0, 1.0);
+        match self {
+            VolumeSteps::Continuous => volume,
+            VolumeSteps::FourBit => (volume * 15.0).round() / 15.0,
+            VolumeSteps::Quarters => match volume {
+                v if v < 0.125 => 0.0,
+                v if v < 0.375 => 0.25,
+                v if v < 0.75 => 0.5,
+                _ => 1.0,
+            },
+            VolumeSteps::OnOff => {
+                if volume > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HighLowPassFilter {
     fltp: f32,
     fltdp: f32,
@@ -78,13 +595,54 @@ pub struct HighLowPassFilter {
     fltphp: f32,
     flthp: f32,
     flthp_d: f32,
+    lpf_ramp: Option<Ramp>,
+    cutoff_mod: f32,
+    steps: u32,
+    flush: bool,
 }
 
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Phaser {
     ipp: usize,
     fphase: f32,
     fdphase: f32,
-    buffer: [f32; 1024],
+    length: usize,
+    stereo: f32,
+    steps: u32,
+    flush: bool,
+    #[cfg_attr(feature = "serde", serde(with = "float_array"))]
+    buffer: [f32; INLINE_PHASER_LENGTH],
+    /// Delay line used in place of `buffer` for lengths above `INLINE_PHASER_LENGTH`
+    long_buffer: Option<Box<[f32]>>,
+}
+
+/// Serializes fixed size `f32` arrays, which serde only supports up to 32 values
+#[cfg(feature = "serde")]
+pub(crate) mod float_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        buffer: &[f32; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(buffer.iter())
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[f32; N], D::Error> {
+        let values = Vec::<f32>::deserialize(deserializer)?;
+        let mut buffer = [0.0; N];
+        if values.len() != N {
+            return Err(D::Error::invalid_length(
+                values.len(),
+                &format!("{} values", N).as_str(),
+            ));
+        }
+        buffer.copy_from_slice(&values);
+        Ok(buffer)
+    }
 }
 
 impl Oscillator {
@@ -96,32 +654,192 @@ impl Oscillator {
             phase: 0,
             fperiod: 0.0,
             fmaxperiod: 0.0,
+            has_limit: false,
+            limit_reached: false,
             fslide: 0.0,
             fdslide: 0.0,
+            slide_mode: SlideMode::Legacy,
+            slide_rate: 0.0,
+            slide_accel: 0.0,
             square_slide: 0.0,
+            duty_ramp: None,
             noise_buffer: [0.0; 32],
+            noise_mode: NoiseMode::Pitched,
+            noise_phase: 0,
+            noise_value: 0.0,
+            custom_noise: None,
+            noise_position: 0,
             vib_phase: 0.0,
             vib_speed: 0.0,
             vib_amp: 0.0,
+            vib_extra: 0.0,
             arp_time: 0,
             arp_limit: 0,
             arp_mod: 0.0,
-            rng: SmallRng::seed_from_u64(0),
+            arp_mode: ArpMode::Once,
+            arp_index: 0,
+            arp_rng: Prng::new(0),
+            pitch: 1.0,
+            pitch_mod: 1.0,
+            bend: 1.0,
+            duty_mod: 0.0,
+            unison_voices: 1,
+            unison_ratios: [1.0; MAX_UNISON_VOICES],
+            unison_phases: [0.0; MAX_UNISON_VOICES],
+            antialiasing: false,
+            console: ConsoleMode::None,
+            key: None,
+            rng: Prng::new(0),
         }
     }
     pub fn reset_noise(&mut self) {
-        for v in self.noise_buffer.iter_mut() {
-            *v = self.rng.gen::<f32>() * 2.0 - 1.0;
+        for i in 0..self.noise_buffer.len() {
+            self.noise_buffer[i] = self.next_noise();
+        }
+    }
+    /// Restarts the noise random sequence from `seed` and refills the noise table. Custom noise
+    /// restarts from an offset chosen by `seed`.
+    pub fn reseed_noise(&mut self, seed: u64) {
+        self.rng = Prng::new(seed);
+        self.noise_position = match self.custom_noise {
+            Some(ref table) => (seed % table.values.len() as u64) as usize,
+            None => 0,
+        };
+        self.reset_noise();
+    }
+    /// Returns the next value of the custom noise, or a random value without one
+    fn next_noise(&mut self) -> f32 {
+        match self.custom_noise {
+            Some(ref table) => {
+                let value = table.get(self.noise_position);
+                self.noise_position = (self.noise_position + 1) % table.values.len();
+                value
+            }
+            None => self.rng.next_f32() * 2.0 - 1.0,
+        }
+    }
+    /// Plays `table` in place of random noise, or random noise if `None`
+    pub fn set_custom_noise(&mut self, table: Option<&NoiseTable>) {
+        self.custom_noise = table.cloned();
+        if let Some(table) = table {
+            self.noise_position %= table.values.len();
         }
     }
     pub fn reset_phase(&mut self) {
         self.phase = 0;
+        for (voice, phase) in self.unison_phases.iter_mut().enumerate() {
+            // Start the extra voices out of phase to avoid a loud in-phase attack
+            *phase = voice as f32 / self.unison_voices as f32;
+        }
+    }
+    /// Sets the number of detuned copies of the wave to sum, and their detune spread where
+    /// `1.0` spreads the voices up to half a semitone either way
+    pub fn set_unison(&mut self, voices: u8, detune: f32) {
+        self.unison_voices = usize::from(voices).clamp(1, MAX_UNISON_VOICES);
+        let pairs = (self.unison_voices / 2) as f32;
+        for (voice, ratio) in self.unison_ratios.iter_mut().enumerate() {
+            // Voice 0 stays in tune, the rest alternate above and below it
+            let step = voice.div_ceil(2) as f32;
+            let sign = if voice % 2 == 1 { 1.0 } else { -1.0 };
+            let semitones = if voice == 0 {
+                0.0
+            } else {
+                sign * 0.5 * detune * step / pairs
+            };
+            // Periods shrink as the frequency rises
+            *ratio = 2f32.powf(-semitones / 12.0);
+        }
+    }
+    /// Sets how the frequency slides, converting the slide set by `reset` so that it starts at
+    /// the same rate. Call after `reset`.
+    pub fn set_slide_mode(&mut self, slide_mode: SlideMode) {
+        self.slide_mode = slide_mode;
+        match slide_mode {
+            SlideMode::Legacy => {}
+            SlideMode::LinearHz => {
+                // Change of frequency per sample, in cycles per supersample
+                let freq = 1.0 / self.fperiod;
+                self.slide_rate = freq * (1.0 / self.fslide - 1.0);
+                self.slide_accel = -freq * self.fdslide;
+            }
+            SlideMode::LinearSemitones => {
+                // Change of the period's logarithm per sample, in octaves
+                self.slide_rate = self.fslide.log2();
+                self.slide_accel = self.fdslide / std::f64::consts::LN_2;
+            }
+        }
+    }
+    /// Moves the duty cycle along `ramp` instead of `duty_ramp`, in `Sample::duty` units. Call
+    /// after `reset`.
+    pub fn set_duty_ramp(&mut self, ramp: Option<Ramp>) {
+        self.duty_ramp = ramp;
+    }
+    pub fn set_noise_mode(&mut self, noise_mode: NoiseMode) {
+        self.noise_mode = noise_mode;
+    }
+    /// Snaps the pitch to the notes of `key`, or not at all if `None`
+    pub fn set_key(&mut self, key: Option<Key>) {
+        self.key = key;
+    }
+    /// Enables PolyBLEP smoothing of the square and triangle wave discontinuities
+    pub fn set_antialiasing(&mut self, antialiasing: bool) {
+        self.antialiasing = antialiasing;
+    }
+    /// Restricts the square duty cycles and wave tables to those of `console`
+    pub fn set_console(&mut self, console: ConsoleMode) {
+        self.console = console;
+    }
+    /// Sets a frequency multiplier applied on top of the slide, vibrato and arpeggio
+    pub fn set_pitch(&mut self, pitch: f64) {
+        self.pitch = pitch;
+    }
+    /// Sets whether the arpeggio steps once or cycles through notes. Call after `reset`.
+    pub fn set_arp_mode(&mut self, arp_mode: ArpMode) {
+        self.arp_mode = arp_mode;
+    }
+    /// Multiplies the frequency by `ratio`, keeping the slide. Call after `reset`.
+    pub fn transpose(&mut self, ratio: f64) {
+        self.fperiod /= ratio;
+    }
+    /// Changes the duty cycle by `amount` in `Sample::duty` units. Call after `reset`.
+    pub fn offset_duty(&mut self, amount: f32) {
+        self.square_duty = (self.square_duty - amount * 0.5).clamp(0.0, 0.5);
+    }
+    /// Sets a frequency multiplier from modulation, applied on top of `set_pitch`
+    pub fn set_pitch_mod(&mut self, pitch_mod: f64) {
+        self.pitch_mod = pitch_mod;
+    }
+    /// Sets a frequency multiplier from a pitch bend controller, applied on top of
+    /// `set_pitch_mod`
+    pub fn set_bend(&mut self, bend: f64) {
+        self.bend = bend;
+    }
+    /// Sets a duty cycle offset from modulation in `Sample::duty` units
+    pub fn set_duty_mod(&mut self, duty_mod: f32) {
+        self.duty_mod = duty_mod;
+    }
+    /// Sets vibrato depth added to `Sample::vib_strength` from a controller, in the same
+    /// units. Kept across `reset_vibrato`.
+    pub fn set_extra_vibrato(&mut self, vib_strength: f64) {
+        self.vib_extra = vib_strength * 0.5;
     }
     pub fn reset_vibrato(&mut self, vib_speed: f64, vib_strength: f64) {
         self.vib_phase = 0.0;
+        self.set_vibrato(vib_speed, vib_strength);
+    }
+    /// Sets the vibrato speed and strength, keeping its phase
+    pub fn set_vibrato(&mut self, vib_speed: f64, vib_strength: f64) {
         self.vib_speed = vib_speed.powi(2) * 0.01;
         self.vib_amp = vib_strength * 0.5;
     }
+    /// Sets the frequency to `base_freq`, from where the slide continues. Call after `reset`.
+    pub fn set_base_freq(&mut self, base_freq: f64) {
+        self.fperiod = Oscillator::period(base_freq);
+    }
+    /// Sets the duty cycle to `duty`, from where `duty_ramp` continues. Call after `reset`.
+    pub fn set_duty(&mut self, duty: f32) {
+        self.square_duty = 0.5 - duty * 0.5;
+    }
     pub fn reset(
         &mut self,
         wave_type: WaveType,
@@ -135,48 +853,135 @@ impl Oscillator {
         arp_mod: f64,
     ) {
         self.wave_type = wave_type;
-        self.fperiod = 100.0 / (base_freq.powi(2) + 0.001);
-        self.fmaxperiod = 100.0 / (freq_limit.powi(2) + 0.001);
+        self.fperiod = Oscillator::period(base_freq);
+        self.fmaxperiod = Oscillator::period(freq_limit);
+        self.has_limit = freq_limit > 0.0;
+        self.limit_reached = false;
         self.fslide = 1.0 - freq_ramp.powi(3) * 0.01;
         self.fdslide = -freq_dramp.powi(3) * 0.000001;
         self.square_duty = 0.5 - duty * 0.5;
         self.square_slide = -duty_ramp * 0.00005;
 
-        self.arp_mod = if arp_mod >= 0.0 {
+        self.arp_mod = Oscillator::arp_factor(arp_mod);
+
+        self.arp_time = 0;
+        self.arp_limit = Oscillator::arp_limit(arp_speed);
+        self.arp_index = 0;
+    }
+    /// Oscillator period in supersamples for a `base_freq` or `freq_limit` value
+    pub fn period(freq: f64) -> f64 {
+        100.0 / (freq.powi(2) + 0.001)
+    }
+    /// Factor the arpeggio multiplies the period by for an `arp_mod` value
+    pub fn arp_factor(arp_mod: f64) -> f64 {
+        if arp_mod >= 0.0 {
             1.0 - arp_mod.powf(2.0) * 0.9
         } else {
             1.0 - arp_mod.powf(2.0) * 10.0
-        };
-
-        self.arp_time = 0;
-        self.arp_limit = ((1.0 - arp_speed).powi(2) * 20000.0 + 32.0) as i32;
-
+        }
+    }
+    /// Number of samples from (re)start until the arpeggio triggers, or `0` if it never does
+    pub fn arp_limit(arp_speed: f32) -> i32 {
         if (arp_speed - 1.0).abs() < f32::EPSILON {
-            self.arp_limit = 0;
+            0
+        } else {
+            ((1.0 - arp_speed).powi(2) * 20000.0 + 32.0) as i32
         }
     }
-    pub fn advance(&mut self) {
+    /// Returns `true` once a slide has reached a nonzero frequency limit since `reset`
+    pub fn limit_reached(&self) -> bool {
+        self.limit_reached
+    }
+    /// Advances the oscillator by one sample. Returns `true` if the arpeggio triggered.
+    pub fn advance(&mut self) -> bool {
         self.arp_time += 1;
 
-        if self.arp_limit != 0 && self.arp_time >= self.arp_limit {
-            self.arp_limit = 0;
-            self.fperiod *= self.arp_mod as f64;
+        let arp_triggered = self.arp_limit != 0 && self.arp_time >= self.arp_limit;
+        if arp_triggered {
+            match self.arp_mode {
+                ArpMode::Once => {
+                    self.arp_limit = 0;
+                    self.fperiod *= self.arp_mod;
+                }
+                ArpMode::Cycle { direction, notes } => {
+                    let notes = i32::from(notes.max(2));
+                    let index = match direction {
+                        ArpDirection::Up => (self.arp_index + 1) % notes,
+                        ArpDirection::Down => (self.arp_index + notes - 1) % notes,
+                        ArpDirection::Random => (self.arp_rng.next_u32() % notes as u32) as i32,
+                    };
+                    self.fperiod *= self.arp_mod.powi(index - self.arp_index);
+                    self.arp_index = index;
+                    self.arp_time = 0;
+                }
+            }
         }
 
-        self.fslide += self.fdslide;
-        self.fperiod = (self.fperiod * self.fslide).min(self.fmaxperiod);
+        let fperiod = match self.slide_mode {
+            SlideMode::Legacy => {
+                self.fslide += self.fdslide;
+                self.fperiod * self.fslide
+            }
+            SlideMode::LinearHz => {
+                self.slide_rate += self.slide_accel;
+                let freq = 1.0 / self.fperiod + self.slide_rate;
+                if freq > 0.0 {
+                    1.0 / freq
+                } else {
+                    f64::INFINITY
+                }
+            }
+            SlideMode::LinearSemitones => {
+                self.slide_rate += self.slide_accel;
+                self.fperiod * self.slide_rate.exp2()
+            }
+        };
+        self.fperiod = if fperiod > self.fmaxperiod {
+            self.limit_reached |= self.has_limit;
+            self.fmaxperiod
+        } else {
+            fperiod
+        };
+
+        // Extra vibrato on a Sample without any vibrato speed is played at a default speed
+        self.vib_phase += if self.vib_extra > 0.0 && self.vib_speed == 0.0 {
+            DEFAULT_VIB_SPEED
+        } else {
+            self.vib_speed
+        };
+        let vibrato = 1.0 + self.vib_phase.sin() * (self.vib_amp + self.vib_extra);
 
-        self.vib_phase += self.vib_speed;
-        let vibrato = 1.0 + self.vib_phase.sin() * self.vib_amp;
+        let fperiod = match self.key {
+            Some(ref key) => key.snap_period(self.fperiod),
+            None => self.fperiod,
+        };
+        let pitch = self.pitch * self.pitch_mod * self.bend;
+        self.period = ((vibrato * fperiod / pitch) as u32).max(8);
+        self.square_duty = match self.duty_ramp {
+            Some(ref mut ramp) => 0.5 - ramp.advance() * 0.5,
+            None => (self.square_duty + self.square_slide).clamp(0.0, 0.5),
+        };
 
-        self.period = ((vibrato * self.fperiod) as u32).max(8);
-        self.square_duty = (self.square_duty + self.square_slide).min(0.5).max(0.0);
+        arp_triggered
     }
 }
-impl Iterator for Oscillator {
-    type Item = f32;
-    fn next(&mut self) -> Option<f32> {
-        self.phase += 1;
+impl Oscillator {
+    /// Current oscillator period in supersamples, including vibrato and pitch
+    pub fn current_period(&self) -> u32 {
+        self.period
+    }
+    /// Advances the phase by `steps` supersamples and returns the wave value
+    pub fn next_sample(&mut self, steps: u32) -> f32 {
+        if self.wave_type == WaveType::Noise && self.noise_mode == NoiseMode::Continuous {
+            // Draw a new value at the start of every output sample of 8 supersamples
+            if self.noise_phase == 0 {
+                self.noise_value = self.next_noise();
+            }
+            self.noise_phase = (self.noise_phase + steps) % 8;
+            return self.noise_value;
+        }
+
+        self.phase += steps;
         if self.phase >= self.period {
             self.phase %= self.period;
             if self.wave_type == WaveType::Noise {
@@ -185,15 +990,68 @@ impl Iterator for Oscillator {
         }
 
         let fp = self.phase as f32 / self.period as f32;
-        let sample = match self.wave_type {
+        let dt = steps as f32 / self.period as f32;
+        if self.unison_voices == 1 {
+            return self.wave(fp, dt);
+        }
+
+        let mut sum = self.wave(fp, dt);
+        for voice in 1..self.unison_voices {
+            let dt = steps as f32 / (self.period as f32 * self.unison_ratios[voice]);
+            let phase = &mut self.unison_phases[voice];
+            *phase += dt;
+            *phase -= phase.floor();
+            let fp = *phase;
+            sum += self.wave(fp, dt);
+        }
+        sum / (self.unison_voices as f32).sqrt()
+    }
+    /// Wave value at phase `fp`, where `dt` is the phase advanced per call
+    fn wave(&self, fp: f32, dt: f32) -> f32 {
+        match (self.console, self.wave_type) {
+            (ConsoleMode::None, _) | (_, WaveType::Square) | (_, WaveType::Noise) => {
+                self.shape(fp, dt)
+            }
+            _ => {
+                // A table of 32 samples of 4 bits, as the NES triangle and Game Boy wave channels
+                let v = self.shape((fp * 32.0).floor() / 32.0, dt);
+                ((v * 7.5 + 7.5).round() - 7.5) / 7.5
+            }
+        }
+    }
+    /// Square wave duty cycle, snapped to the hardware duty cycles in console modes
+    fn duty(&self) -> f32 {
+        let square_duty = (self.square_duty - self.duty_mod * 0.5).clamp(0.0, 0.5);
+        match self.console {
+            ConsoleMode::None => square_duty,
+            ConsoleMode::Nes | ConsoleMode::GameBoy => [0.125, 0.25, 0.5, 0.75]
+                .iter()
+                .copied()
+                .min_by(|a, b| (a - square_duty).abs().total_cmp(&(b - square_duty).abs()))
+                .unwrap_or(square_duty),
+        }
+    }
+    /// Unquantized wave value at phase `fp`
+    fn shape(&self, fp: f32, dt: f32) -> f32 {
+        match self.wave_type {
             WaveType::Square => {
-                if fp < self.square_duty {
-                    0.5
+                let duty = self.duty();
+                let v = if fp < duty { 0.5 } else { -0.5 };
+                if self.antialiasing {
+                    let fall = (fp - duty).rem_euclid(1.0);
+                    v + 0.5 * poly_blep(fp, dt) - 0.5 * poly_blep(fall, dt)
                 } else {
-                    -0.5
+                    v
+                }
+            }
+            WaveType::Triangle => {
+                let v = 1.0 - fp * 2.0;
+                if self.antialiasing {
+                    v + poly_blep(fp, dt)
+                } else {
+                    v
                 }
             }
-            WaveType::Triangle => 1.0 - fp * 2.0,
             WaveType::Sawtooth => {
                 if fp < self.square_duty {
                     -1.0 + 2.0 * fp / self.square_duty
@@ -202,10 +1060,21 @@ impl Iterator for Oscillator {
                 }
             }
             WaveType::Sine => (fp * 2.0 * PI).sin(),
-            WaveType::Noise => self.noise_buffer[(fp * 32.0) as usize],
-        };
-
-        Some(sample)
+            WaveType::Noise => self.noise_buffer[((fp * 32.0) as usize).min(31)],
+        }
+    }
+}
+/// Polynomial correction for a unit upward step at phase `0`, for phase `t` advancing `dt` per
+/// sample
+fn poly_blep(t: f32, dt: f32) -> f32 {
+    if t < dt {
+        let t = t / dt;
+        2.0 * t - t * t - 1.0
+    } else if t > 1.0 - dt {
+        let t = (t - 1.0) / dt;
+        t * t + 2.0 * t + 1.0
+    } else {
+        0.0
     }
 }
 impl Envelope {
@@ -217,17 +1086,53 @@ impl Envelope {
             sustain: 0,
             decay: 0,
             punch: 0.0,
+            volume_steps: VolumeSteps::Continuous,
+            deadline: None,
+            hold: false,
         }
     }
+    /// Limits the volume to `steps`
+    pub fn set_volume_steps(&mut self, steps: VolumeSteps) {
+        self.volume_steps = steps;
+    }
     pub fn reset(&mut self, attack: f32, sustain: f32, decay: f32, punch: f32) {
-        self.attack = (attack.powi(2) * 100_000.0) as u32;
-        self.sustain = (sustain.powi(2) * 100_000.0) as u32;
-        self.decay = (decay.powi(2) * 100_000.0) as u32;
+        self.attack = Envelope::stage_length(attack);
+        self.sustain = Envelope::stage_length(sustain);
+        self.decay = Envelope::stage_length(decay);
         self.punch = punch;
         self.stage = EnvelopeStage::Attack;
         self.stage_left = self.current_stage_length();
+        self.deadline = None;
+    }
+    /// Restarts from the attack, ending the envelope after at most `remaining` more samples
+    pub fn retrigger(&mut self, remaining: u32) {
+        self.stage = EnvelopeStage::Attack;
+        self.stage_left = self.current_stage_length();
+        self.deadline = Some(remaining);
+    }
+    /// Keeps the envelope in the sustain stage at full volume until disabled, ignoring any
+    /// deadline
+    pub fn set_hold(&mut self, hold: bool) {
+        self.hold = hold;
+    }
+    /// Ends the envelope at once, silencing the sound
+    pub fn stop(&mut self) {
+        self.stage = EnvelopeStage::End;
+        self.stage_left = 0;
     }
     pub fn advance(&mut self) {
+        if self.hold && self.stage == EnvelopeStage::Sustain {
+            self.stage_left = self.stage_left.saturating_sub(1);
+            return;
+        }
+        if let Some(ref mut deadline) = self.deadline {
+            if *deadline <= 1 {
+                self.stage = EnvelopeStage::End;
+                self.stage_left = 0;
+                return;
+            }
+            *deadline -= 1;
+        }
         if self.stage_left > 1 {
             self.stage_left -= 1;
         } else {
@@ -241,6 +1146,13 @@ impl Envelope {
             self.stage_left = self.current_stage_length();
         }
     }
+    /// Length of an envelope stage in samples
+    pub fn stage_length(duration: f32) -> u32 {
+        (duration.powi(2) * 100_000.0) as u32
+    }
+    pub fn stage(&self) -> EnvelopeStage {
+        self.stage
+    }
     fn current_stage_length(&self) -> u32 {
         match self.stage {
             EnvelopeStage::Attack => self.attack,
@@ -249,7 +1161,11 @@ impl Envelope {
             EnvelopeStage::End => 0,
         }
     }
-    fn volume(&self) -> f32 {
+    /// Current envelope amplitude, between `0.0` and `1.0` plus punch
+    pub fn volume(&self) -> f32 {
+        if self.hold && self.stage == EnvelopeStage::Sustain && self.stage_left == 0 {
+            return 1.0;
+        }
         let dt = self.stage_left as f32 / self.current_stage_length() as f32;
         match self.stage {
             EnvelopeStage::Attack => 1.0 - dt,
@@ -262,7 +1178,11 @@ impl Envelope {
 
 impl Filter for Envelope {
     fn filter(&mut self, sample: f32) -> f32 {
-        sample * self.volume()
+        if self.volume_steps == VolumeSteps::Continuous {
+            sample * self.volume()
+        } else {
+            sample * self.volume_steps.apply(self.volume())
+        }
     }
 }
 impl HighLowPassFilter {
@@ -276,8 +1196,23 @@ impl HighLowPassFilter {
             fltphp: 0.0,
             flthp: 0.0,
             flthp_d: 0.0,
+            lpf_ramp: None,
+            cutoff_mod: 1.0,
+            steps: 1,
+            flush: true,
         }
     }
+    /// Sets the number of original sfxr supersamples each filtered supersample stands for,
+    /// above `1` scaling the cutoffs, resonance and ramps to keep them in Hz. Call before
+    /// `reset`.
+    pub fn set_steps(&mut self, steps: u32) {
+        self.steps = steps.max(1);
+    }
+    /// Sets whether tiny and non-finite values are flushed from the state with `flush`, which
+    /// original sfxr doesn't do. Default is `true`.
+    pub fn set_flush(&mut self, flush: bool) {
+        self.flush = flush;
+    }
     pub fn reset(
         &mut self,
         lpf_resonance: f32,
@@ -288,17 +1223,64 @@ impl HighLowPassFilter {
     ) {
         self.fltp = 0.0;
         self.fltdp = 0.0;
-        self.fltw = lpf_freq.powi(3) * 0.1;
-        self.fltw_d = 1.0 + lpf_ramp * 0.0001;
-
-        self.fltdmp = 5.0 / (1.0 + lpf_resonance.powi(2) * 20.0) * (0.01 + self.fltw);
-        if self.fltdmp > 0.8 {
-            self.fltdmp = 0.8;
-        }
+        self.set_low_pass(lpf_resonance, lpf_freq);
+        self.fltw_d = (1.0 + lpf_ramp * 0.0001).powi(self.steps as i32);
 
         self.fltphp = 0.0;
+        self.set_high_pass(hpf_freq);
+        self.flthp_d = (1.0 + hpf_ramp * 0.0003).powi(self.steps as i32);
+    }
+    /// Sets the low pass cutoff and resonance, keeping the filter state
+    pub fn set_low_pass(&mut self, lpf_resonance: f32, lpf_freq: f32) {
+        self.fltw = lpf_freq.powi(3) * 0.1;
+        self.fltdmp = (5.0 / (1.0 + lpf_resonance.powi(2) * 20.0) * (0.01 + self.fltw)).min(0.8);
+    }
+    /// Sets the high pass cutoff, keeping the filter state
+    pub fn set_high_pass(&mut self, hpf_freq: f32) {
         self.flthp = hpf_freq.powi(2) * 0.1;
-        self.flthp_d = 1.0 + hpf_ramp * 0.0003;
+    }
+    /// Moves the low pass cutoff along `ramp` instead of `lpf_ramp`, in `Sample::lpf_freq`
+    /// units. Call after `reset`.
+    pub fn set_lpf_ramp(&mut self, ramp: Option<Ramp>) {
+        if ramp.is_some() {
+            self.fltw_d = 1.0;
+        }
+        self.lpf_ramp = ramp;
+    }
+    /// Sets a low pass cutoff multiplier from modulation, kept across `reset`
+    pub fn set_cutoff_mod(&mut self, cutoff_mod: f32) {
+        self.cutoff_mod = cutoff_mod;
+    }
+    /// Advances the cutoff ramp by one sample
+    pub fn advance(&mut self) {
+        if let Some(ref mut ramp) = self.lpf_ramp {
+            self.fltw = ramp.advance().powi(3) * 0.1;
+        }
+    }
+    /// Magnitude response at `freq` cycles per supersample for the current cutoffs, ignoring
+    /// the ramps
+    pub fn response(&self, freq: f64) -> f64 {
+        let theta = 2.0 * std::f64::consts::PI * freq;
+        let (c1, s1) = (theta.cos(), theta.sin());
+        let (c2, s2) = ((2.0 * theta).cos(), (2.0 * theta).sin());
+
+        // p[n] = p[n-1] + (1 - d) * (p[n-1] - p[n-2] + w * (x[n] - p[n-1]))
+        let low = if self.fltw > 0.0 {
+            let w = f64::from(self.fltw.min(0.1));
+            let k = 1.0 - f64::from(self.fltdmp);
+            let a = 1.0 + k - k * w;
+            let re = 1.0 - a * c1 + k * c2;
+            let im = a * s1 - k * s2;
+            k * w / re.hypot(im)
+        } else {
+            1.0
+        };
+
+        // y[n] = (1 - h) * (y[n-1] + p[n] - p[n-1])
+        let k = 1.0 - f64::from(self.flthp.clamp(0.00001, 0.1));
+        let high = k * (1.0 - c1).hypot(s1) / (1.0 - k * c1).hypot(k * s1);
+
+        low * high
     }
 }
 impl Filter for HighLowPassFilter {
@@ -306,9 +1288,18 @@ impl Filter for HighLowPassFilter {
         let pp = self.fltp;
 
         if self.fltw > 0.0 {
-            self.fltw = (self.fltw * self.fltw_d).min(0.1).max(0.0);
-            self.fltdp += (sample - self.fltp) * self.fltw;
-            self.fltdp -= self.fltdp * self.fltdmp;
+            self.fltw = (self.fltw * self.fltw_d).clamp(0.0, 0.1);
+            let w = (self.fltw * self.cutoff_mod).min(0.1);
+            if self.steps == 1 {
+                self.fltdp += (sample - self.fltp) * w;
+                self.fltdp -= self.fltdp * self.fltdmp;
+            } else {
+                // The resonance frequency goes with the square root of `w` and the damping
+                // with `fltdmp`, per supersample
+                let steps = self.steps as f32;
+                self.fltdp += (sample - self.fltp) * (w * steps * steps).min(MAX_SCALED_FLTW);
+                self.fltdp -= self.fltdp * (self.fltdmp * steps).min(0.8);
+            }
         } else {
             self.fltp = sample;
             self.fltdp = 0.0;
@@ -317,10 +1308,20 @@ impl Filter for HighLowPassFilter {
         self.fltp += self.fltdp;
 
         // High pass filter
-        self.flthp = (self.flthp * self.flthp_d).min(0.1).max(0.00001);
+        self.flthp = (self.flthp * self.flthp_d).clamp(0.00001, 0.1);
         self.fltphp += self.fltp - pp;
-        self.fltphp -= self.fltphp * self.flthp;
+        if self.steps == 1 {
+            self.fltphp -= self.fltphp * self.flthp;
+        } else {
+            // The decay of `steps` original supersamples at once
+            self.fltphp -= self.fltphp * (1.0 - (1.0 - self.flthp).powi(self.steps as i32));
+        }
 
+        if self.flush {
+            self.fltp = flush(self.fltp);
+            self.fltdp = flush(self.fltdp);
+            self.fltphp = flush(self.fltphp);
+        }
         self.fltphp
     }
 }
@@ -330,15 +1331,29 @@ impl Phaser {
             ipp: 0,
             fphase: 0.0,
             fdphase: 0.0,
-            buffer: [0.0; 1024],
+            length: 1024,
+            stereo: 0.0,
+            steps: 1,
+            flush: true,
+            buffer: [0.0; INLINE_PHASER_LENGTH],
+            long_buffer: None,
         }
     }
-    pub fn reset(&mut self, pha_offset: f32, pha_ramp: f32) {
-        self.fphase = pha_offset.powi(2) * 1020.0;
-
-        if pha_offset < 0.0 {
-            self.fphase = -self.fphase
+    /// Restarts the offset sweep for a delay line of `length` samples, where the right
+    /// channel of `filter_stereo` is offset `stereo` times further than the left
+    pub fn reset(&mut self, pha_offset: f32, pha_ramp: f32, length: usize, stereo: f32) {
+        self.length = length.clamp(8, MAX_PHASER_LENGTH);
+        if self.length > INLINE_PHASER_LENGTH
+            && self
+                .long_buffer
+                .as_ref()
+                .is_none_or(|b| b.len() < self.length)
+        {
+            self.long_buffer = Some(vec![0.0; self.length].into_boxed_slice());
         }
+        self.ipp %= self.line().len();
+        self.stereo = stereo.clamp(-1.0, 1.0);
+        self.set_offset(pha_offset);
 
         self.fdphase = pha_ramp.powi(2) * 1.0;
 
@@ -347,17 +1362,77 @@ impl Phaser {
         }
     }
 
+    /// Moves the offset to `pha_offset`, from where `pha_ramp` continues
+    pub fn set_offset(&mut self, pha_offset: f32) {
+        self.fphase = pha_offset.powi(2) * (self.length - 4) as f32;
+
+        if pha_offset < 0.0 {
+            self.fphase = -self.fphase
+        }
+    }
     pub fn advance(&mut self) {
         self.fphase += self.fdphase;
     }
+    /// Sets the number of original sfxr supersamples each supersample stands for, above `1`
+    /// shortening the delay in supersamples to keep it in seconds
+    pub fn set_steps(&mut self, steps: u32) {
+        self.steps = steps.max(1);
+    }
+    /// Sets whether tiny and non-finite input is flushed with `flush` before it enters the
+    /// delay line, which original sfxr doesn't do. Default is `true`.
+    pub fn set_flush(&mut self, flush: bool) {
+        self.flush = flush;
+    }
+    /// The delay line in use for the current length
+    fn line(&self) -> &[f32] {
+        match self.long_buffer {
+            Some(ref buffer) if self.length > INLINE_PHASER_LENGTH => buffer,
+            _ => &self.buffer,
+        }
+    }
+    fn line_mut(&mut self) -> &mut [f32] {
+        match self.long_buffer {
+            Some(ref mut buffer) if self.length > INLINE_PHASER_LENGTH => buffer,
+            _ => &mut self.buffer,
+        }
+    }
+}
+impl Phaser {
+    /// Filters `sample` like `filter`, returning the left channel and a right channel whose
+    /// offset differs by the `stereo` factor
+    pub fn filter_stereo(&mut self, sample: f32) -> (f32, f32) {
+        let sample = if self.flush { flush(sample) } else { sample };
+        let ipp = self.ipp;
+        let line = self.line_mut();
+        line[ipp] = sample;
+        let p_len = line.len();
+        let left = sample + self.delayed(self.fphase);
+        let right = sample + self.delayed(self.fphase * (1.0 + self.stereo));
+        self.ipp = (ipp + 1) % p_len;
+        (left, right)
+    }
+    /// Sample written `fphase` samples before the latest
+    fn delayed(&self, fphase: f32) -> f32 {
+        let line = self.line();
+        let p_len = line.len();
+        let fphase = if self.steps == 1 {
+            fphase
+        } else {
+            fphase / self.steps as f32
+        };
+        let iphase = (fphase.abs() as i32).min(self.length as i32 - 1);
+        line[(self.ipp + p_len - iphase as usize) % p_len]
+    }
 }
 impl Filter for Phaser {
     fn filter(&mut self, sample: f32) -> f32 {
-        let p_len = self.buffer.len();
-        self.buffer[self.ipp % p_len] = sample;
-        let iphase = (self.fphase.abs() as i32).min(p_len as i32 - 1);
-        let result = sample + self.buffer[(self.ipp + p_len - iphase as usize) % p_len];
-        self.ipp = (self.ipp + 1) % p_len;
+        let sample = if self.flush { flush(sample) } else { sample };
+        let ipp = self.ipp;
+        let line = self.line_mut();
+        let p_len = line.len();
+        line[ipp % p_len] = sample;
+        let result = sample + self.delayed(self.fphase);
+        self.ipp = (ipp + 1) % p_len;
         result
     }
 }
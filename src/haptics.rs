@@ -0,0 +1,73 @@
+//! Conversion of sound effects to game controller rumble
+//!
+//! Controllers usually have a low frequency (heavy) and a high frequency (light) rumble motor.
+//! The intensity of each is derived from the amplitude of the rendered sound, split between the
+//! motors according to how bright the sound is at that moment.
+
+use std::convert::TryFrom;
+
+use crate::{Generator, Sample, SAMPLE_RATE};
+
+/// Renders `sample` and converts it to a rumble envelope with one `(low, high)` motor intensity
+/// pair per `resolution_ms` milliseconds. Intensities are between `0.0` and `1.0`, normalized so
+/// that the loudest moment of the sound has a combined intensity of `1.0`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{haptics, Sample};
+/// let rumble = haptics::to_rumble(&Sample::explosion(None), 10);
+/// assert!(rumble.iter().all(|&(low, high)| low <= 1.0 && high <= 1.0));
+/// // The whole sound in one frame
+/// assert_eq!(haptics::to_rumble(&Sample::explosion(None), u32::MAX).len(), 1);
+/// ```
+pub fn to_rumble(sample: &Sample, resolution_ms: u32) -> Vec<(f32, f32)> {
+    assert!(resolution_ms > 0, "resolution_ms must be greater than zero");
+
+    let window = u64::from(SAMPLE_RATE) * u64::from(resolution_ms) / 1000;
+    let window = usize::try_from(window.max(1)).unwrap_or(usize::MAX);
+    let mut buffer = vec![0.0; sample.envelope_length()];
+    Generator::new(sample.clone()).generate(&mut buffer);
+
+    let frames: Vec<(f32, f32)> = buffer
+        .chunks(window)
+        .map(|chunk| {
+            let amplitude = rms(chunk.iter().copied());
+            let slope = rms(chunk.windows(2).map(|pair| pair[1] - pair[0]));
+            // The slope of a signal at the Nyquist frequency is twice its amplitude
+            let brightness = if amplitude > 0.0 {
+                (slope / (2.0 * amplitude)).min(1.0)
+            } else {
+                0.0
+            };
+            (amplitude, brightness)
+        })
+        .collect();
+
+    let loudest = frames
+        .iter()
+        .map(|&(amplitude, _)| amplitude)
+        .fold(0.0, f32::max);
+
+    frames
+        .into_iter()
+        .map(|(amplitude, brightness)| {
+            let intensity = if loudest > 0.0 {
+                amplitude / loudest
+            } else {
+                0.0
+            };
+            (intensity * (1.0 - brightness), intensity * brightness)
+        })
+        .collect()
+}
+
+/// Root mean square of `values`
+fn rms(values: impl Iterator<Item = f32>) -> f32 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v * v, count + 1));
+    if count > 0 {
+        (sum / count as f32).sqrt()
+    } else {
+        0.0
+    }
+}
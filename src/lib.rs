@@ -34,6 +34,27 @@
 //! let mut  buffer = [0.0; 44_100];
 //! generator.generate(&mut buffer);
 //! ```
+//!
+//! Rendering a whole sound effect at 48 kHz, with no audio device or files involved, for
+//! example to generate assets on a server or in a `wasm32-wasip1` sandbox
+//!
+//! ``` rust
+//! let samples = sfxr::render(&sfxr::Sample::new(), 48_000);
+//! assert!(!samples.is_empty());
+//! ```
+//!
+//! # Features
+//!
+//! - `rand` (default): random constructors like `Sample::explosion`, `Sample::mutate`,
+//!   `VariedSample` and the `fit` module. Without it the `rand` dependency is dropped and
+//!   Samples are built from `Sample::new` and manual field setting.
+//! - `json`: loading and saving `PresetDef`s and `SoundBank`s as JSON.
+//! - `opus`: encoding to Opus frames with the `opus` module. Needs the libopus C library.
+//! - `device`: the `device` module, for playing on any `cpal` output device.
+//! - `script`: the `script` module, for building `SoundBank`s with Rhai scripts.
+//! - `watch`: `SoundBank::watch`, for reloading changed sound files while a game is running.
+//! - `heapless`: limits `Sample::pha_length` to `1024`, so playing a Sample never allocates
+//!   heap memory. See the memory section of `Generator`.
 
 #![deny(
     rust_2018_compatibility,
@@ -44,17 +65,117 @@
     clippy::all
 )]
 
-use rand::rngs::SmallRng;
-use rand::{Rng, SeedableRng};
-
+use std::convert::TryFrom;
+use std::path::Path;
+
+#[cfg(feature = "rand")]
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+pub mod analysis;
+pub mod audacity;
+mod automation;
+pub mod bake;
+mod bank;
+mod chain;
+mod clipboard;
+pub mod compat;
+mod composite;
+mod compressor;
+mod controller;
+mod cost;
+mod curve;
+mod decimator;
+#[cfg(feature = "rand")]
+mod descriptor;
+#[cfg(feature = "device")]
+pub mod device;
+mod drone;
+pub mod edit;
+mod equalizer;
+mod error;
+#[cfg(feature = "rand")]
+pub mod fit;
 mod generator;
-
-pub use generator::WaveType;
-
-use generator::{Envelope, Filterable, HighLowPassFilter, Oscillator, Phaser};
+pub mod haptics;
+mod meter;
+mod mixer;
+mod note;
+#[cfg(feature = "opus")]
+pub mod opus;
+mod param;
+mod pool;
+pub mod preset;
+mod prng;
+mod render;
+pub mod rt;
+mod scale;
+#[cfg(feature = "script")]
+pub mod script;
+mod sequence;
+pub mod sfs;
+pub mod spatial;
+mod stereo;
+pub mod test_util;
+pub mod tracker;
+mod typed;
+#[cfg(feature = "rand")]
+mod variation;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use automation::{Automation, Keyframe};
+pub use bank::{SampleMeta, SoundBank};
+pub use chain::ChainedGenerator;
+pub use composite::{CompositeGenerator, CompositeSample, Layer};
+pub use compressor::Compressor;
+pub use drone::{Drone, DroneConfig};
+pub use equalizer::Equalizer;
+pub use error::Error;
+pub use generator::{
+    ArpDirection, ArpMode, Easing, EnvelopeStage, FreqLimitBehavior, NoiseMode, NoiseTable,
+    RampTarget, RepeatMode, SampleHold, SlideMode, WaveType,
+};
+pub use meter::{Level, StageLevels};
+pub use mixer::{Mixer, VoiceStealing};
+pub use note::NoteEvent;
+pub use param::{FieldChange, FieldMask, MutationReport, Param, ParamRanges};
+pub use pool::{GeneratorPool, Handle};
+pub use preset::{Category, GenerationTrace, PresetDef};
+pub use prng::Prng;
+pub use render::Renderer;
+pub use scale::{Key, Note, Scale};
+pub use sequence::Sequence;
+pub use stereo::Widener;
+pub use typed::{
+    FiniteF32, RangeError, SignedUnitF32, SignedUnitF64, TypedSample, UnitF32, UnitF64,
+};
+#[cfg(feature = "rand")]
+pub use variation::VariedSample;
+#[cfg(feature = "watch")]
+pub use watch::{BankWatcher, LiveSample};
+
+use automation::Automated;
+use compressor::CompressorFilter;
+use controller::Controllers;
+use decimator::Decimator;
+use equalizer::EqualizerFilter;
+use generator::{
+    flush, Envelope, Filter, HighLowPassFilter, Oscillator, Phaser, Ramp, SampleHoldLfo,
+    VolumeSteps,
+};
+use meter::Meters;
+
+/// Sample rate of the generated sound data in Hz
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Number of oscillator supersamples averaged into each output sample
+const OVERSAMPLING: usize = 8;
 
 /// Defines a sound effect configuration for a Generator
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Sample {
     /// Oscillator wave type
     pub wave_type: WaveType,
@@ -110,12 +231,112 @@ pub struct Sample {
     pub arp_speed: f32,
     /// Arpeggio step in frequency. Value must be between `-1.0` and `1.0`.
     pub arp_mod: f64,
+
+    /// Number of slightly detuned copies of the oscillator summed together, for a thicker
+    /// sound. `1` disables unison, `0` is treated as `1` and values above
+    /// `Sample::MAX_UNISON_VOICES` as the maximum.
+    pub unison_voices: u8,
+    /// Unison detune spread. `1.0` spreads the voices up to half a semitone above and below
+    /// the base frequency. Value must be between `0.0` and `1.0`.
+    pub unison_detune: f32,
+
+    /// Volume of this sound, as the sound volume slider of original sfxr and `.sfs` files. The
+    /// output is scaled by `2.0 * volume`, so the default `0.5` leaves it unchanged. This is
+    /// separate from `Generator::volume`, which applies to the whole playback. Value must be
+    /// between `0.0` and `1.0`.
+    pub volume: f32,
+
+    /// Restart the noise from the same random sequence on every repeat and reset, so each
+    /// repeat of a noise sound is identical, giving a "machine gun" texture. By default the
+    /// noise continues, and repeats differ.
+    pub noise_retrigger: bool,
+    /// Seed of the noise waveform. With a seed, the noise restarts from it on every reset, so
+    /// the sound is reproducible, and different seeds give different noise for otherwise
+    /// identical Samples. `None` continues the Generator's own sequence across resets.
+    pub noise_seed: Option<u64>,
+    /// Whether `WaveType::Noise` is pitched by the oscillator frequency, as in original sfxr,
+    /// or continuous white noise
+    pub noise_mode: NoiseMode,
+    /// How `freq_ramp` and `freq_dramp` slide the frequency. `SlideMode::Legacy` matches
+    /// original sfxr, the others make glides that are easier to aim at a target note.
+    pub slide_mode: SlideMode,
+    /// Moves the duty cycle from `duty` to a target value over time, replacing `duty_ramp`
+    pub duty_target: Option<RampTarget>,
+    /// Moves the low pass filter cutoff from `lpf_freq` to a target value over time, replacing
+    /// `lpf_ramp`
+    pub lpf_target: Option<RampTarget>,
+    /// Three band equalizer applied at the end of the synthesis chain. Default is `None`.
+    pub equalizer: Option<Equalizer>,
+    /// Dynamics compressor applied after `equalizer`. Default is `None`.
+    pub compressor: Option<Compressor>,
+    /// Random stepped modulation of the pitch or filter cutoff. Default is `SampleHold::Off`.
+    pub sample_hold: SampleHold,
+    /// Time each random `sample_hold` value is held, at least one sample. Default is `0.05`.
+    pub sample_hold_secs: f32,
+    /// What restarts on each repeat. Default is `RepeatMode::Partial`.
+    pub repeat_mode: RepeatMode,
+    /// Whether the arpeggio steps once or cycles through notes. Default is `ArpMode::Once`.
+    pub arp_mode: ArpMode,
+    /// Length of the phaser delay line in samples, which is reached at a `pha_offset` of
+    /// `1.0`. Longer lines give deeper flanger sweeps. Values are limited to between `8` and
+    /// `Sample::MAX_PHASER_LENGTH`, and lengths above `1024` allocate their delay line. Default
+    /// is `1024`, as original sfxr.
+    pub pha_length: u16,
+    /// Phaser offset of the right channel in `Generator::generate_stereo`, relative to the
+    /// left: the right offset is `1.0 + pha_stereo` times the left. Value must be between
+    /// `-1.0` and `1.0`. Default is `0.0`.
+    pub pha_stereo: f32,
+    /// Additional gain of this sound in decibels, applied along with `volume`. Unlike
+    /// `volume`, it can boost the sound by more than 6 dB, so it can hold a `Generator::volume`
+    /// with `Generator::store_volume`. Value must be finite. Default is `0.0`.
+    pub gain_db: f32,
+    /// Values played by `WaveType::Noise` in place of random noise, such as a short recorded
+    /// texture. `noise_seed` picks the starting offset. Default is `None`.
+    pub custom_noise: Option<NoiseTable>,
+    /// Snaps the oscillator pitch to the nearest note of the key while playing, including
+    /// slides and arpeggio steps, so sweeps become runs of notes. Vibrato is applied after
+    /// snapping. Default is `None`.
+    pub snap_to_key: Option<Key>,
+    /// Whether the sound ends or holds its pitch when a downward slide reaches `freq_limit`.
+    /// Default is `FreqLimitBehavior::EndSound`, as in original sfxr.
+    pub freq_limit_behavior: FreqLimitBehavior,
+}
+
+/// Constructs a `Sample` from the given fields, taking the rest from `Sample::new`.
+///
+/// The expansion is a constant expression, so Samples can be defined in `const` and `static`
+/// items without any runtime initialization.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{sample, Sample, WaveType};
+/// static COIN: Sample = sample! {
+///     wave_type: WaveType::Sine,
+///     base_freq: 0.6,
+///     env_attack: 0.0,
+///     env_decay: 0.2,
+/// };
+/// assert_eq!(COIN.env_sustain, Sample::new().env_sustain);
+/// ```
+#[macro_export]
+macro_rules! sample {
+    ($($field:ident : $value:expr),* $(,)?) => {{
+        let mut sample = $crate::Sample::new();
+        $(sample.$field = $value;)*
+        sample
+    }};
 }
 
 #[allow(clippy::new_without_default)]
 impl Sample {
-    /// Constructs a new Sample with default settings
-    pub fn new() -> Sample {
+    /// Largest effective `unison_voices` value
+    pub const MAX_UNISON_VOICES: u8 = generator::MAX_UNISON_VOICES as u8;
+    /// Largest effective `pha_length` value
+    pub const MAX_PHASER_LENGTH: u16 = generator::MAX_PHASER_LENGTH as u16;
+
+    /// Constructs a new Sample with default settings. Usable in constant expressions.
+    pub const fn new() -> Sample {
         Sample {
             wave_type: WaveType::Square,
             base_freq: 0.3,
@@ -147,395 +368,882 @@ impl Sample {
 
             arp_speed: 0.0,
             arp_mod: 0.0,
+
+            unison_voices: 1,
+            unison_detune: 0.0,
+
+            volume: 0.5,
+
+            noise_retrigger: false,
+            noise_seed: None,
+            noise_mode: NoiseMode::Pitched,
+            slide_mode: SlideMode::Legacy,
+            duty_target: None,
+            lpf_target: None,
+            equalizer: None,
+            compressor: None,
+            sample_hold: SampleHold::Off,
+            sample_hold_secs: 0.05,
+            repeat_mode: RepeatMode::Partial,
+            arp_mode: ArpMode::Once,
+            pha_length: 1024,
+            pha_stereo: 0.0,
+            gain_db: 0.0,
+            custom_noise: None,
+            snap_to_key: None,
+            freq_limit_behavior: FreqLimitBehavior::EndSound,
         }
     }
 
-    /// Asserts all fields' values to be within correct values
-    fn assert_valid(&self) {
-        assert!(
-            self.base_freq >= 0.0 && self.base_freq <= 1.0,
-            "base_freq must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.freq_limit >= 0.0 && self.freq_limit <= 1.0,
-            "freq_limit must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.freq_ramp >= -1.0 && self.freq_ramp <= 1.0,
-            "freq_ramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.freq_dramp >= -1.0 && self.freq_dramp <= 1.0,
-            "freq_dramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.duty >= 0.0 && self.duty <= 1.0,
-            "duty must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.duty_ramp >= -1.0 && self.duty_ramp <= 1.0,
-            "duty_ramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.vib_strength >= 0.0 && self.vib_strength <= 1.0,
-            "vib_strength must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.vib_speed >= 0.0 && self.vib_speed <= 1.0,
-            "vib_speed must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.vib_delay >= 0.0 && self.vib_delay <= 1.0,
-            "vib_delay must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.env_attack >= 0.0 && self.env_attack <= 1.0,
-            "env_attack must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.env_sustain >= 0.0 && self.env_sustain <= 1.0,
-            "env_sustain must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.env_decay >= 0.0 && self.env_decay <= 1.0,
-            "env_decay must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.env_punch >= -1.0 && self.env_punch <= 1.0,
-            "env_punch must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.lpf_resonance >= 0.0 && self.lpf_resonance <= 1.0,
-            "lpf_resonance must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.lpf_freq >= 0.0 && self.lpf_freq <= 1.0,
-            "lpf_freq must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.lpf_ramp >= -1.0 && self.lpf_ramp <= 1.0,
-            "lpf_ramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.hpf_freq >= 0.0 && self.hpf_freq <= 1.0,
-            "hpf_freq must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.hpf_ramp >= -1.0 && self.hpf_ramp <= 1.0,
-            "hpf_ramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.pha_offset >= -1.0 && self.pha_offset <= 1.0,
-            "pha_offset must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.pha_ramp >= -1.0 && self.pha_ramp <= 1.0,
-            "pha_ramp must be between -1.0 and 1.0"
-        );
-        assert!(
-            self.repeat_speed >= 0.0 && self.repeat_speed <= 1.0,
-            "repeat_speed must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.arp_speed >= 0.0 && self.arp_speed <= 1.0,
-            "arp_speed must be between 0.0 and 1.0"
-        );
-        assert!(
-            self.arp_mod >= -1.0 && self.arp_mod <= 1.0,
-            "arp_mod must be between -1.0 and 1.0"
-        );
+    /// Constructs a new noise Sample playing `noise` in place of random noise, for example a
+    /// short recording loaded with `bake::read_wav`. See `NoiseTable`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{bake, Generator, Sample};
+    /// let mut wav = Vec::new();
+    /// let gravel: Vec<f32> = (0..512).map(|i| ((i * 7_919) % 200) as f32 / 100.0 - 1.0).collect();
+    /// bake::write_wav(&mut wav, &gravel, 44_100).unwrap();
+    ///
+    /// let (recording, _rate) = bake::read_wav(&wav[..]).unwrap();
+    /// let sample = Sample::with_custom_noise(&recording);
+    /// Generator::new(sample).generate(&mut [0.0; 1_024]);
+    /// ```
+    pub fn with_custom_noise(noise: &[f32]) -> Sample {
+        let mut sample = Sample::new();
+        sample.wave_type = WaveType::Noise;
+        sample.custom_noise = Some(NoiseTable::new(noise));
+        sample
     }
 
-    /// Changes Sample fields randomly by a little
-    pub fn mutate(&mut self, seed: Option<u64>) {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-
-        fn mutate_f64(rng: &mut SmallRng, v: &mut f64, min: f64, max: f64) {
-            if rand_bool(rng, 1, 1) {
-                *v = (*v + rand_f64(rng, -0.05, 0.05)).min(max).max(min);
-            }
-        }
-        fn mutate_f32(rng: &mut SmallRng, v: &mut f32, min: f32, max: f32) {
-            if rand_bool(rng, 1, 1) {
-                *v = (*v + rand_f32(rng, -0.05, 0.05)).min(max).max(min);
-            }
-        }
+    /// Constructs a new Sample sweeping from `start_hz` to `end_hz` in `duration_secs` seconds.
+    ///
+    /// The envelope sustains for most of the duration and decays quickly at the end. Frequencies
+    /// are clamped to the range supported by `base_freq`, and the slide to what `freq_ramp` can
+    /// represent. Durations are clamped to at most about 4.5 seconds.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Sample, WaveType};
+    /// let sample = Sample::sweep(880.0, 220.0, 0.5, WaveType::Sine);
+    /// assert!((Sample::freq_to_hz(sample.freq_limit) - 220.0).abs() < 1.0);
+    /// ```
+    pub fn sweep(start_hz: f64, end_hz: f64, duration_secs: f64, wave_type: WaveType) -> Sample {
+        assert!(
+            duration_secs > 0.0,
+            "duration_secs must be greater than zero"
+        );
 
-        mutate_f64(rng, &mut self.base_freq, 0.0, 1.0);
-        // Commented out in sfxr?
-        // mutate_f64(rng, &mut self.freq_limit);
-        mutate_f64(rng, &mut self.freq_ramp, -1.0, 1.0);
-        mutate_f64(rng, &mut self.freq_dramp, 0.0, 1.0);
-        mutate_f32(rng, &mut self.duty, 0.0, 1.0);
-        mutate_f32(rng, &mut self.duty_ramp, -1.0, 1.0);
-        mutate_f64(rng, &mut self.vib_strength, 0.0, 1.0);
-        mutate_f64(rng, &mut self.vib_speed, 0.0, 1.0);
-        mutate_f32(rng, &mut self.vib_delay, 0.0, 1.0);
-        mutate_f32(rng, &mut self.env_attack, 0.0, 1.0);
-        mutate_f32(rng, &mut self.env_sustain, 0.0, 1.0);
-        mutate_f32(rng, &mut self.env_decay, 0.0, 1.0);
-        mutate_f32(rng, &mut self.env_punch, -1.0, 1.0);
-        mutate_f32(rng, &mut self.lpf_resonance, 0.0, 1.0);
-        mutate_f32(rng, &mut self.lpf_freq, 0.0, 1.0);
-        mutate_f32(rng, &mut self.lpf_ramp, -1.0, 1.0);
-        mutate_f32(rng, &mut self.hpf_freq, 0.0, 1.0);
-        mutate_f32(rng, &mut self.hpf_ramp, -1.0, 1.0);
-        mutate_f32(rng, &mut self.pha_offset, -1.0, 1.0);
-        mutate_f32(rng, &mut self.pha_ramp, 0.0, 1.0);
-        mutate_f32(rng, &mut self.repeat_speed, 0.0, 1.0);
-        mutate_f32(rng, &mut self.arp_speed, 0.0, 1.0);
-        mutate_f64(rng, &mut self.arp_mod, -1.0, 1.0);
-    }
-
-    /// Constructs a new random "coin" or "item pickup" style sample using optional random seed
-    pub fn pickup(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
         let mut s = Sample::new();
+        s.wave_type = wave_type;
+        s.base_freq = Sample::freq_from_hz(start_hz);
+        s.slide_to(end_hz, duration_secs);
 
-        s.base_freq = rand_f64(rng, 0.4, 0.9);
+        let samples = duration_secs * f64::from(SAMPLE_RATE);
+        let stage = |length: f64| ((length / 100_000.0).sqrt() as f32).min(1.0);
         s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.0, 0.1);
-        s.env_decay = rand_f32(rng, 0.1, 0.5);
-        s.env_punch = rand_f32(rng, 0.3, 0.6);
-
-        if rand_bool(rng, 1, 1) {
-            s.arp_speed = rand_f32(rng, 0.5, 0.7);
-            s.arp_mod = rand_f64(rng, 0.2, 0.6);
-        }
+        s.env_sustain = stage(samples * 0.9);
+        s.env_decay = stage(samples * 0.1);
 
         s
     }
 
-    /// Constructs a new random "shoot" or "laser" style sample using optional random seed
-    pub fn laser(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
+    /// Sets `freq_ramp` so that the frequency slides from `base_freq` to `end_hz` in
+    /// `duration_secs` seconds according to `slide_mode`, and clears `freq_dramp`.
+    ///
+    /// When sliding down, `freq_limit` is set to `end_hz` and `freq_limit_behavior` to
+    /// `FreqLimitBehavior::Clamp`, so the pitch stays at the target afterwards. Sliding up
+    /// continues past it until the sound ends. Slides faster than `freq_ramp` can represent are
+    /// clamped.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Sample, SlideMode};
+    /// let mut glide = Sample::new();
+    /// glide.base_freq = Sample::freq_from_hz(440.0);
+    /// glide.slide_mode = SlideMode::LinearSemitones;
+    /// glide.slide_to(220.0, 0.25);
+    /// ```
+    pub fn slide_to(&mut self, end_hz: f64, duration_secs: f64) {
+        assert!(
+            duration_secs > 0.0,
+            "duration_secs must be greater than zero"
+        );
 
-        let wave_types = {
-            use WaveType::*;
-            [Square, Square, Sine, Sine, Triangle]
+        let end_freq = Sample::freq_from_hz(end_hz);
+        let samples = duration_secs * f64::from(SAMPLE_RATE);
+        let start_period = Oscillator::period(self.base_freq);
+        let end_period = Oscillator::period(end_freq);
+        let slide = match self.slide_mode {
+            SlideMode::Legacy | SlideMode::LinearSemitones => {
+                (end_period / start_period).powf(1.0 / samples)
+            }
+            SlideMode::LinearHz => {
+                let rate = (1.0 / end_period - 1.0 / start_period) / samples;
+                1.0 / (1.0 + rate * start_period)
+            }
         };
-        s.wave_type = rand_element(rng, &wave_types);
+        self.freq_ramp = ((1.0 - slide) * 100.0).cbrt().clamp(-1.0, 1.0);
+        self.freq_dramp = 0.0;
 
-        if rand_bool(rng, 1, 2) {
-            s.base_freq = rand_f64(rng, 0.3, 0.9);
-            s.freq_limit = rand_f64(rng, 0.0, 0.1);
-            s.freq_ramp = rand_f64(rng, -0.35, -0.65);
-        } else {
-            s.base_freq = rand_f64(rng, 0.5, 1.0);
-            s.freq_limit = (s.base_freq - rand_f64(rng, 0.2, 0.8)).max(0.2);
-            s.freq_ramp = rand_f64(rng, -0.15, -0.35);
-        }
-
-        if rand_bool(rng, 1, 1) {
-            s.duty = rand_f32(rng, 0.0, 0.5);
-            s.duty_ramp = rand_f32(rng, 0.0, 0.2);
-        } else {
-            s.duty = rand_f32(rng, 0.4, 0.9);
-            s.duty_ramp = rand_f32(rng, 0.0, -0.7);
+        // Sliding down ends at the frequency limit, while sliding up is cut by the envelope
+        if end_freq < self.base_freq {
+            self.freq_limit = end_freq;
+            self.freq_limit_behavior = FreqLimitBehavior::Clamp;
         }
+    }
 
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.1, 0.3);
-        s.env_decay = rand_f32(rng, 0.0, 0.4);
+    /// Checks all fields' values to be within correct values, like `TypedSample::try_from`
+    fn validate(&self) -> Result<(), RangeError> {
+        TypedSample::try_from(self.clone()).map(|_| ())
+    }
 
-        if rand_bool(rng, 1, 1) {
-            s.env_punch = rand_f32(rng, 0.0, 0.3);
+    /// Asserts all fields' values to be within correct values
+    fn assert_valid(&self) {
+        if let Err(e) = self.validate() {
+            panic!("invalid Sample: {}", e);
         }
+    }
 
-        if rand_bool(rng, 1, 2) {
-            s.pha_offset = rand_f32(rng, 0.0, 0.2);
-            s.pha_ramp = -rand_f32(rng, 0.0, 0.2);
-        }
+    /// Converts a `base_freq` or `freq_limit` value to a frequency in Hz
+    pub fn freq_to_hz(freq: f64) -> f64 {
+        (SAMPLE_RATE as usize * OVERSAMPLING) as f64 / Oscillator::period(freq)
+    }
+    /// Converts a frequency in Hz to a `base_freq` or `freq_limit` value. Frequencies outside
+    /// the representable range of roughly 3.5 Hz to 3.5 kHz are clamped.
+    pub fn freq_from_hz(hz: f64) -> f64 {
+        let period = (SAMPLE_RATE as usize * OVERSAMPLING) as f64 / hz;
+        (100.0 / period - 0.001).max(0.0).sqrt().min(1.0)
+    }
 
-        if rand_bool(rng, 1, 1) {
-            s.hpf_freq = rand_f32(rng, 0.0, 0.3);
+    /// Moves `base_freq` and the arpeggio target to the nearest notes of `scale` starting at
+    /// `root`, so a random sound plays in key. Slides are not changed, see `snap_to_key` for
+    /// snapping them too.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Note, Sample, Scale};
+    /// let mut coin = Sample::new();
+    /// coin.base_freq = 0.43;
+    /// coin.arp_mod = 0.4;
+    /// coin.quantize_pitch(Scale::MajorPentatonic, Note::G);
+    /// ```
+    pub fn quantize_pitch(&mut self, scale: Scale, root: Note) {
+        let key = Key::new(root, scale);
+        let base = scale::hz_to_midi(Sample::freq_to_hz(self.base_freq));
+        let snapped = key.snap(base);
+        self.base_freq = Sample::freq_from_hz(scale::midi_to_hz(snapped));
+
+        // The arpeggio multiplies the period by `factor`, raising the pitch
+        let factor = Oscillator::arp_factor(self.arp_mod);
+        if self.arp_mod != 0.0 && factor > 0.0 {
+            let interval = key.snap(base - 12.0 * factor.log2()) - snapped;
+            let factor = (-interval / 12.0).exp2();
+            self.arp_mod = ((1.0 - factor) / 0.9).max(0.0).sqrt().min(1.0);
         }
+    }
 
-        s
+    /// Sets `repeat_speed` so that the sound repeats every `beats` beats at tempo `bpm`.
+    ///
+    /// Intervals longer than about 14.5 seconds can't be represented and are clamped.
+    pub fn set_repeat_beats(&mut self, bpm: f32, beats: f32) {
+        let samples = beats_to_samples(bpm, beats).clamp(1.0, 20_000.0 * 32.0 - 1.0);
+        self.repeat_speed = 1.0 - ((samples + 0.5) / (20_000.0 * 32.0)).sqrt();
+    }
+    /// Sets `arp_speed` so that the arpeggio triggers `beats` beats after the sound starts at
+    /// tempo `bpm`.
+    ///
+    /// Delays shorter than 33 samples or longer than about 0.45 seconds can't be represented and
+    /// are clamped.
+    pub fn set_arp_beats(&mut self, bpm: f32, beats: f32) {
+        let samples = beats_to_samples(bpm, beats).clamp(33.0, 20_032.0);
+        self.arp_speed = 1.0 - ((samples + 0.5 - 32.0) / 20_000.0).sqrt().min(1.0);
     }
 
-    /// Constructs a new random "explosion" style sample using optional random seed
-    pub fn explosion(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
+    /// Returns the length of the amplitude envelope in samples, after which the sound is silent
+    pub(crate) fn envelope_length(&self) -> usize {
+        [self.env_attack, self.env_sustain, self.env_decay]
+            .iter()
+            .map(|&duration| Envelope::stage_length(duration) as usize)
+            .sum()
+    }
 
-        s.wave_type = WaveType::Noise;
+    /// Changes Sample fields randomly by a little using optional random seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn mutate(&mut self, seed: Option<u64>) {
+        self.mutate_with_rng(&mut Prng::new(seed_or_random(seed)));
+    }
 
-        if rand_bool(rng, 1, 1) {
-            s.base_freq = rand_f64(rng, 0.1, 0.5);
-            s.freq_ramp = rand_f64(rng, -0.1, 0.3);
-        } else {
-            s.base_freq = rand_f64(rng, 0.2, 0.9);
-            s.freq_ramp = rand_f64(rng, -0.2, -0.4);
+    /// Changes Sample fields randomly by a little using `rng`. Returns the changed fields.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Prng, Sample};
+    /// let mut sample = Sample::new();
+    /// let report = sample.mutate_with_rng(&mut Prng::new(3));
+    /// report.undo(&mut sample);
+    /// assert_eq!(sample.base_freq, Sample::new().base_freq);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn mutate_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) -> MutationReport {
+        // Ranges as in sfxr, which does not mutate freq_limit
+        const FIELDS: [(Param, f64, f64); 22] = [
+            (Param::BaseFreq, 0.0, 1.0),
+            (Param::FreqRamp, -1.0, 1.0),
+            (Param::FreqDramp, 0.0, 1.0),
+            (Param::Duty, 0.0, 1.0),
+            (Param::DutyRamp, -1.0, 1.0),
+            (Param::VibStrength, 0.0, 1.0),
+            (Param::VibSpeed, 0.0, 1.0),
+            (Param::VibDelay, 0.0, 1.0),
+            (Param::EnvAttack, 0.0, 1.0),
+            (Param::EnvSustain, 0.0, 1.0),
+            (Param::EnvDecay, 0.0, 1.0),
+            (Param::EnvPunch, -1.0, 1.0),
+            (Param::LpfResonance, 0.0, 1.0),
+            (Param::LpfFreq, 0.0, 1.0),
+            (Param::LpfRamp, -1.0, 1.0),
+            (Param::HpfFreq, 0.0, 1.0),
+            (Param::HpfRamp, -1.0, 1.0),
+            (Param::PhaOffset, -1.0, 1.0),
+            (Param::PhaRamp, 0.0, 1.0),
+            (Param::RepeatSpeed, 0.0, 1.0),
+            (Param::ArpSpeed, 0.0, 1.0),
+            (Param::ArpMod, -1.0, 1.0),
+        ];
+
+        let mut report = MutationReport::default();
+        for &(param, min, max) in FIELDS.iter() {
+            if !rand_bool(rng, 1, 1) {
+                continue;
+            }
+            let old = self.param(param);
+            let new = if param.is_f64() {
+                (old + rand_f64(rng, -0.05, 0.05)).clamp(min, max)
+            } else {
+                let v = old as f32 + rand_f32(rng, -0.05, 0.05);
+                f64::from(v.clamp(min as f32, max as f32))
+            };
+            if new != old {
+                self.set_param(param, new);
+                report.changes.push(FieldChange { param, old, new });
+            }
         }
+        report
+    }
 
-        s.base_freq = s.base_freq.powi(2);
+    /// Randomizes the fields of `category` again using `rng`, except the `locked` ones, like
+    /// the lock toggles of bfxr. See `PresetDef::reroll`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Category, FieldMask, Param, Prng, Sample};
+    /// let mut sample = Sample::laser(Some(1));
+    /// let locked = FieldMask::new()
+    ///     .with(Param::WaveType)
+    ///     .with(Param::BaseFreq);
+    /// let before = sample.clone();
+    /// sample.reroll(locked, Category::Laser, &mut Prng::new(2));
+    /// assert_eq!(sample.wave_type, before.wave_type);
+    /// assert_eq!(sample.base_freq, before.base_freq);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn reroll<R: Rng + ?Sized>(&mut self, locked: FieldMask, category: Category, rng: &mut R) {
+        category.def().reroll(self, locked, rng);
+    }
 
-        if rand_bool(rng, 1, 4) {
-            s.freq_ramp = 0.0;
-        }
+    /// Constructs a new random sample in a category picked randomly using optional random seed
+    /// (random if `None`).
+    /// Each category in `weights` is picked with probability proportional to its weight.
+    /// Returns the picked category along with the sample.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Category, Sample};
+    /// let weights = [(Category::Pickup, 3), (Category::Blip, 1)];
+    /// let (category, sample) = Sample::random_category(&weights, Some(7));
+    /// assert!(category == Category::Pickup || category == Category::Blip);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn random_category(weights: &[(Category, u32)], seed: Option<u64>) -> (Category, Sample) {
+        let total: u32 = weights.iter().map(|&(_, weight)| weight).sum();
+        assert!(total > 0, "weights must not all be zero");
+
+        let rng = &mut Prng::new(seed_or_random(seed));
+        let mut pick = rng.next_u32() % total;
+        let category = weights
+            .iter()
+            .find(|&&(_, weight)| {
+                let found = pick < weight;
+                pick = pick.saturating_sub(weight);
+                found
+            })
+            .map(|&(category, _)| category)
+            .expect("pick is less than total weight");
+
+        (category, category.def().generate(rng))
+    }
 
-        if rand_bool(rng, 1, 2) {
-            s.repeat_speed = rand_f32(rng, 0.3, 0.8);
-        }
+    /// Constructs a new random "coin" or "item pickup" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn pickup(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Pickup.def().generate(rng)
+    }
 
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.1, 0.4);
-        s.env_decay = rand_f32(rng, 0.0, 0.5);
+    /// Constructs a new random "shoot" or "laser" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn laser(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Laser.def().generate(rng)
+    }
 
-        if rand_bool(rng, 1, 1) {
-            s.pha_offset = rand_f32(rng, -0.3, 0.6);
-            s.pha_ramp = rand_f32(rng, -0.3, 0.0);
-        }
+    /// Constructs a new random "explosion" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn explosion(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Explosion.def().generate(rng)
+    }
 
-        s.env_punch = rand_f32(rng, 0.2, 0.8);
+    /// Constructs a new random "powerup" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn powerup(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Powerup.def().generate(rng)
+    }
 
-        if rand_bool(rng, 1, 1) {
-            s.vib_strength = rand_f64(rng, 0.0, 0.7);
-            s.vib_speed = rand_f64(rng, 0.0, 0.6);
-        }
+    /// Constructs a new random "hit" or "damage" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn hit(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Hit.def().generate(rng)
+    }
 
-        if rand_bool(rng, 1, 2) {
-            s.arp_speed = rand_f32(rng, 0.6, 0.9);
-            s.arp_mod = rand_f64(rng, -0.8, 0.8);
-        }
+    /// Constructs a new random "jump" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn jump(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Jump.def().generate(rng)
+    }
 
-        s
+    /// Constructs a new random "blip" or "menu navigation" style sample using optional random
+    /// seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn blip(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Blip.def().generate(rng)
     }
 
-    /// Constructs a new random "powerup" style sample using optional random seed
-    pub fn powerup(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
+    /// Constructs a new random "alien" or "bird" style sample with heavy vibrato, often
+    /// trilling through repeats, using optional random seed (random if `None`)
+    #[cfg(feature = "rand")]
+    pub fn alien(seed: Option<u64>) -> Sample {
+        let rng = &mut Prng::new(seed_or_random(seed));
+        Category::Alien.def().generate(rng)
+    }
+}
 
-        if rand_bool(rng, 1, 1) {
-            s.wave_type = WaveType::Sine;
-        } else {
-            s.duty = rand_f32(rng, 0.0, 0.6);
+/// Loads a Sample from a file: `.sfs` files with `sfs::load`, and other files as JSON with the
+/// `json` feature. Fails with `Error::Invalid` if the Sample has values outside their valid
+/// ranges, so a Sample that loads can always be played.
+///
+/// # Examples
+///
+/// ``` rust
+/// use std::convert::TryFrom;
+/// use std::path::Path;
+/// use sfxr::{sfs, Sample};
+/// let path = std::env::temp_dir().join("sfxr_try_from_path.sfs");
+/// sfs::save(&path, &Sample::new()).unwrap();
+/// let loaded = Sample::try_from(path.as_path()).unwrap();
+/// // .sfs files store fields as f32
+/// assert_eq!(loaded.base_freq as f32, Sample::new().base_freq as f32);
+/// assert!(Sample::try_from(Path::new("missing.sfs")).is_err());
+/// ```
+impl TryFrom<&Path> for Sample {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Sample, Error> {
+        if path.extension().is_some_and(|e| e == "sfs") {
+            return sfs::load(path);
         }
-
-        s.base_freq = rand_f64(rng, 0.2, 0.5);
-
-        if rand_bool(rng, 1, 1) {
-            s.freq_ramp = rand_f64(rng, 0.1, 0.5);
-            s.repeat_speed = rand_f32(rng, 0.4, 0.8);
-        } else {
-            s.freq_ramp = rand_f64(rng, 0.05, 0.25);
-
-            if rand_bool(rng, 1, 1) {
-                s.vib_strength = rand_f64(rng, 0.0, 0.7);
-                s.vib_speed = rand_f64(rng, 0.0, 0.6);
-            }
+        #[cfg(feature = "json")]
+        {
+            let sample: Sample = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            sample.validate()?;
+            Ok(sample)
         }
-
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.0, 0.4);
-        s.env_decay = rand_f32(rng, 0.1, 0.5);
-
-        s
+        #[cfg(not(feature = "json"))]
+        Err(Error::Unsupported(
+            "loading JSON Samples needs the json feature",
+        ))
     }
+}
 
-    /// Constructs a new random "hit" or "damage" style sample using optional random seed
-    pub fn hit(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
-
-        s.wave_type = rand_element(rng, &[WaveType::Square, WaveType::Sine, WaveType::Noise]);
+/// Per-playback variations of a Sample
+///
+/// Allows playing the same Sample with different pitch, speed and gain without modifying it,
+/// for example to slightly vary the pitch of each gunshot.
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeneratorOptions {
+    /// Pitch offset in semitones. Default is `0.0`.
+    pub pitch_offset_semitones: f32,
+    /// Playback speed multiplier, independent of pitch. Must be greater than `0.0`. Default is
+    /// `1.0`. Filter cutoff frequencies scale with speed.
+    pub speed: f32,
+    /// Gain multiplier applied on top of `Generator::volume`. Default is `1.0`.
+    pub gain: f32,
+    /// How hard the sound was triggered, between `0.0` and `1.0`. Scales the amplitude linearly
+    /// and closes the low pass filter cutoff by up to 40% for soft hits, as softer impacts
+    /// sound duller. The filter change takes effect on `Generator::reset`. Default is `1.0`.
+    pub velocity: f32,
+}
 
-        if s.wave_type == WaveType::Square {
-            s.duty = rand_f32(rng, 0.0, 0.6);
+#[allow(clippy::new_without_default)]
+impl GeneratorOptions {
+    /// Constructs new GeneratorOptions that play a Sample unmodified
+    pub fn new() -> GeneratorOptions {
+        GeneratorOptions {
+            pitch_offset_semitones: 0.0,
+            speed: 1.0,
+            gain: 1.0,
+            velocity: 1.0,
         }
+    }
+}
 
-        s.base_freq = rand_f64(rng, 0.2, 0.8);
-        s.freq_ramp = rand_f64(rng, -0.3, -0.7);
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.0, 0.1);
-        s.env_decay = rand_f32(rng, 0.1, 0.3);
+/// How output samples outside `-1.0..=1.0` are handled
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ClipMode {
+    /// Clamp to `-1.0..=1.0`, as original sfxr does
+    Hard,
+    /// Saturate smoothly with `tanh`, which also slightly compresses loud samples below full
+    /// scale
+    Soft,
+    /// Leave samples unclipped, for further processing in floating point
+    None,
+}
 
-        if rand_bool(rng, 1, 1) {
-            s.hpf_freq = rand_f32(rng, 0.0, 0.3);
-        }
+/// Sound chip whose limitations the output is constrained to
+///
+/// Both consoles restrict the square wave to duty cycles of 12.5%, 25%, 50% and 75%, and the
+/// square and noise volume to 16 steps. Other pitched waves are played from a table of 32
+/// samples of 4 bits, with the volume of the NES triangle channel, which is either silent or
+/// full, or of the Game Boy wave channel, which is silent, 25%, 50% or full. Volumes above full,
+/// from `Sample::env_punch`, are limited to full.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ConsoleMode {
+    /// No restrictions
+    None,
+    /// Nintendo Entertainment System 2A03
+    Nes,
+    /// Nintendo Game Boy
+    GameBoy,
+}
 
-        s
-    }
+/// How the oscillator supersamples are reduced to one synthesized sample, see
+/// `GeneratorConfig::oversampling`
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Decimation {
+    /// Average the supersamples, as original sfxr. Cheap, but leaves audible aliasing on high
+    /// pitched square and sawtooth waves.
+    Average,
+    /// Filter the supersamples with a windowed sinc FIR low pass at half the synthesis rate.
+    /// Removes most of the aliasing, at the cost of 16 multiplications per supersample and a
+    /// delay of 8 samples.
+    Fir,
+}
 
-    /// Constructs a new random "jump" style sample using optional random seed
-    pub fn jump(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
+/// Tuning of the original sfxr coefficients that are defined per oscillator supersample, see
+/// `GeneratorConfig::dsp_constants`
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum DspConstants {
+    /// Use the coefficients of original sfxr, such as the `0.0001` low pass ramp factor,
+    /// unchanged. They assume 8 supersamples per sample, so at a lower `oversampling` the
+    /// filters and phaser sound different.
+    Legacy,
+    /// Scale the low and high pass cutoffs, resonance and ramps, and the phaser delay, by the
+    /// `oversampling`, so they keep their frequencies and durations in seconds. Identical to
+    /// `Legacy` at an `oversampling` of `8`.
+    Corrected,
+}
 
-        s.wave_type = WaveType::Square;
-        s.duty = rand_f32(rng, 0.0, 0.6);
-        s.base_freq = rand_f64(rng, 0.3, 0.6);
-        s.freq_ramp = rand_f64(rng, 0.1, 0.3);
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.1, 0.4);
-        s.env_decay = rand_f32(rng, 0.1, 0.3);
+/// Construction time settings of a Generator
+///
+/// Collects the behavior flags that change how a Sample is rendered, as opposed to
+/// `GeneratorOptions` which vary a single playback. `GeneratorConfig::new` renders the same as
+/// `Generator::new`.
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeneratorConfig {
+    /// Output sample rate in Hz. Rates other than `SAMPLE_RATE` are produced by linear
+    /// interpolation of the synthesis, keeping pitch and duration. Must be greater than `0`.
+    /// Default is `SAMPLE_RATE`.
+    pub sample_rate: u32,
+    /// Oscillator supersamples averaged per synthesized sample, one of `1`, `2`, `4` or `8`.
+    /// Lower values are cheaper but alias more, and since the filters and phaser run once per
+    /// supersample they also change the filter and phaser timbre. Default is `8`.
+    pub oversampling: usize,
+    /// Smooths the square and triangle wave edges with PolyBLEP to reduce aliasing of high
+    /// notes. Default is `false`.
+    pub antialiasing: bool,
+    /// How samples outside `-1.0..=1.0` are handled. Default is `ClipMode::Hard`.
+    pub clip_mode: ClipMode,
+    /// Removes DC offset from the output with a one pole high pass filter at about 35 Hz,
+    /// applied before clipping. Default is `false`.
+    pub dc_blocker: bool,
+    /// Constrains the sound to the capabilities of a console sound chip. Disables
+    /// `antialiasing`, whose smoothing the chips don't have. Default is `ConsoleMode::None`.
+    pub console: ConsoleMode,
+    /// How the supersamples are reduced to one sample. Has no effect with an `oversampling`
+    /// of `1`. Default is `Decimation::Average`.
+    pub decimation: Decimation,
+    /// Whether the filter and phaser coefficients are scaled for the `oversampling`. The
+    /// synthesis itself always runs at `SAMPLE_RATE`, with `sample_rate` resampled from it,
+    /// so the coefficients defined per sample, like the `0.00005` duty ramp factor, need no
+    /// correction. Default is `DspConstants::Legacy`.
+    pub dsp_constants: DspConstants,
+    /// Renders bit for bit like the original sfxr port, ignoring `oversampling`,
+    /// `antialiasing`, `clip_mode`, `dc_blocker`, `console`, `decimation` and `dsp_constants`,
+    /// and without flushing tiny values from the filters. Output is bit identical only at
+    /// `SAMPLE_RATE`. Default is `false`.
+    pub legacy_compat: bool,
+}
 
-        if rand_bool(rng, 1, 1) {
-            s.hpf_freq = rand_f32(rng, 0.0, 0.3);
+#[allow(clippy::new_without_default)]
+impl GeneratorConfig {
+    /// Constructs a GeneratorConfig with the default settings
+    pub fn new() -> GeneratorConfig {
+        GeneratorConfig {
+            sample_rate: SAMPLE_RATE,
+            oversampling: OVERSAMPLING,
+            antialiasing: false,
+            clip_mode: ClipMode::Hard,
+            dc_blocker: false,
+            console: ConsoleMode::None,
+            decimation: Decimation::Average,
+            dsp_constants: DspConstants::Legacy,
+            legacy_compat: false,
         }
-
-        if rand_bool(rng, 1, 1) {
-            s.lpf_freq = rand_f32(rng, 0.4, 1.0);
+    }
+    /// Constructs a GeneratorConfig that guarantees bit faithful output of the original
+    /// algorithm at `SAMPLE_RATE`
+    pub fn legacy_compat() -> GeneratorConfig {
+        GeneratorConfig {
+            legacy_compat: true,
+            ..GeneratorConfig::new()
         }
-
-        s
     }
-
-    /// Constructs a new random "blip" or "menu navigation" style sample using optional random seed
-    pub fn blip(seed: Option<u64>) -> Sample {
-        let rng = &mut SmallRng::seed_from_u64(seed.unwrap_or(0));
-        let mut s = Sample::new();
-
-        s.wave_type = rand_element(rng, &[WaveType::Square, WaveType::Sine]);
-
-        if s.wave_type == WaveType::Square {
-            s.duty = rand_f32(rng, 0.0, 0.6);
+    /// Supersamples per synthesized sample after applying `legacy_compat`
+    fn effective_oversampling(&self) -> usize {
+        if self.legacy_compat {
+            OVERSAMPLING
+        } else {
+            self.oversampling
+        }
+    }
+    /// Original supersamples each supersample stands for in the filters and phaser, after
+    /// applying `dsp_constants` and `legacy_compat`
+    fn effective_steps(&self) -> u32 {
+        match self.dsp_constants {
+            DspConstants::Corrected => (OVERSAMPLING / self.effective_oversampling()) as u32,
+            DspConstants::Legacy => 1,
         }
+    }
+    /// Console mode after applying `legacy_compat`
+    fn effective_console(&self) -> ConsoleMode {
+        if self.legacy_compat {
+            ConsoleMode::None
+        } else {
+            self.console
+        }
+    }
+    fn assert_valid(&self) {
+        assert!(
+            self.sample_rate > 0,
+            "sample_rate must be greater than zero"
+        );
+        assert!(
+            [1, 2, 4, 8].contains(&self.oversampling),
+            "oversampling must be 1, 2, 4 or 8"
+        );
+    }
+}
 
-        s.base_freq = rand_f64(rng, 0.2, 0.6);
-        s.env_attack = 0.0;
-        s.env_sustain = rand_f32(rng, 0.1, 0.2);
-        s.env_decay = rand_f32(rng, 0.0, 0.2);
-        s.hpf_freq = 0.1;
+/// Snapshot of the complete internal state of a Generator
+///
+/// Captured with `Generator::state` and applied with `Generator::restore`, for example to rewind
+/// audio along with the rest of a game. Includes the Sample, volume, options, config and
+/// Automation, but not the event handler.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GeneratorState {
+    sample: Sample,
+    volume: f32,
+    options: GeneratorOptions,
+    config: GeneratorConfig,
+    oscillator: Oscillator,
+    hlpf: HighLowPassFilter,
+    envelope: Envelope,
+    phaser: Phaser,
+    decimator: Option<Decimator>,
+    equalizer: Option<EqualizerFilter>,
+    side_equalizer: Option<EqualizerFilter>,
+    compressor: Option<CompressorFilter>,
+    sample_hold: SampleHoldLfo,
+    rep_time: i32,
+    rep_limit: i32,
+    repeat_rng: Prng,
+    elapsed: usize,
+    resample_position: f32,
+    resample_previous: f32,
+    resample_next: f32,
+    side_previous: f32,
+    side_next: f32,
+    dc_input: f32,
+    dc_output: f32,
+    amplitude: f32,
+    note: Option<u8>,
+    controllers: Controllers,
+    automation: Option<Box<Automated>>,
+    paused: bool,
+}
 
-        s
-    }
+impl GeneratorState {
+    /// Size of a GeneratorState in bytes. Snapshots own no heap memory, unless the Generator
+    /// plays an Automation or a `Sample::pha_length` above `1024`.
+    pub const MEMORY_FOOTPRINT: usize = std::mem::size_of::<GeneratorState>();
+}
+
+/// Notable moments during sound effect generation
+///
+/// Each event carries the `offset` of the sample within the buffer passed to
+/// `Generator::generate` at which the event occurred.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum GeneratorEvent {
+    /// The amplitude envelope moved to a new stage
+    EnvelopeStageChanged {
+        /// Sample offset within the generated buffer
+        offset: usize,
+        /// The stage that was entered
+        stage: EnvelopeStage,
+    },
+    /// The sound restarted due to `repeat_speed`
+    Repeat {
+        /// Sample offset within the generated buffer
+        offset: usize,
+    },
+    /// The arpeggio changed the frequency
+    ArpStep {
+        /// Sample offset within the generated buffer
+        offset: usize,
+    },
+    /// The envelope ended and the rest of the sound is silence
+    Finished {
+        /// Sample offset within the generated buffer
+        offset: usize,
+    },
 }
 
 /// Sound effect generator
 ///
 /// Generates sound effect data according to a Sample into a buffer. The data can be generated in
 /// multiple chunks, as the generator maintains its state from one call to `generate` to the next.
+///
+/// # Memory
+///
+/// A Generator takes `Generator::MEMORY_FOOTPRINT` bytes, most of it the phaser delay line.
+/// Constructing, generating, resetting, pausing and taking or restoring snapshots never
+/// allocate heap memory, except with a `Sample::pha_length` above `1024`, whose delay line is
+/// allocated when first played and copied into snapshots, and for snapshots copying an
+/// Automation. The `heapless` feature limits `pha_length` to `1024` so that playing never
+/// allocates. Only `on_event`, `set_automation`, `generate_exact` and the `render_*` methods
+/// allocate, for the handler, the keyframes, the reused output buffer and the returned data.
 pub struct Generator {
     /// Generator settings
     pub sample: Sample,
 
-    /// Sound effect volume. Default is `0.2`.
+    /// Sound effect volume as a linear gain. Default is `DEFAULT_VOLUME`.
     pub volume: f32,
+    /// Playback variations applied on top of the Sample
+    pub options: GeneratorOptions,
+    config: GeneratorConfig,
     oscillator: Oscillator,
     hlpf: HighLowPassFilter,
     envelope: Envelope,
     phaser: Phaser,
+    decimator: Option<Decimator>,
+    equalizer: Option<EqualizerFilter>,
+    side_equalizer: Option<EqualizerFilter>,
+    compressor: Option<CompressorFilter>,
+    sample_hold: SampleHoldLfo,
     rep_time: i32,
     rep_limit: i32,
+    repeat_rng: Prng,
+    elapsed: usize,
+    event_handler: Option<Box<dyn FnMut(GeneratorEvent) + Send>>,
+    resample_position: f32,
+    resample_previous: f32,
+    resample_next: f32,
+    side_previous: f32,
+    side_next: f32,
+    dc_input: f32,
+    dc_output: f32,
+    paused: bool,
+    pitch_cache: Option<(f32, f32, f64)>,
+    exact_buffer: Vec<f32>,
+    metering: bool,
+    meters: Meters,
+    levels: StageLevels,
+    amplitude: f32,
+    revalidate: bool,
+    note: Option<u8>,
+    controllers: Controllers,
+    automation: Option<Box<Automated>>,
 }
 impl Generator {
+    /// Default linear volume, about -14 dB.
+    ///
+    /// The oscillator and filters can produce peaks well above full scale, especially with the
+    /// phaser or resonant low pass filter, and output is clipped to `-1.0..=1.0`. The default
+    /// leaves enough headroom that a typical effect doesn't clip and about five effects can be
+    /// mixed at full scale.
+    pub const DEFAULT_VOLUME: f32 = 0.2;
+    /// Size of a Generator in bytes, not counting heap memory from the allocating methods
+    pub const MEMORY_FOOTPRINT: usize = std::mem::size_of::<Generator>();
+    /// Time constants of the `amplitude_envelope` follower
+    const FOLLOWER_ATTACK_SECS: f32 = 0.005;
+    const FOLLOWER_RELEASE_SECS: f32 = 0.1;
+
     /// Constructs a new Generator based on the provided Sample
     pub fn new(s: Sample) -> Generator {
+        Generator::new_with(s, GeneratorOptions::new())
+    }
+    /// Constructs a new Generator based on the provided Sample, played with `options`
+    pub fn new_with(s: Sample, options: GeneratorOptions) -> Generator {
+        Generator::new_with_config(s, options, GeneratorConfig::new())
+    }
+    /// Constructs a new Generator based on the provided Sample, played with `options` and
+    /// rendered according to `config`
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, GeneratorConfig, GeneratorOptions, Sample};
+    /// let mut config = GeneratorConfig::new();
+    /// config.sample_rate = 48_000;
+    /// config.antialiasing = true;
+    /// let options = GeneratorOptions::new();
+    /// let mut generator = Generator::new_with_config(Sample::new(), options, config);
+    /// let mut buffer = [0.0; 512];
+    /// generator.generate(&mut buffer);
+    /// ```
+    pub fn new_with_config(
+        s: Sample,
+        options: GeneratorOptions,
+        config: GeneratorConfig,
+    ) -> Generator {
         s.assert_valid();
+        config.assert_valid();
+        Generator::new_unchecked(s, options, config)
+    }
+    /// Constructs a new Generator based on the provided TypedSample. No validation is needed as
+    /// its fields are always in range.
+    pub fn from_typed(s: TypedSample) -> Generator {
+        Generator::new_unchecked(s.into(), GeneratorOptions::new(), GeneratorConfig::new())
+    }
+    fn new_unchecked(s: Sample, options: GeneratorOptions, config: GeneratorConfig) -> Generator {
         let wave_type = s.wave_type;
+        let mut oscillator = Oscillator::new(wave_type);
+        let console = config.effective_console();
+        oscillator.set_antialiasing(
+            config.antialiasing && !config.legacy_compat && console == ConsoleMode::None,
+        );
+        oscillator.set_console(console);
+        let oversampling = config.effective_oversampling();
+        let decimator = match config.decimation {
+            Decimation::Fir if !config.legacy_compat && oversampling > 1 => {
+                Some(Decimator::new(oversampling))
+            }
+            _ => None,
+        };
+        let mut hlpf = HighLowPassFilter::new();
+        hlpf.set_steps(config.effective_steps());
+        hlpf.set_flush(!config.legacy_compat);
+        let mut phaser = Phaser::new();
+        phaser.set_steps(config.effective_steps());
+        phaser.set_flush(!config.legacy_compat);
         let mut g = Generator {
             sample: s,
-            volume: 0.2,
-            oscillator: Oscillator::new(wave_type),
-            hlpf: HighLowPassFilter::new(),
+            volume: Generator::DEFAULT_VOLUME,
+            options,
+            config,
+            oscillator,
+            hlpf,
             envelope: Envelope::new(),
-            phaser: Phaser::new(),
+            phaser,
+            decimator,
+            equalizer: None,
+            side_equalizer: None,
+            compressor: None,
+            sample_hold: SampleHoldLfo::new(),
             rep_time: 0,
             rep_limit: 0,
+            repeat_rng: Prng::new(0),
+            elapsed: 0,
+            event_handler: None,
+            resample_position: 0.0,
+            resample_previous: 0.0,
+            resample_next: 0.0,
+            side_previous: 0.0,
+            side_next: 0.0,
+            dc_input: 0.0,
+            dc_output: 0.0,
+            paused: false,
+            pitch_cache: None,
+            exact_buffer: Vec::new(),
+            metering: false,
+            meters: Meters::default(),
+            levels: StageLevels::default(),
+            amplitude: 0.0,
+            revalidate: false,
+            note: None,
+            controllers: Controllers::new(),
+            automation: None,
         };
 
         g.reset();
@@ -544,34 +1252,639 @@ impl Generator {
     }
     /// Fills `buffer` with sound effect data. Subsequent calls continue where the last left off.
     /// Call `reset` first to start generating from the beginning.
+    ///
+    /// While the generator is paused, `buffer` is filled with silence.
     pub fn generate(&mut self, buffer: &mut [f32]) {
-        buffer.iter_mut().for_each(|buffer_value| {
-            self.rep_time += 1;
+        self.fill(buffer, false, false, Route::Full);
+    }
+    /// Fills `buffer` with interleaved stereo (left, right) frames, like `generate`. The left
+    /// channel is the same as the output of `generate`, and the right channel differs only by
+    /// the phaser offset, according to `Sample::pha_stereo`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut whoosh = Sample::new();
+    /// whoosh.pha_offset = 0.3;
+    /// whoosh.pha_ramp = 0.2;
+    /// whoosh.pha_stereo = 0.5;
+    /// let mut stereo = [0.0; 2 * 4_096];
+    /// Generator::new(whoosh).generate_stereo(&mut stereo);
+    /// ```
+    pub fn generate_stereo(&mut self, buffer: &mut [f32]) {
+        assert!(
+            buffer.len().is_multiple_of(2),
+            "buffer must hold a whole number of stereo frames"
+        );
+        self.fill(buffer, false, true, Route::Full);
+    }
+    /// Fills `buffer` like `generate`, but without applying `volume`, `Sample::volume`,
+    /// `GeneratorOptions::gain`, velocity, DC blocker or clipping.
+    ///
+    /// For mixing many sounds externally: the samples can exceed `[-1.0, 1.0]`, so the mixer
+    /// can apply its own gain staging and clip only once at the end.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::explosion(Some(1)));
+    /// let mut buffer = [0.0; 1_024];
+    /// generator.generate_raw(&mut buffer);
+    /// let quieter: Vec<f32> = buffer.iter().map(|v| v * 0.25).collect();
+    /// ```
+    pub fn generate_raw(&mut self, buffer: &mut [f32]) {
+        self.fill(buffer, true, false, Route::Full);
+    }
+    /// Fills `buffer` with the output of the oscillator alone, like `generate_raw` but
+    /// without the envelope, filters, phaser, equalizer and compressor. The sound advances as
+    /// with `generate`, so slides, vibrato, arpeggio and repeats still play.
+    ///
+    /// For processing the raw waveform with other effects. The oscillator doesn't stop when
+    /// the envelope ends.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::laser(Some(1)));
+    /// let mut buffer = [0.0; 1_024];
+    /// generator.oscillator_only(&mut buffer);
+    /// ```
+    pub fn oscillator_only(&mut self, buffer: &mut [f32]) {
+        self.fill(buffer, true, false, Route::OscillatorOnly);
+    }
+    /// Passes the samples of `buffer` through the envelope, filters, phaser, equalizer and
+    /// compressor in place of the oscillator, like `generate_raw`. The sound advances as with
+    /// `generate`, so the envelope and filter sweeps play over the input.
+    ///
+    /// For giving external audio, such as a voice clip, the sfxr treatment. Each input sample
+    /// is held over the supersamples of its output sample, and `GeneratorOptions::speed`
+    /// changes how fast the sound advances but doesn't resample the input.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut sample = Sample::new();
+    /// sample.lpf_freq = 0.3;
+    /// sample.pha_offset = 0.2;
+    /// let mut voice: Vec<f32> = (0..4_410).map(|i| (i as f32 * 0.05).sin()).collect();
+    /// Generator::new(sample).filter_buffer(&mut voice);
+    /// ```
+    pub fn filter_buffer(&mut self, buffer: &mut [f32]) {
+        self.fill(buffer, true, false, Route::Filters);
+    }
+    fn fill(&mut self, buffer: &mut [f32], raw: bool, stereo: bool, route: Route) {
+        if self.metering {
+            self.meters = Meters::default();
+        }
+        if self.paused {
+            buffer.iter_mut().for_each(|v| *v = 0.0);
+            self.levels = self.meters.levels();
+            return;
+        }
+
+        // Synthesized samples per output sample
+        let step = self.options.speed * SAMPLE_RATE as f32 / self.config.sample_rate as f32;
+        self.prewarm();
+        let gain = if raw {
+            1.0
+        } else {
+            self.volume
+                * 2.0
+                * self.sample.volume
+                * db_to_gain(self.sample.gain_db)
+                * self.options.gain
+                * self.options.velocity.clamp(0.0, 1.0)
+        };
+        let legacy = self.config.legacy_compat;
+        let clip_mode = if raw {
+            ClipMode::None
+        } else if legacy {
+            ClipMode::Hard
+        } else {
+            self.config.clip_mode
+        };
+        let dc_blocker = self.config.dc_blocker && !legacy && !raw;
+        let dc_pole = 1.0 - 2.0 * std::f32::consts::PI * 35.0 / self.config.sample_rate as f32;
+        let follower_rate = self.config.sample_rate as f32;
+        let attack = (-1.0 / (Generator::FOLLOWER_ATTACK_SECS * follower_rate)).exp();
+        let release = (-1.0 / (Generator::FOLLOWER_RELEASE_SECS * follower_rate)).exp();
+
+        let channels = if stereo { 2 } else { 1 };
+        buffer
+            .chunks_mut(channels)
+            .enumerate()
+            .for_each(|(offset, frame)| {
+                let input = frame[0];
+                let (sample, side) = if (step - 1.0).abs() < f32::EPSILON {
+                    self.synthesize(offset, stereo, route, input)
+                } else {
+                    // Run the synthesis at `step` times the output rate and interpolate linearly
+                    self.resample_position += step;
+                    while self.resample_position >= 1.0 {
+                        self.resample_position -= 1.0;
+                        self.resample_previous = self.resample_next;
+                        self.side_previous = self.side_next;
+                        let (next, side) = self.synthesize(offset, stereo, route, input);
+                        self.resample_next = next;
+                        self.side_next = side;
+                    }
+                    let position = self.resample_position;
+                    (
+                        self.resample_previous
+                            + (self.resample_next - self.resample_previous) * position,
+                        self.side_previous + (self.side_next - self.side_previous) * position,
+                    )
+                };
+
+                let mut sample = sample * gain;
+                if dc_blocker {
+                    self.dc_output = flush(sample - self.dc_input + dc_pole * self.dc_output);
+                    self.dc_input = sample;
+                    sample = self.dc_output;
+                }
+
+                // The side signal of the stereo phaser has no DC offset to block
+                let outputs = [sample, sample + side * gain];
+                for (value, &output) in frame.iter_mut().zip(outputs.iter()) {
+                    *value = match clip_mode {
+                        ClipMode::Hard => output.clamp(-1.0, 1.0),
+                        ClipMode::Soft => output.tanh(),
+                        ClipMode::None => output,
+                    };
+                    if self.metering {
+                        self.meters.output.add(*value);
+                    }
+                }
+
+                let level = frame[0].abs();
+                let coefficient = if level > self.amplitude {
+                    attack
+                } else {
+                    release
+                };
+                self.amplitude = level + (self.amplitude - level) * coefficient;
+            });
+
+        if self.metering {
+            self.levels = self.meters.levels();
+        }
+    }
+    /// Generates the next `len` samples into an internal buffer and returns them, like
+    /// `generate`. The buffer is reused between calls and only reallocated when `len` exceeds
+    /// its capacity, so repeated calls with callback sized lengths don't allocate.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// let frames = generator.generate_exact(256);
+    /// assert_eq!(frames.len(), 256);
+    /// ```
+    pub fn generate_exact(&mut self, len: usize) -> &[f32] {
+        let mut buffer = std::mem::take(&mut self.exact_buffer);
+        buffer.resize(len, 0.0);
+        self.generate(&mut buffer);
+        self.exact_buffer = buffer;
+        &self.exact_buffer
+    }
+    /// Generates the sound in chunks of `chunk_len` samples, passing each to `callback` as soon
+    /// as it is generated, like `generate_exact`. Stops when `callback` returns `false`, or
+    /// before a chunk that is silent after the envelope has ended. Returns the number of
+    /// samples passed to `callback`.
+    ///
+    /// Made for pull based audio such as a Web Audio `AudioWorkletProcessor`, which consumes
+    /// 128 sample chunks: the first chunk is available immediately instead of after rendering
+    /// the whole sound.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// let mut rendered = Vec::new();
+    /// generator.generate_chunks(128, |chunk| {
+    ///     rendered.extend_from_slice(chunk);
+    ///     true
+    /// });
+    /// assert_eq!(rendered.len() % 128, 0);
+    /// ```
+    pub fn generate_chunks<F: FnMut(&[f32]) -> bool>(
+        &mut self,
+        chunk_len: usize,
+        mut callback: F,
+    ) -> usize {
+        assert!(chunk_len > 0, "chunk_len must be greater than zero");
+
+        let mut generated = 0;
+        loop {
+            self.generate_exact(chunk_len);
+            let silent = self.exact_buffer.iter().all(|v| v.abs() < 1.0e-3);
+            if self.is_finished() && silent {
+                break;
+            }
+            generated += chunk_len;
+            if !callback(&self.exact_buffer) {
+                break;
+            }
+        }
+        generated
+    }
+    /// Renders the whole sound effect from the beginning, including the tail the filters and
+    /// phaser produce after the envelope ends. Trailing silence is trimmed.
+    ///
+    /// Equivalent to `render_to_vec_with(1.0e-3, 1_024)`.
+    pub fn render_to_vec(&mut self) -> Vec<f32> {
+        self.render_to_vec_with(1.0e-3, 1_024)
+    }
+    /// Renders the whole sound effect from the beginning. Generation stops once the envelope
+    /// has ended and the output has stayed below `threshold` for `hold_samples` samples, and
+    /// trailing samples below `threshold` are trimmed.
+    ///
+    /// The generator is left at the end of the sound and unpaused. See `renderer_with` for
+    /// rendering in steps.
+    pub fn render_to_vec_with(&mut self, threshold: f32, hold_samples: usize) -> Vec<f32> {
+        self.render_chunks(threshold, hold_samples, |_| ())
+    }
+    /// Implements `render_to_vec_with`, calling `on_chunk` with the position of each chunk
+    /// after generating it
+    fn render_chunks<F: FnMut(usize)>(
+        &mut self,
+        threshold: f32,
+        hold_samples: usize,
+        mut on_chunk: F,
+    ) -> Vec<f32> {
+        // Longer than any Sample can last, in case the tail never decays
+        let max_samples = 60 * self.config.sample_rate as usize;
+
+        self.reset();
+        self.resume();
+
+        let mut buffer = Vec::new();
+        let mut quiet = 0;
+        let mut chunk = [0.0; 1_024];
+        while buffer.len() < max_samples && !(self.is_finished() && quiet >= hold_samples) {
+            self.generate(&mut chunk);
+            on_chunk(buffer.len());
+            for &v in chunk.iter() {
+                quiet = if v.abs() < threshold { quiet + 1 } else { 0 };
+            }
+            buffer.extend_from_slice(&chunk);
+        }
 
-            if self.rep_limit != 0 && self.rep_time >= self.rep_limit {
-                self.rep_time = 0;
-                self.restart();
+        let end = buffer
+            .iter()
+            .rposition(|v| v.abs() >= threshold)
+            .map_or(0, |i| i + 1);
+        buffer.truncate(end);
+        buffer
+    }
+    /// Generates exactly one output sample, equivalent to `generate` with a one sample buffer.
+    /// Events are reported with offset `0`.
+    ///
+    /// Output only depends on the generator state, so two generators with identical state, for
+    /// example one restored from the other's `state`, produce identical samples. This holds
+    /// across machines as long as they use the same build of the program, as `sin` and `powf`
+    /// may round differently between platforms and standard library versions.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut a = Generator::new(Sample::new());
+    /// let mut b = Generator::new(Sample::new());
+    /// b.restore(a.state());
+    /// for _ in 0..1_000 {
+    ///     assert_eq!(a.tick().to_bits(), b.tick().to_bits());
+    /// }
+    /// ```
+    pub fn tick(&mut self) -> f32 {
+        let mut buffer = [0.0; 1];
+        self.generate(&mut buffer);
+        buffer[0]
+    }
+    /// Generates one sample before volume is applied, with `input` in place of the oscillator
+    /// for `Route::Filters`. `offset` is reported in events.
+    fn synthesize(&mut self, offset: usize, stereo: bool, route: Route, input: f32) -> (f32, f32) {
+        self.advance(offset);
+
+        let oversampling = self.config.effective_oversampling();
+        let steps = (OVERSAMPLING / oversampling) as u32;
+        let mut sum = 0.0;
+        let mut side_sum = 0.0;
+        for _ in 0..oversampling {
+            let oscillator = match route {
+                Route::Filters => input,
+                Route::Full | Route::OscillatorOnly => self.oscillator.next_sample(steps),
+            };
+            if route == Route::OscillatorOnly {
+                match self.decimator {
+                    Some(ref mut decimator) => decimator.push(oscillator, 0.0),
+                    None => sum += oscillator,
+                }
+                continue;
+            }
+            let envelope = self.envelope.filter(oscillator);
+            let filter = self.hlpf.filter(envelope);
+            let (phaser, side) = if stereo {
+                let (left, right) = self.phaser.filter_stereo(filter);
+                (left, right - left)
+            } else {
+                (self.phaser.filter(filter), 0.0)
+            };
+            if self.metering {
+                self.meters.oscillator.add(oscillator);
+                self.meters.envelope.add(envelope);
+                self.meters.filter.add(filter);
+                self.meters.phaser.add(phaser);
             }
+            match self.decimator {
+                Some(ref mut decimator) => decimator.push(phaser, side),
+                None => {
+                    sum += phaser;
+                    side_sum += side;
+                }
+            }
+        }
+        // The side signal is the difference of the right channel from the left
+        let (mut sample, mut side) = match self.decimator {
+            Some(ref decimator) => decimator.output(stereo),
+            None => (sum / oversampling as f32, side_sum / oversampling as f32),
+        };
+        if route == Route::OscillatorOnly {
+            return (sample, side);
+        }
+        if let Some(ref mut equalizer) = self.equalizer {
+            sample = equalizer.filter(sample);
+        }
+        if let (true, Some(equalizer)) = (stereo, self.side_equalizer.as_mut()) {
+            side = equalizer.filter(side);
+        }
+        if let Some(ref mut compressor) = self.compressor {
+            sample = compressor.filter(sample);
+            // Both channels are compressed by the same amount
+            side *= compressor.gain();
+        }
+        (sample, side)
+    }
+    /// Advances repeat, arpeggio, slide, vibrato, envelope and phaser by one sample without
+    /// generating audio. `offset` is reported in events.
+    fn advance(&mut self, offset: usize) {
+        self.rep_time += 1;
+        self.elapsed += 1;
+
+        if self.rep_limit != 0 && self.rep_time >= self.rep_limit {
+            self.rep_time = 0;
+            self.restart();
+            self.vary_repeat();
+            self.emit(GeneratorEvent::Repeat { offset });
+        }
 
-            self.oscillator.advance();
-            self.envelope.advance();
-            self.phaser.advance();
+        self.advance_controllers();
+        self.advance_automation();
+        if self.oscillator.advance() {
+            self.emit(GeneratorEvent::ArpStep { offset });
+        }
+        self.hlpf.advance();
+        if let Some(value) = self.sample_hold.advance() {
+            match self.sample.sample_hold {
+                SampleHold::Off => {}
+                SampleHold::Pitch(semitones) => self
+                    .oscillator
+                    .set_pitch_mod(2f64.powf(f64::from(value * semitones) / 12.0)),
+                SampleHold::Filter(octaves) => self.hlpf.set_cutoff_mod(2f32.powf(value * octaves)),
+            }
+        }
 
-            let sample = self
-                .oscillator
-                .by_ref()
-                .chain_filter(&mut self.envelope)
-                .chain_filter(&mut self.hlpf)
-                .chain_filter(&mut self.phaser)
-                .take(8)
-                .sum::<f32>()
-                / 8.0;
+        let stage = self.envelope.stage();
+        let end_at_limit = self.sample.freq_limit_behavior == FreqLimitBehavior::EndSound;
+        if end_at_limit && self.oscillator.limit_reached() {
+            // As in original sfxr, the sound is cut rather than decaying
+            self.envelope.stop();
+        } else {
+            self.envelope.advance();
+        }
+        if self.envelope.stage() != stage {
+            let stage = self.envelope.stage();
+            self.emit(GeneratorEvent::EnvelopeStageChanged { offset, stage });
+            if stage == EnvelopeStage::End {
+                self.emit(GeneratorEvent::Finished { offset });
+            }
+        }
 
-            *buffer_value = (sample * self.volume).min(1.0).max(-1.0);
-        });
+        self.phaser.advance();
+    }
+    /// Performs the setup `generate` would otherwise do at the start of its next call.
+    ///
+    /// Cost model: `new` and `reset` do all per-sound setup up front, which is computing the
+    /// envelope lengths and filter coefficients, filling the 32 entry noise table and, in `new`
+    /// only, clearing the 16 KiB phaser delay line. Nothing is allocated. Each `generate` call
+    /// then recomputes the pitch multiplier if `options` changed, and costs a fixed amount per
+    /// output sample, plus one noise table refill per noise period for `WaveType::Noise`.
+    /// Calling `prewarm` after `new` or after changing `options` moves the pitch computation
+    /// out of the first audio callback.
+    pub fn prewarm(&mut self) {
+        let key = (self.options.pitch_offset_semitones, self.options.speed);
+        let pitch = match self.pitch_cache {
+            Some((offset, speed, pitch)) if (offset, speed) == key => pitch,
+            _ => {
+                assert!(key.1 > 0.0, "speed must be greater than zero");
+                let pitch = 2f64.powf(f64::from(key.0) / 12.0) / f64::from(key.1);
+                self.pitch_cache = Some((key.0, key.1, pitch));
+                pitch
+            }
+        };
+        self.oscillator.set_pitch(pitch);
+    }
+    /// Pauses the generator. Until `resume` is called, `generate` outputs silence without
+    /// advancing the sound.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+    /// Resumes a paused generator from where it was paused
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+    /// Returns `true` if the generator is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Returns the volume in decibels relative to full scale
+    pub fn volume_db(&self) -> f32 {
+        gain_to_db(self.volume)
+    }
+    /// Sets the volume in decibels relative to full scale
+    pub fn set_volume_db(&mut self, db: f32) {
+        self.volume = db_to_gain(db);
+    }
+    /// Moves the difference of `volume` from `DEFAULT_VOLUME` into `Sample::gain_db` and resets
+    /// `volume` to the default. The output is unchanged, and a saved `sample` plays at the same
+    /// level when loaded into a new Generator.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// generator.set_volume_db(-6.0);
+    /// generator.store_volume();
+    /// assert_eq!(generator.volume, Generator::DEFAULT_VOLUME);
+    /// let saved = generator.sample.clone();
+    /// assert!((Generator::new(saved.clone()).volume_db() + saved.gain_db + 6.0).abs() < 1e-3);
+    /// ```
+    pub fn store_volume(&mut self) {
+        let db = self.volume_db() - gain_to_db(Generator::DEFAULT_VOLUME);
+        assert!(db.is_finite(), "volume must be greater than zero");
+        self.sample.gain_db += db;
+        self.volume = Generator::DEFAULT_VOLUME;
+    }
+    /// Enables or disables measuring the signal level after each stage, see `meter`. Disabled
+    /// by default, as it adds some cost to every sample.
+    pub fn set_metering(&mut self, enabled: bool) {
+        self.metering = enabled;
+        self.meters = Meters::default();
+        self.levels = StageLevels::default();
+    }
+    /// Returns the signal levels after each stage over the last `generate` call. All levels
+    /// are zero unless metering is enabled with `set_metering`.
+    pub fn meter(&self) -> StageLevels {
+        self.levels
+    }
+    /// Returns the smoothed amplitude of the output as of the last generated sample, between
+    /// `0.0` and about the output peak level
+    ///
+    /// The output level is followed with a 5 ms attack and a 100 ms release, so game code can
+    /// drive visuals from the sound as it plays without analyzing the buffers separately.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::explosion(Some(5)));
+    /// generator.generate(&mut [0.0; 735]);
+    /// let flash_brightness = (generator.amplitude_envelope() * 4.0).min(1.0);
+    /// assert!(flash_brightness > 0.0);
+    /// ```
+    pub fn amplitude_envelope(&self) -> f32 {
+        self.amplitude
+    }
+    /// Returns the number of output samples until the envelope ends, at the configured sample
+    /// rate and speed. `0` once the sound has finished.
+    ///
+    /// Repeats don't change the length of the sound. The tail that the filters and phaser may
+    /// produce after the envelope ends is not included.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::pickup(Some(4)));
+    /// let mut buffer = vec![0.0; generator.remaining_samples()];
+    /// generator.generate(&mut buffer);
+    /// assert_eq!(generator.remaining_samples(), 0);
+    /// assert_eq!(generator.progress(), 1.0);
+    /// ```
+    pub fn remaining_samples(&self) -> usize {
+        let remaining = self.remaining_synthesized();
+        let step = self.options.speed * SAMPLE_RATE as f32 / self.config.sample_rate as f32;
+        if remaining == 0 || (step - 1.0).abs() < f32::EPSILON {
+            remaining
+        } else {
+            // Each output sample advances the resampling position by `step`
+            ((remaining as f32 - self.resample_position) / step)
+                .ceil()
+                .max(0.0) as usize
+        }
+    }
+    /// Returns how far the envelope has progressed, from `0.0` at the start to `1.0` once the
+    /// sound has finished
+    pub fn progress(&self) -> f32 {
+        let total = self.sample.envelope_length() + 1;
+        1.0 - self.remaining_synthesized() as f32 / total as f32
+    }
+    /// Number of synthesized samples until the envelope ends
+    fn remaining_synthesized(&self) -> usize {
+        if self.is_finished() {
+            0
+        } else {
+            // The envelope ends on the advance numbered `envelope_length`
+            (self.sample.envelope_length() + 1).saturating_sub(self.elapsed)
+        }
+    }
+    /// Holds the envelope at full volume once it reaches the sustain stage, or lets it
+    /// continue to the decay when `hold` is `false`
+    pub(crate) fn set_envelope_hold(&mut self, hold: bool) {
+        self.envelope.set_hold(hold);
+    }
+    /// Sets the pitch multiplier, low pass cutoff multiplier and duty cycle offset of external
+    /// modulation, replacing any from `Sample::sample_hold`
+    pub(crate) fn set_modulation(&mut self, pitch: f64, cutoff: f32, duty: f32) {
+        self.oscillator.set_pitch_mod(pitch);
+        self.hlpf.set_cutoff_mod(cutoff);
+        self.oscillator.set_duty_mod(duty);
+    }
+    /// Returns `true` once the envelope has ended and the rest of the sound is silence
+    pub(crate) fn is_finished(&self) -> bool {
+        self.envelope.stage() == EnvelopeStage::End
+    }
+    /// Registers `handler` to be called with events as they occur during `generate`. Replaces
+    /// any previously registered handler.
+    ///
+    /// The handler is called from within `generate`, so it should return quickly when the
+    /// generator runs in an audio callback.
+    pub fn on_event<F: FnMut(GeneratorEvent) + Send + 'static>(&mut self, handler: F) {
+        self.event_handler = Some(Box::new(handler));
+    }
+    /// Removes the event handler registered with `on_event`
+    pub fn clear_event_handler(&mut self) {
+        self.event_handler = None;
+    }
+    fn emit(&mut self, event: GeneratorEvent) {
+        if let Some(handler) = self.event_handler.as_mut() {
+            handler(event);
+        }
+    }
+    /// Returns the Sample for modification. The next `reset` checks that its fields are within
+    /// their valid ranges, like `new`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::from(Sample::new());
+    /// generator.sample_mut().env_decay = 0.1;
+    /// generator.reset();
+    /// ```
+    ///
+    /// ``` rust,should_panic
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::from(Sample::new());
+    /// generator.sample_mut().duty = 2.0;
+    /// generator.reset();
+    /// ```
+    pub fn sample_mut(&mut self) -> &mut Sample {
+        self.revalidate = true;
+        &mut self.sample
     }
     /// Resets the generator to the beginning of the sound effect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the Sample was modified through `sample_mut` to have values outside their
+    /// valid ranges.
     pub fn reset(&mut self) {
+        if self.revalidate {
+            self.sample.assert_valid();
+            self.revalidate = false;
+        }
+        if let Some(automated) = self.automation.take() {
+            self.restore_automated(&automated);
+            self.automation = Some(automated);
+        }
         self.restart();
         self.envelope.reset(
             self.sample.env_attack,
@@ -579,30 +1892,218 @@ impl Generator {
             self.sample.env_decay,
             self.sample.env_punch,
         );
-        self.phaser
-            .reset(self.sample.pha_offset, self.sample.pha_ramp);
+        self.envelope.set_volume_steps(VolumeSteps::new(
+            self.config.effective_console(),
+            self.sample.wave_type,
+        ));
+        self.reset_phaser();
+        if let Some(ref mut decimator) = self.decimator {
+            decimator.clear();
+        }
+        self.equalizer = self.sample.equalizer.as_ref().map(EqualizerFilter::new);
+        self.side_equalizer = self.equalizer.clone();
+        self.compressor = self.sample.compressor.as_ref().map(CompressorFilter::new);
 
         self.oscillator.reset_phase();
         self.oscillator
             .reset_vibrato(self.sample.vib_speed, self.sample.vib_strength);
-        self.oscillator.reset_noise();
+        // With `noise_retrigger` the noise was already restarted by `restart`
+        if !self.sample.noise_retrigger {
+            match self.sample.noise_seed {
+                Some(seed) => self.oscillator.reseed_noise(seed),
+                None => self.oscillator.reset_noise(),
+            }
+        }
+        self.oscillator.set_pitch_mod(1.0);
+        self.oscillator.set_duty_mod(0.0);
+        self.hlpf.set_cutoff_mod(1.0);
+        self.sample_hold
+            .reset(self.sample.sample_hold_secs, SAMPLE_RATE);
+        if let Some(seed) = self.sample.noise_seed {
+            self.sample_hold.reseed(seed);
+            self.repeat_rng = Prng::new(seed);
+        }
 
         self.rep_time = 0;
-        self.rep_limit = ((1.0 - self.sample.repeat_speed).powi(2) * 20_000.0 * 32.0) as i32;
-
-        if self.sample.repeat_speed == 0.0 {
-            self.rep_limit = 0;
+        self.rep_limit = Generator::rep_limit(self.sample.repeat_speed);
+        self.elapsed = 0;
+
+        self.resample_position = 0.0;
+        self.resample_previous = 0.0;
+        self.resample_next = 0.0;
+        self.side_previous = 0.0;
+        self.side_next = 0.0;
+        self.dc_input = 0.0;
+        self.dc_output = 0.0;
+        self.amplitude = 0.0;
+    }
+    /// Returns the settings the generator was constructed with
+    pub fn config(&self) -> &GeneratorConfig {
+        &self.config
+    }
+    /// Returns a snapshot of the generator state
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// let state = generator.state();
+    /// let mut first = [0.0; 512];
+    /// generator.generate(&mut first);
+    ///
+    /// generator.restore(state);
+    /// let mut second = [0.0; 512];
+    /// generator.generate(&mut second);
+    /// assert_eq!(first[..], second[..]);
+    /// ```
+    pub fn state(&self) -> GeneratorState {
+        GeneratorState {
+            sample: self.sample.clone(),
+            volume: self.volume,
+            options: self.options,
+            config: self.config,
+            oscillator: self.oscillator.clone(),
+            hlpf: self.hlpf.clone(),
+            envelope: self.envelope.clone(),
+            phaser: self.phaser.clone(),
+            decimator: self.decimator.clone(),
+            equalizer: self.equalizer.clone(),
+            side_equalizer: self.side_equalizer.clone(),
+            compressor: self.compressor.clone(),
+            sample_hold: self.sample_hold.clone(),
+            rep_time: self.rep_time,
+            rep_limit: self.rep_limit,
+            repeat_rng: self.repeat_rng.clone(),
+            elapsed: self.elapsed,
+            resample_position: self.resample_position,
+            resample_previous: self.resample_previous,
+            resample_next: self.resample_next,
+            side_previous: self.side_previous,
+            side_next: self.side_next,
+            dc_input: self.dc_input,
+            dc_output: self.dc_output,
+            amplitude: self.amplitude,
+            note: self.note,
+            controllers: self.controllers,
+            automation: self.automation.clone(),
+            paused: self.paused,
         }
     }
+    /// Continues generating from a snapshot returned by `state`. The event handler is kept.
+    pub fn restore(&mut self, state: GeneratorState) {
+        self.sample = state.sample;
+        self.volume = state.volume;
+        self.options = state.options;
+        self.config = state.config;
+        self.oscillator = state.oscillator;
+        self.hlpf = state.hlpf;
+        self.envelope = state.envelope;
+        self.phaser = state.phaser;
+        self.decimator = state.decimator;
+        self.equalizer = state.equalizer;
+        self.side_equalizer = state.side_equalizer;
+        self.compressor = state.compressor;
+        self.sample_hold = state.sample_hold;
+        self.rep_time = state.rep_time;
+        self.rep_limit = state.rep_limit;
+        self.repeat_rng = state.repeat_rng;
+        self.elapsed = state.elapsed;
+        self.resample_position = state.resample_position;
+        self.resample_previous = state.resample_previous;
+        self.resample_next = state.resample_next;
+        self.side_previous = state.side_previous;
+        self.side_next = state.side_next;
+        self.dc_input = state.dc_input;
+        self.dc_output = state.dc_output;
+        self.amplitude = state.amplitude;
+        self.note = state.note;
+        self.controllers = state.controllers;
+        self.automation = state.automation;
+        self.paused = state.paused;
+        self.pitch_cache = None;
+    }
+    /// Returns the number of samples between repeats, or `None` if the sound does not repeat.
+    ///
+    /// The first repeat starts at sample index `interval - 1` and every following one `interval`
+    /// samples after the previous.
+    pub fn repeat_interval_samples(&self) -> Option<usize> {
+        match Generator::rep_limit(self.sample.repeat_speed) {
+            0 => None,
+            limit => Some(limit as usize),
+        }
+    }
+    /// Returns the sample index, relative to the start of the sound or latest repeat, at which
+    /// the arpeggio frequency change is applied, or `None` if the arpeggio is disabled by an
+    /// `arp_speed` of `1.0` or an `arp_mod` of `0.0`.
+    pub fn arp_trigger_sample(&self) -> Option<usize> {
+        if self.sample.arp_mod == 0.0 {
+            return None;
+        }
+        match Oscillator::arp_limit(self.sample.arp_speed) {
+            0 => None,
+            limit => Some(limit as usize - 1),
+        }
+    }
+    /// Number of samples between repeats, or `0` if the sound does not repeat
+    fn rep_limit(repeat_speed: f32) -> i32 {
+        if repeat_speed == 0.0 {
+            0
+        } else {
+            ((1.0 - repeat_speed).powi(2) * 20_000.0 * 32.0) as i32
+        }
+    }
+    fn reset_phaser(&mut self) {
+        self.phaser.reset(
+            self.sample.pha_offset,
+            self.sample.pha_ramp,
+            usize::from(self.sample.pha_length),
+            self.sample.pha_stereo,
+        );
+    }
+    /// Applies `Sample::repeat_mode` on a repeat, after `restart`
+    fn vary_repeat(&mut self) {
+        match self.sample.repeat_mode {
+            RepeatMode::Full if !self.is_finished() => {
+                // The envelope would end on the advance numbered `envelope_length`
+                let remaining = (self.sample.envelope_length() + 1).saturating_sub(self.elapsed);
+                self.envelope.retrigger(remaining as u32);
+                self.reset_phaser();
+            }
+            RepeatMode::Partial | RepeatMode::Full => {}
+            RepeatMode::Randomized {
+                pitch_semitones,
+                duty,
+            } => {
+                let pitch = (self.repeat_rng.next_f32() * 2.0 - 1.0) * pitch_semitones;
+                self.oscillator
+                    .transpose(2f64.powf(f64::from(pitch) / 12.0));
+                let duty = (self.repeat_rng.next_f32() * 2.0 - 1.0) * duty;
+                self.oscillator.offset_duty(duty);
+            }
+        }
+    }
+    /// Factor velocity scales the low pass cutoff by
+    fn lpf_scale(&self) -> f32 {
+        0.6 + 0.4 * self.options.velocity.clamp(0.0, 1.0)
+    }
     /// Resets only the oscillator and band pass filter.
     fn restart(&mut self) {
+        let lpf_scale = self.lpf_scale();
         self.hlpf.reset(
             self.sample.lpf_resonance,
-            self.sample.lpf_freq,
+            self.sample.lpf_freq * lpf_scale,
             self.sample.lpf_ramp,
             self.sample.hpf_freq,
             self.sample.hpf_ramp,
         );
+        self.hlpf.set_lpf_ramp(self.sample.lpf_target.map(|target| {
+            let target = RampTarget {
+                value: target.value * lpf_scale,
+                ..target
+            };
+            Ramp::new(self.sample.lpf_freq * lpf_scale, target, SAMPLE_RATE)
+        }));
         self.oscillator.reset(
             self.sample.wave_type,
             self.sample.base_freq,
@@ -614,6 +2115,43 @@ impl Generator {
             self.sample.arp_speed,
             self.sample.arp_mod,
         );
+        self.oscillator
+            .set_unison(self.sample.unison_voices, self.sample.unison_detune);
+        self.oscillator.set_slide_mode(self.sample.slide_mode);
+        self.oscillator.set_arp_mode(self.sample.arp_mode);
+        self.oscillator.set_duty_ramp(
+            self.sample
+                .duty_target
+                .map(|target| Ramp::new(self.sample.duty, target, SAMPLE_RATE)),
+        );
+        self.oscillator.set_noise_mode(self.sample.noise_mode);
+        self.oscillator.set_key(self.sample.snap_to_key);
+        self.oscillator
+            .set_custom_noise(self.sample.custom_noise.as_ref());
+        if self.sample.noise_retrigger {
+            self.oscillator
+                .reseed_noise(self.sample.noise_seed.unwrap_or(0));
+        }
+    }
+}
+
+impl From<Sample> for Generator {
+    /// Constructs a Generator like `Generator::new`
+    fn from(s: Sample) -> Generator {
+        Generator::new(s)
+    }
+}
+
+impl AsRef<Sample> for Generator {
+    fn as_ref(&self) -> &Sample {
+        &self.sample
+    }
+}
+
+impl AsMut<Sample> for Generator {
+    /// Returns the Sample for modification like `sample_mut`
+    fn as_mut(&mut self) -> &mut Sample {
+        self.sample_mut()
     }
 }
 
@@ -621,25 +2159,82 @@ impl Iterator for Generator {
     type Item = f32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buffer = [0.0; 1];
-        self.generate(&mut buffer);
-        Some(buffer[0])
+        Some(self.tick())
     }
 }
 
+/// Parts of the signal chain `Generator::fill` runs
+#[derive(PartialEq, Copy, Clone, Debug)]
+enum Route {
+    /// The oscillator followed by the envelope and effects
+    Full,
+    /// The oscillator alone
+    OscillatorOnly,
+    /// The envelope and effects applied to input samples
+    Filters,
+}
+
+/// Number of samples in `beats` beats at tempo `bpm`
+fn beats_to_samples(bpm: f32, beats: f32) -> f32 {
+    assert!(bpm > 0.0, "bpm must be greater than zero");
+    beats * 60.0 / bpm * SAMPLE_RATE as f32
+}
+
+/// Renders the whole sound effect of `sample` at `rate` Hz like `Generator::render_to_vec`
+///
+/// The result only depends on the arguments: a Sample without a `noise_seed` is rendered with
+/// seed `0`. Needs no threads, files or audio device, so it also works on targets such as
+/// `wasm32-wasip1`.
+pub fn render(sample: &Sample, rate: u32) -> Vec<f32> {
+    let mut sample = sample.clone();
+    sample.noise_seed.get_or_insert(0);
+    let config = GeneratorConfig {
+        sample_rate: rate,
+        ..GeneratorConfig::new()
+    };
+    Generator::new_with_config(sample, GeneratorOptions::new(), config).render_to_vec()
+}
+
+/// Converts decibels to a linear gain multiplier
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+/// Converts a linear gain multiplier to decibels. Zero gain is negative infinity.
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+/// Constant power left and right channel gains for `pan` between `-1.0` and `1.0`.
+pub(crate) fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Returns `seed`, or a new seed from the thread local generator of `rand` if it is `None`.
+#[cfg(feature = "rand")]
+fn seed_or_random(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(rand::random)
+}
 /// Generate a random `f32` using `rng` in the range [`from`...`until`).
-fn rand_f32(rng: &mut SmallRng, from: f32, until: f32) -> f32 {
-    from + (until - from) * rng.gen::<f32>()
+#[cfg(feature = "rand")]
+fn rand_f32<R: Rng + ?Sized>(rng: &mut R, from: f32, until: f32) -> f32 {
+    from + (until - from) * ((rng.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32))
 }
 /// Generate a random `f64` using `rng` in the range [`from`...`until`).
-fn rand_f64(rng: &mut SmallRng, from: f64, until: f64) -> f64 {
-    from + (until - from) * rng.gen::<f64>()
+#[cfg(feature = "rand")]
+fn rand_f64<R: Rng + ?Sized>(rng: &mut R, from: f64, until: f64) -> f64 {
+    from + (until - from) * ((rng.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64))
 }
 /// Generate a random `bool` using `rng` with `chance_true`:`chance_false` odds of being true.
-fn rand_bool(rng: &mut SmallRng, chance_true: u32, chance_false: u32) -> bool {
-    rng.gen::<u32>() % (chance_true + chance_false) < chance_true
+/// Always `false` if both are zero.
+#[cfg(feature = "rand")]
+fn rand_bool<R: Rng + ?Sized>(rng: &mut R, chance_true: u32, chance_false: u32) -> bool {
+    let total = u64::from(chance_true) + u64::from(chance_false);
+    let draw = u64::from(rng.next_u32());
+    total != 0 && draw % total < u64::from(chance_true)
 }
 /// Pick a random element from `slice` using `rng`.
-fn rand_element<T: Copy>(rng: &mut SmallRng, slice: &[T]) -> T {
-    slice[rng.gen::<u32>() as usize % slice.len()]
+#[cfg(feature = "rand")]
+fn rand_element<T: Copy, R: Rng + ?Sized>(rng: &mut R, slice: &[T]) -> T {
+    slice[rng.next_u32() as usize % slice.len()]
 }
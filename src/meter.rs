@@ -0,0 +1,95 @@
+//! Signal level metering between the Generator stages
+
+/// Peak and RMS level of a signal
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct Level {
+    /// Largest absolute sample value
+    pub peak: f32,
+    /// Root mean square of the sample values
+    pub rms: f32,
+}
+
+/// Signal levels after each stage of a Generator, measured over the last `generate` call
+///
+/// The stages run in field order. A level that drops to zero, or becomes NaN, shows which
+/// stage silences or breaks the sound, and a peak above `1.0` at `output` that it clips.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Generator, Sample};
+/// let mut sample = Sample::new();
+/// sample.volume = 0.0;
+/// let mut generator = Generator::new(sample);
+/// generator.set_metering(true);
+/// generator.generate(&mut [0.0; 4_096]);
+/// let levels = generator.meter();
+/// // Silenced after the last synthesis stage
+/// assert!(levels.phaser.peak > 0.0);
+/// assert_eq!(levels.output.peak, 0.0);
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct StageLevels {
+    /// Oscillator output, including unison voices
+    pub oscillator: Level,
+    /// After the amplitude envelope
+    pub envelope: Level,
+    /// After the low and high pass filters
+    pub filter: Level,
+    /// After the phaser
+    pub phaser: Level,
+    /// Final output after volume, gain, DC blocker and clipping
+    pub output: Level,
+}
+
+/// Running peak and sum of squares for one stage
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Accumulator {
+    peak: f32,
+    sum_squares: f64,
+    count: usize,
+}
+
+impl Accumulator {
+    pub fn add(&mut self, value: f32) {
+        // `max` would ignore NaN, which should show up in the meter
+        if value.is_nan() || value.abs() > self.peak {
+            self.peak = value.abs();
+        }
+        self.sum_squares += f64::from(value) * f64::from(value);
+        self.count += 1;
+    }
+    fn level(&self) -> Level {
+        let rms = if self.count > 0 {
+            (self.sum_squares / self.count as f64).sqrt() as f32
+        } else {
+            0.0
+        };
+        Level {
+            peak: self.peak,
+            rms,
+        }
+    }
+}
+
+/// Accumulates the levels of all stages during a `generate` call
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Meters {
+    pub oscillator: Accumulator,
+    pub envelope: Accumulator,
+    pub filter: Accumulator,
+    pub phaser: Accumulator,
+    pub output: Accumulator,
+}
+
+impl Meters {
+    pub fn levels(&self) -> StageLevels {
+        StageLevels {
+            oscillator: self.oscillator.level(),
+            envelope: self.envelope.level(),
+            filter: self.filter.level(),
+            phaser: self.phaser.level(),
+            output: self.output.level(),
+        }
+    }
+}
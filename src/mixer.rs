@@ -0,0 +1,409 @@
+use crate::edit::{duck, release_coefficient};
+use crate::stereo::wide;
+use crate::{db_to_gain, gain_to_db, pan_gains, Generator, GeneratorOptions, Widener, SAMPLE_RATE};
+
+/// Cutoff at which the master low pass filter is fully open
+const OPEN_CUTOFF: f32 = SAMPLE_RATE as f32 / 2.0;
+
+/// Plays multiple Generators simultaneously
+///
+/// Voices are mixed into interleaved stereo (left, right) frames and removed automatically
+/// once their envelope has ended. The mix is scaled by `master_gain` and clipped to
+/// `-1.0..=1.0`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Generator, Mixer, Sample};
+/// let mut mixer = Mixer::new();
+/// mixer.play(Generator::new(Sample::pickup(None)), 0.0);
+/// mixer.play(Generator::new(Sample::explosion(None)), -0.5);
+/// mixer.set_master_gain_db(-6.0);
+///
+/// let mut stereo = [0.0; 2 * 1_024];
+/// mixer.generate(&mut stereo);
+/// assert_eq!(mixer.voice_count(), 2);
+/// ```
+///
+/// Muffled slow motion, as when the player dives underwater
+///
+/// ``` rust
+/// use sfxr::{Generator, Mixer, Sample};
+/// let mut mixer = Mixer::new();
+/// mixer.play(Generator::new(Sample::explosion(None)), 0.0);
+/// mixer.set_lowpass(Some(500.0), 0.5);
+/// mixer.set_playback_rate(0.5);
+/// ```
+///
+/// Ambience that dips under gunfire
+///
+/// ``` rust
+/// use sfxr::{Generator, Mixer, Sample};
+/// let mut mixer = Mixer::new();
+/// mixer.set_ducking(0.7, 0.3);
+/// mixer.play_ducked(Generator::new(Sample::explosion(Some(4))), 0.0);
+/// mixer.play(Generator::new(Sample::laser(None)), 0.5);
+/// ```
+pub struct Mixer {
+    /// Linear gain applied to the whole mix. Default is `1.0`.
+    pub master_gain: f32,
+    voices: Vec<Voice>,
+    scratch: Vec<f32>,
+    lowpass: Lowpass,
+    playback_rate: f32,
+    max_voices: Option<usize>,
+    stealing: VoiceStealing,
+    started: u64,
+    duck_amount: f32,
+    duck_release: f32,
+    duck_level: f32,
+    duck_envelope: Vec<f32>,
+}
+
+/// Which voice to stop when a new voice is played while the Mixer is at its voice limit
+///
+/// Only voices with at most the priority of the new voice are considered, lowest priority
+/// first. If there are none, the new voice is not played.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum VoiceStealing {
+    /// Stop the voice that started playing first
+    Oldest,
+    /// Stop the voice with the lowest peak level in the last generated buffer
+    Quietest,
+    /// Never stop playing voices, drop the new voice instead
+    None,
+}
+
+struct Voice {
+    generator: Generator,
+    pan: f32,
+    options: GeneratorOptions,
+    priority: u8,
+    started: u64,
+    level: f32,
+    delay: usize,
+    ducked: bool,
+    /// Only for voices with a non-zero width, as the delay line is large
+    widener: Option<Box<Widener>>,
+}
+
+/// One pole low pass filter on the stereo mix with an exponential cutoff sweep
+struct Lowpass {
+    cutoff: f32,
+    target: f32,
+    step: f32,
+    coefficient: f32,
+    state: [f32; 2],
+}
+
+impl Lowpass {
+    fn process(&mut self, buffer: &mut [f32]) {
+        if self.cutoff >= OPEN_CUTOFF && self.target >= OPEN_CUTOFF {
+            self.state = [0.0; 2];
+            return;
+        }
+        for frame in buffer.chunks_mut(2) {
+            if self.cutoff != self.target {
+                self.cutoff *= self.step;
+                if (self.step > 1.0) == (self.cutoff >= self.target) {
+                    self.cutoff = self.target;
+                }
+                self.coefficient = Lowpass::coefficient(self.cutoff);
+            }
+            for (v, state) in frame.iter_mut().zip(self.state.iter_mut()) {
+                *state += (*v - *state) * self.coefficient;
+                *v = *state;
+            }
+        }
+    }
+    fn coefficient(cutoff: f32) -> f32 {
+        1.0 - (-2.0 * std::f32::consts::PI * cutoff / SAMPLE_RATE as f32).exp()
+    }
+}
+
+#[allow(clippy::new_without_default)]
+impl Mixer {
+    /// Constructs a new Mixer without voices
+    pub fn new() -> Mixer {
+        Mixer {
+            master_gain: 1.0,
+            voices: Vec::new(),
+            scratch: Vec::new(),
+            lowpass: Lowpass {
+                cutoff: OPEN_CUTOFF,
+                target: OPEN_CUTOFF,
+                step: 1.0,
+                coefficient: Lowpass::coefficient(OPEN_CUTOFF),
+                state: [0.0; 2],
+            },
+            playback_rate: 1.0,
+            max_voices: None,
+            stealing: VoiceStealing::Oldest,
+            started: 0,
+            duck_amount: 0.0,
+            duck_release: 0.0,
+            duck_level: 0.0,
+            duck_envelope: Vec::new(),
+        }
+    }
+    /// Starts playing `generator` from its current position with priority `0`. `pan` must be
+    /// between `-1.0` (left) and `1.0` (right).
+    pub fn play(&mut self, generator: Generator, pan: f32) {
+        self.play_with_priority(generator, pan, 0);
+    }
+    /// Starts playing `generator` like `play`, with `priority` deciding which voices may be
+    /// stopped to make room at the voice limit. Returns `false` if the voice limit was reached
+    /// and the generator was not played.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Mixer, Sample, VoiceStealing};
+    /// let mut mixer = Mixer::new();
+    /// mixer.set_voice_limit(Some(8), VoiceStealing::Oldest);
+    /// mixer.play_with_priority(Generator::new(Sample::explosion(None)), 0.0, 10);
+    /// for _ in 0..60 {
+    ///     mixer.play(Generator::new(Sample::pickup(None)), 0.0);
+    /// }
+    /// assert_eq!(mixer.voice_count(), 8);
+    /// ```
+    pub fn play_with_priority(&mut self, generator: Generator, pan: f32, priority: u8) -> bool {
+        self.start(generator, pan, priority, 0, false, 0.0)
+    }
+    /// Starts playing `generator` like `play`, `frame_offset` stereo frames into the next
+    /// buffer passed to `generate`. Offsets past the end of that buffer carry over to the
+    /// following buffers, so sounds can be scheduled with sample accuracy, for example on the
+    /// beat in a rhythm game. Returns `false` if the voice limit was reached.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Mixer, Sample};
+    /// let mut mixer = Mixer::new();
+    /// mixer.play_at(Generator::new(Sample::new()), 0.0, 100);
+    ///
+    /// let mut stereo = [0.0; 2 * 256];
+    /// mixer.generate(&mut stereo);
+    /// assert!(stereo[..2 * 100].iter().all(|&v| v == 0.0));
+    /// ```
+    pub fn play_at(&mut self, generator: Generator, pan: f32, frame_offset: usize) -> bool {
+        self.start(generator, pan, 0, frame_offset, false, 0.0)
+    }
+    /// Starts playing `generator` like `play`, as a background layer such as music or ambience
+    /// that is lowered while other voices play, see `set_ducking`. Returns `false` if the voice
+    /// limit was reached.
+    pub fn play_ducked(&mut self, generator: Generator, pan: f32) -> bool {
+        self.start(generator, pan, 0, 0, true, 0.0)
+    }
+    /// Starts playing `generator` like `play`, spread across the stereo field by a `Widener`
+    /// with `width` between `0.0` (mono) and `1.0` (widest), for example to make big
+    /// explosions feel wide. Returns `false` if the voice limit was reached.
+    pub fn play_with_width(&mut self, generator: Generator, pan: f32, width: f32) -> bool {
+        self.start(generator, pan, 0, 0, false, width)
+    }
+    /// Lowers voices played with `play_ducked` while the other voices play, by up to `amount`
+    /// between `0.0` and `1.0` at full scale, like `edit::duck`. The dip follows the peak level
+    /// of the other voices and recovers over `release_secs` seconds. Default `amount` is `0.0`,
+    /// no ducking.
+    pub fn set_ducking(&mut self, amount: f32, release_secs: f32) {
+        assert!(
+            (0.0..=1.0).contains(&amount),
+            "amount must be between 0.0 and 1.0"
+        );
+        self.duck_amount = amount;
+        self.duck_release =
+            release_coefficient((release_secs.max(0.0) * SAMPLE_RATE as f32) as usize);
+    }
+    fn start(
+        &mut self,
+        generator: Generator,
+        pan: f32,
+        priority: u8,
+        delay: usize,
+        ducked: bool,
+        width: f32,
+    ) -> bool {
+        assert!(
+            (-1.0..=1.0).contains(&pan),
+            "pan must be between -1.0 and 1.0"
+        );
+
+        if let Some(max_voices) = self.max_voices {
+            if self.voices.len() >= max_voices {
+                match self.victim(priority) {
+                    Some(index) => {
+                        self.voices.swap_remove(index);
+                    }
+                    None => return false,
+                }
+            }
+        }
+
+        self.started += 1;
+        let options = generator.options;
+        let mut voice = Voice {
+            generator,
+            pan,
+            options,
+            priority,
+            started: self.started,
+            level: f32::INFINITY,
+            delay,
+            ducked,
+            widener: wide(width),
+        };
+        voice.apply_playback_rate(self.playback_rate);
+        self.voices.push(voice);
+        true
+    }
+    /// Limits the number of simultaneously playing voices to `max_voices`, stopping voices
+    /// according to `stealing` to make room for new ones. `None` removes the limit, which is
+    /// the default. Voices above a lowered limit keep playing until they end.
+    pub fn set_voice_limit(&mut self, max_voices: Option<usize>, stealing: VoiceStealing) {
+        self.max_voices = max_voices;
+        self.stealing = stealing;
+    }
+    /// Index of the voice to stop for a new voice with `priority`
+    fn victim(&self, priority: u8) -> Option<usize> {
+        let candidates = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter(|(_, voice)| voice.priority <= priority);
+        match self.stealing {
+            VoiceStealing::Oldest => candidates
+                .min_by_key(|(_, voice)| (voice.priority, voice.started))
+                .map(|(index, _)| index),
+            VoiceStealing::Quietest => candidates
+                .min_by(|(_, a), (_, b)| {
+                    a.priority
+                        .cmp(&b.priority)
+                        .then(a.level.total_cmp(&b.level))
+                })
+                .map(|(index, _)| index),
+            VoiceStealing::None => None,
+        }
+    }
+    /// Returns the number of currently playing voices
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+    /// Stops all voices
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+    /// Returns the master gain in decibels
+    pub fn master_gain_db(&self) -> f32 {
+        gain_to_db(self.master_gain)
+    }
+    /// Sets the master gain in decibels
+    pub fn set_master_gain_db(&mut self, db: f32) {
+        self.master_gain = db_to_gain(db);
+    }
+    /// Sweeps the cutoff of a low pass filter on the whole mix to `cutoff_hz` over
+    /// `sweep_secs` seconds, for example to muffle all sounds while underwater. `None` sweeps
+    /// the filter open and then bypasses it. The sweep is exponential, so it sounds even.
+    pub fn set_lowpass(&mut self, cutoff_hz: Option<f32>, sweep_secs: f32) {
+        let lowpass = &mut self.lowpass;
+        lowpass.target = cutoff_hz.map_or(OPEN_CUTOFF, |hz| hz.clamp(10.0, OPEN_CUTOFF));
+        let frames = sweep_secs * SAMPLE_RATE as f32;
+        if frames >= 1.0 {
+            lowpass.step = (lowpass.target / lowpass.cutoff).powf(1.0 / frames);
+        } else {
+            lowpass.cutoff = lowpass.target;
+            lowpass.coefficient = Lowpass::coefficient(lowpass.target);
+        }
+    }
+    /// Returns the current master low pass cutoff in Hz, or `None` if the filter is open
+    pub fn lowpass_cutoff(&self) -> Option<f32> {
+        if self.lowpass.cutoff >= OPEN_CUTOFF {
+            None
+        } else {
+            Some(self.lowpass.cutoff)
+        }
+    }
+    /// Scales the playback rate of all voices, including those played later, like changing
+    /// the speed of a tape: `0.5` plays at half speed an octave lower for slow motion. Must be
+    /// greater than `0.0`. Default is `1.0`.
+    pub fn set_playback_rate(&mut self, rate: f32) {
+        assert!(rate > 0.0, "rate must be greater than zero");
+        self.playback_rate = rate;
+        for voice in self.voices.iter_mut() {
+            voice.apply_playback_rate(rate);
+        }
+    }
+    /// Returns the playback rate set with `set_playback_rate`
+    pub fn playback_rate(&self) -> f32 {
+        self.playback_rate
+    }
+    /// Fills `buffer` with interleaved stereo frames of all playing voices
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        assert!(
+            buffer.len().is_multiple_of(2),
+            "buffer must hold a whole number of stereo frames"
+        );
+        buffer.iter_mut().for_each(|v| *v = 0.0);
+        let frames = buffer.len() / 2;
+        self.scratch.resize(frames, 0.0);
+        self.duck_envelope.clear();
+        self.duck_envelope.resize(frames, 0.0);
+
+        // Mix the ducking triggers first, recording their peak level, then the ducked layers
+        for ducked in [false, true].iter() {
+            if *ducked && self.duck_amount > 0.0 {
+                let release = self.duck_release;
+                for level in self.duck_envelope.iter_mut() {
+                    self.duck_level = level.max(self.duck_level * release);
+                    *level = self.duck_level;
+                }
+            }
+
+            for voice in self
+                .voices
+                .iter_mut()
+                .filter(|voice| voice.ducked == *ducked)
+            {
+                let skip = voice.delay.min(frames);
+                voice.delay -= skip;
+                if skip == frames {
+                    continue;
+                }
+
+                let scratch = &mut self.scratch[..frames - skip];
+                voice.generator.generate(scratch);
+                voice.level = scratch.iter().fold(0.0, |peak, v| v.abs().max(peak));
+                if *ducked {
+                    duck(scratch, &self.duck_envelope[skip..], self.duck_amount);
+                } else {
+                    for (level, v) in self.duck_envelope[skip..].iter_mut().zip(scratch.iter()) {
+                        *level += v.abs();
+                    }
+                }
+
+                let (left, right) = pan_gains(voice.pan);
+                for (frame, &v) in buffer.chunks_mut(2).skip(skip).zip(scratch.iter()) {
+                    let (v_left, v_right) = voice.widener.as_mut().map_or((v, v), |w| w.process(v));
+                    frame[0] += v_left * left;
+                    frame[1] += v_right * right;
+                }
+            }
+        }
+
+        self.voices.retain(|voice| !voice.generator.is_finished());
+        self.lowpass.process(buffer);
+
+        let master_gain = self.master_gain;
+        buffer
+            .iter_mut()
+            .for_each(|v| *v = (*v * master_gain).clamp(-1.0, 1.0));
+    }
+}
+
+impl Voice {
+    /// Sets the generator options to the ones the voice was played with, scaled by `rate`
+    fn apply_playback_rate(&mut self, rate: f32) {
+        let options = &mut self.generator.options;
+        options.speed = self.options.speed * rate;
+        options.pitch_offset_semitones = self.options.pitch_offset_semitones + 12.0 * rate.log2();
+    }
+}
@@ -0,0 +1,135 @@
+//! Playing a Generator as a synthesizer voice
+
+use crate::scale::midi_to_hz;
+use crate::{Generator, Sample};
+
+/// A key press, key release or wheel movement of a keyboard, played with
+/// `Generator::handle_note`
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::NoteEvent;
+/// assert_eq!(
+///     NoteEvent::from_midi(&[0x90, 60, 100]),
+///     Some(NoteEvent::On { note: 60, velocity: 100 })
+/// );
+/// // A note on message with velocity zero releases the key
+/// assert_eq!(NoteEvent::from_midi(&[0x90, 60, 0]), Some(NoteEvent::Off { note: 60 }));
+/// // The modulation wheel halfway up
+/// assert_eq!(
+///     NoteEvent::from_midi(&[0xB0, 1, 64]),
+///     Some(NoteEvent::Modulation { amount: 64.0 / 127.0 })
+/// );
+/// assert_eq!(NoteEvent::from_midi(&[0xC0, 5]), None);
+/// ```
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum NoteEvent {
+    /// Key `note`, a MIDI note number, was pressed with `velocity` between `1` and `127`
+    On {
+        /// MIDI note number, `60` being middle C
+        note: u8,
+        /// How hard the key was pressed, between `1` and `127`
+        velocity: u8,
+    },
+    /// Key `note` was released
+    Off {
+        /// MIDI note number, `60` being middle C
+        note: u8,
+    },
+    /// The pitch wheel moved, see `Generator::set_pitch_bend`
+    PitchBend {
+        /// Bend in semitones
+        semitones: f32,
+    },
+    /// The modulation wheel moved, see `Generator::set_mod_amount`
+    Modulation {
+        /// Modulation amount between `0.0` and `1.0`
+        amount: f32,
+    },
+}
+
+impl NoteEvent {
+    /// Range of the pitch wheel in semitones either way, the General MIDI default
+    pub const PITCH_BEND_RANGE: f32 = 2.0;
+
+    /// Parses a MIDI note on, note off, pitch bend or modulation wheel message on any
+    /// channel, or returns `None` for other messages. The pitch wheel bends by up to
+    /// `PITCH_BEND_RANGE`.
+    pub fn from_midi(message: &[u8]) -> Option<NoteEvent> {
+        match *message {
+            [status, a, b] if a < 0x80 && b < 0x80 => match status & 0xF0 {
+                0x90 if b > 0 => Some(NoteEvent::On {
+                    note: a,
+                    velocity: b,
+                }),
+                0x80 | 0x90 => Some(NoteEvent::Off { note: a }),
+                0xB0 if a == 1 => Some(NoteEvent::Modulation {
+                    amount: f32::from(b) / 127.0,
+                }),
+                0xE0 => {
+                    // 14 bits, least significant first, centered at 8192
+                    let value = (i32::from(b) << 7 | i32::from(a)) - 8192;
+                    Some(NoteEvent::PitchBend {
+                        semitones: value as f32 / 8192.0 * NoteEvent::PITCH_BEND_RANGE,
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Generator {
+    /// Plays MIDI note `note` from the beginning of the sound at `velocity` between `0` and
+    /// `127`, holding the envelope in its sustain stage until `note_off`
+    ///
+    /// Sets `Sample::base_freq` to the pitch of the note and `GeneratorOptions::velocity`, so
+    /// the Sample is used as the timbre of a monophonic synthesizer voice. Notes outside the
+    /// range of `Sample::freq_from_hz` are clamped, and a `Sample::freq_limit` above the note
+    /// cuts it short.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Generator, Sample};
+    /// let mut generator = Generator::new(Sample::new());
+    /// generator.note_on(69, 127);
+    /// let mut buffer = vec![0.0; 2 * 44_100];
+    /// generator.generate(&mut buffer);
+    /// assert!(generator.amplitude_envelope() > 0.05);
+    ///
+    /// generator.note_off();
+    /// generator.generate(&mut buffer);
+    /// assert!(generator.amplitude_envelope() < 0.001);
+    /// ```
+    pub fn note_on(&mut self, note: u8, velocity: u8) {
+        self.sample.base_freq = Sample::freq_from_hz(midi_to_hz(f64::from(note)));
+        self.options.velocity = f32::from(velocity.min(127)) / 127.0;
+        self.set_envelope_hold(true);
+        self.note = Some(note);
+        self.reset();
+    }
+    /// Releases the held note, letting the envelope continue to its decay
+    pub fn note_off(&mut self) {
+        self.set_envelope_hold(false);
+        self.note = None;
+    }
+    /// Plays `event` with `note_on`, `set_pitch_bend` or `set_mod_amount`, or `note_off` if it
+    /// releases the note playing. Releases of other notes are ignored, so legato playing keeps
+    /// the latest note held.
+    pub fn handle_note(&mut self, event: NoteEvent) {
+        match event {
+            NoteEvent::On { note, velocity } => self.note_on(note, velocity),
+            NoteEvent::Off { note } if self.note == Some(note) => self.note_off(),
+            NoteEvent::Off { .. } => {}
+            NoteEvent::PitchBend { semitones } => self.set_pitch_bend(semitones),
+            NoteEvent::Modulation { amount } => self.set_mod_amount(amount),
+        }
+    }
+    /// Returns the note held since `note_on`, or `None` after `note_off`
+    pub fn held_note(&self) -> Option<u8> {
+        self.note
+    }
+}
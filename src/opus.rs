@@ -0,0 +1,91 @@
+//! Encoding of sound effects to Opus frames
+//!
+//! Produces the 20 ms stereo 48 kHz Opus packets that Discord bots and WebRTC applications send,
+//! straight from a Sample without an intermediate WAV file or separate encoding step.
+//!
+//! # Examples
+//!
+//! ``` rust no_run
+//! use sfxr::opus::OpusFrames;
+//! use sfxr::Sample;
+//! for packet in OpusFrames::new(Sample::new()).unwrap() {
+//!     let packet = packet.unwrap();
+//!     // Send `packet` to the voice connection every 20 ms
+//! }
+//! ```
+
+use audiopus::coder::Encoder;
+use audiopus::{Application, Channels, SampleRate};
+
+use crate::{Error, Generator, GeneratorConfig, GeneratorOptions, Sample};
+
+/// Sample rate of the encoded audio in Hz
+pub const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// Samples per channel in one 20 ms frame
+pub const FRAME_SAMPLES: usize = 960;
+/// Largest encoded packet size in bytes, as recommended by libopus
+const MAX_PACKET: usize = 4_000;
+
+/// Iterator over the Opus packets of a sound effect
+///
+/// Each item is one 20 ms frame of stereo audio, with the mono sound effect on both channels.
+/// Iteration ends once the envelope has ended and a frame is silent.
+pub struct OpusFrames {
+    generator: Generator,
+    encoder: Encoder,
+    mono: [f32; FRAME_SAMPLES],
+    stereo: [f32; FRAME_SAMPLES * 2],
+    finished: bool,
+}
+
+impl OpusFrames {
+    /// Constructs a new OpusFrames encoding `sample`
+    pub fn new(sample: Sample) -> Result<OpusFrames, Error> {
+        OpusFrames::new_with(sample, GeneratorOptions::new())
+    }
+    /// Constructs a new OpusFrames encoding `sample` played with `options`
+    pub fn new_with(sample: Sample, options: GeneratorOptions) -> Result<OpusFrames, Error> {
+        let mut config = GeneratorConfig::new();
+        config.sample_rate = OPUS_SAMPLE_RATE;
+        Ok(OpusFrames {
+            generator: Generator::new_with_config(sample, options, config),
+            encoder: Encoder::new(SampleRate::Hz48000, Channels::Stereo, Application::Audio)?,
+            mono: [0.0; FRAME_SAMPLES],
+            stereo: [0.0; FRAME_SAMPLES * 2],
+            finished: false,
+        })
+    }
+    /// Returns the Generator producing the audio, for example to change its volume
+    pub fn generator_mut(&mut self) -> &mut Generator {
+        &mut self.generator
+    }
+    /// Encodes the next frame, or returns `None` once the sound has ended
+    pub fn next_frame(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        self.generator.generate(&mut self.mono);
+        if self.generator.is_finished() && self.mono.iter().all(|v| v.abs() < 1.0e-3) {
+            self.finished = true;
+            return Ok(None);
+        }
+
+        for (frame, &v) in self.stereo.chunks_mut(2).zip(self.mono.iter()) {
+            frame[0] = v;
+            frame[1] = v;
+        }
+        let mut packet = vec![0; MAX_PACKET];
+        let len = self.encoder.encode_float(&self.stereo, &mut packet)?;
+        packet.truncate(len);
+        Ok(Some(packet))
+    }
+}
+
+impl Iterator for OpusFrames {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
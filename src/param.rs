@@ -0,0 +1,567 @@
+#[cfg(feature = "rand")]
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "rand")]
+use crate::{rand_element, rand_f64};
+use crate::{RangeError, Sample, WaveType};
+
+/// Names a numeric, boolean or wave type Sample field
+///
+/// Fields holding structures, like `equalizer`, or modes, like `slide_mode`, have no Param.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Param {
+    /// `Sample::base_freq`
+    BaseFreq,
+    /// `Sample::freq_limit`
+    FreqLimit,
+    /// `Sample::freq_ramp`
+    FreqRamp,
+    /// `Sample::freq_dramp`
+    FreqDramp,
+    /// `Sample::duty`
+    Duty,
+    /// `Sample::duty_ramp`
+    DutyRamp,
+    /// `Sample::vib_strength`
+    VibStrength,
+    /// `Sample::vib_speed`
+    VibSpeed,
+    /// `Sample::vib_delay`
+    VibDelay,
+    /// `Sample::env_attack`
+    EnvAttack,
+    /// `Sample::env_sustain`
+    EnvSustain,
+    /// `Sample::env_decay`
+    EnvDecay,
+    /// `Sample::env_punch`
+    EnvPunch,
+    /// `Sample::lpf_resonance`
+    LpfResonance,
+    /// `Sample::lpf_freq`
+    LpfFreq,
+    /// `Sample::lpf_ramp`
+    LpfRamp,
+    /// `Sample::hpf_freq`
+    HpfFreq,
+    /// `Sample::hpf_ramp`
+    HpfRamp,
+    /// `Sample::pha_offset`
+    PhaOffset,
+    /// `Sample::pha_ramp`
+    PhaRamp,
+    /// `Sample::repeat_speed`
+    RepeatSpeed,
+    /// `Sample::arp_speed`
+    ArpSpeed,
+    /// `Sample::arp_mod`
+    ArpMod,
+    /// `Sample::unison_detune`
+    UnisonDetune,
+    /// `Sample::wave_type`, as its `WaveType::as_index`
+    WaveType,
+    /// `Sample::unison_voices`
+    UnisonVoices,
+    /// `Sample::volume`
+    Volume,
+    /// `Sample::noise_retrigger`, `1.0` for `true` and `0.0` for `false`
+    NoiseRetrigger,
+    /// `Sample::sample_hold_secs`
+    SampleHoldSecs,
+    /// `Sample::pha_length`
+    PhaLength,
+    /// `Sample::pha_stereo`
+    PhaStereo,
+    /// `Sample::gain_db`
+    GainDb,
+}
+
+impl Param {
+    /// All parameters in Sample field order
+    pub const ALL: [Param; 32] = [
+        Param::WaveType,
+        Param::BaseFreq,
+        Param::FreqLimit,
+        Param::FreqRamp,
+        Param::FreqDramp,
+        Param::Duty,
+        Param::DutyRamp,
+        Param::VibStrength,
+        Param::VibSpeed,
+        Param::VibDelay,
+        Param::EnvAttack,
+        Param::EnvSustain,
+        Param::EnvDecay,
+        Param::EnvPunch,
+        Param::LpfResonance,
+        Param::LpfFreq,
+        Param::LpfRamp,
+        Param::HpfFreq,
+        Param::HpfRamp,
+        Param::PhaOffset,
+        Param::PhaRamp,
+        Param::RepeatSpeed,
+        Param::ArpSpeed,
+        Param::ArpMod,
+        Param::UnisonVoices,
+        Param::UnisonDetune,
+        Param::Volume,
+        Param::NoiseRetrigger,
+        Param::SampleHoldSecs,
+        Param::PhaLength,
+        Param::PhaStereo,
+        Param::GainDb,
+    ];
+    /// The sound design parameters randomized by presets, `Sample::random_in` and the other
+    /// random functions, in Sample field order. Their values are between `-1.0` and `1.0`.
+    pub const RANDOMIZED: [Param; 24] = [
+        Param::BaseFreq,
+        Param::FreqLimit,
+        Param::FreqRamp,
+        Param::FreqDramp,
+        Param::Duty,
+        Param::DutyRamp,
+        Param::VibStrength,
+        Param::VibSpeed,
+        Param::VibDelay,
+        Param::EnvAttack,
+        Param::EnvSustain,
+        Param::EnvDecay,
+        Param::EnvPunch,
+        Param::LpfResonance,
+        Param::LpfFreq,
+        Param::LpfRamp,
+        Param::HpfFreq,
+        Param::HpfRamp,
+        Param::PhaOffset,
+        Param::PhaRamp,
+        Param::RepeatSpeed,
+        Param::ArpSpeed,
+        Param::ArpMod,
+        Param::UnisonDetune,
+    ];
+
+    /// Returns the name of the corresponding Sample field
+    pub fn name(self) -> &'static str {
+        match self {
+            Param::BaseFreq => "base_freq",
+            Param::FreqLimit => "freq_limit",
+            Param::FreqRamp => "freq_ramp",
+            Param::FreqDramp => "freq_dramp",
+            Param::Duty => "duty",
+            Param::DutyRamp => "duty_ramp",
+            Param::VibStrength => "vib_strength",
+            Param::VibSpeed => "vib_speed",
+            Param::VibDelay => "vib_delay",
+            Param::EnvAttack => "env_attack",
+            Param::EnvSustain => "env_sustain",
+            Param::EnvDecay => "env_decay",
+            Param::EnvPunch => "env_punch",
+            Param::LpfResonance => "lpf_resonance",
+            Param::LpfFreq => "lpf_freq",
+            Param::LpfRamp => "lpf_ramp",
+            Param::HpfFreq => "hpf_freq",
+            Param::HpfRamp => "hpf_ramp",
+            Param::PhaOffset => "pha_offset",
+            Param::PhaRamp => "pha_ramp",
+            Param::RepeatSpeed => "repeat_speed",
+            Param::ArpSpeed => "arp_speed",
+            Param::ArpMod => "arp_mod",
+            Param::UnisonDetune => "unison_detune",
+            Param::WaveType => "wave_type",
+            Param::UnisonVoices => "unison_voices",
+            Param::Volume => "volume",
+            Param::NoiseRetrigger => "noise_retrigger",
+            Param::SampleHoldSecs => "sample_hold_secs",
+            Param::PhaLength => "pha_length",
+            Param::PhaStereo => "pha_stereo",
+            Param::GainDb => "gain_db",
+        }
+    }
+
+    /// Returns the parameter of the Sample field named `name`, the inverse of `name`
+    pub fn from_name(name: &str) -> Option<Param> {
+        Param::ALL
+            .iter()
+            .copied()
+            .find(|param| param.name() == name)
+    }
+
+    /// Returns `true` if the corresponding Sample field is an `f64` rather than an `f32`
+    #[cfg_attr(not(feature = "rand"), allow(dead_code))]
+    pub(crate) fn is_f64(self) -> bool {
+        matches!(
+            self,
+            Param::BaseFreq
+                | Param::FreqLimit
+                | Param::FreqRamp
+                | Param::FreqDramp
+                | Param::VibStrength
+                | Param::VibSpeed
+                | Param::ArpMod
+        )
+    }
+
+    /// Returns `true` if the corresponding Sample field is an integer or a boolean, which
+    /// `Sample::set` rounds values for
+    pub fn is_integer(self) -> bool {
+        matches!(
+            self,
+            Param::WaveType | Param::UnisonVoices | Param::NoiseRetrigger | Param::PhaLength
+        )
+    }
+
+    /// Returns the valid `(min, max)` range of the parameter
+    pub fn range(self) -> (f64, f64) {
+        match self {
+            Param::WaveType => (0.0, 4.0),
+            Param::UnisonVoices => (0.0, f64::from(u8::MAX)),
+            Param::SampleHoldSecs => (0.0, f64::INFINITY),
+            Param::PhaLength => (0.0, f64::from(u16::MAX)),
+            Param::GainDb => (f64::from(f32::MIN), f64::from(f32::MAX)),
+            Param::FreqRamp
+            | Param::FreqDramp
+            | Param::DutyRamp
+            | Param::EnvPunch
+            | Param::LpfRamp
+            | Param::HpfRamp
+            | Param::PhaOffset
+            | Param::PhaRamp
+            | Param::ArpMod
+            | Param::PhaStereo => (-1.0, 1.0),
+            _ => (0.0, 1.0),
+        }
+    }
+}
+
+impl Sample {
+    /// Returns the value of the field named by `param`
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::{Param, Sample};
+    /// let mut sample = Sample::new();
+    /// // For example from a script or a network message
+    /// let param = Param::from_name("volume").unwrap();
+    /// sample.set(param, 0.25).unwrap();
+    /// assert_eq!(sample.get(Param::Volume), 0.25);
+    /// assert!(sample.set(Param::LpfFreq, 2.0).is_err());
+    /// ```
+    pub fn get(&self, param: Param) -> f64 {
+        self.param(param)
+    }
+    /// Sets the field named by `param` to `value`. Integer and boolean fields take the value
+    /// rounded to the nearest integer, with `0.0` as `false` for booleans. Fails, leaving the
+    /// field unchanged, if the value is outside `param.range()`.
+    pub fn set(&mut self, param: Param, value: f64) -> Result<(), RangeError> {
+        let (min, max) = param.range();
+        if !(min..=max).contains(&value) {
+            return Err(RangeError {
+                param: Some(param),
+                value,
+                min,
+                max,
+            });
+        }
+        self.set_param(param, value);
+        Ok(())
+    }
+
+    /// Returns the value of the field named by `param`
+    pub(crate) fn param(&self, param: Param) -> f64 {
+        match param {
+            Param::BaseFreq => self.base_freq,
+            Param::FreqLimit => self.freq_limit,
+            Param::FreqRamp => self.freq_ramp,
+            Param::FreqDramp => self.freq_dramp,
+            Param::Duty => f64::from(self.duty),
+            Param::DutyRamp => f64::from(self.duty_ramp),
+            Param::VibStrength => self.vib_strength,
+            Param::VibSpeed => self.vib_speed,
+            Param::VibDelay => f64::from(self.vib_delay),
+            Param::EnvAttack => f64::from(self.env_attack),
+            Param::EnvSustain => f64::from(self.env_sustain),
+            Param::EnvDecay => f64::from(self.env_decay),
+            Param::EnvPunch => f64::from(self.env_punch),
+            Param::LpfResonance => f64::from(self.lpf_resonance),
+            Param::LpfFreq => f64::from(self.lpf_freq),
+            Param::LpfRamp => f64::from(self.lpf_ramp),
+            Param::HpfFreq => f64::from(self.hpf_freq),
+            Param::HpfRamp => f64::from(self.hpf_ramp),
+            Param::PhaOffset => f64::from(self.pha_offset),
+            Param::PhaRamp => f64::from(self.pha_ramp),
+            Param::RepeatSpeed => f64::from(self.repeat_speed),
+            Param::ArpSpeed => f64::from(self.arp_speed),
+            Param::ArpMod => self.arp_mod,
+            Param::UnisonDetune => f64::from(self.unison_detune),
+            Param::WaveType => f64::from(self.wave_type.as_index()),
+            Param::UnisonVoices => f64::from(self.unison_voices),
+            Param::Volume => f64::from(self.volume),
+            Param::NoiseRetrigger => f64::from(u8::from(self.noise_retrigger)),
+            Param::SampleHoldSecs => f64::from(self.sample_hold_secs),
+            Param::PhaLength => f64::from(self.pha_length),
+            Param::PhaStereo => f64::from(self.pha_stereo),
+            Param::GainDb => f64::from(self.gain_db),
+        }
+    }
+
+    /// Sets the value of the field named by `param` without validating it
+    #[cfg_attr(not(feature = "rand"), allow(dead_code))]
+    pub(crate) fn set_param(&mut self, param: Param, value: f64) {
+        match param {
+            Param::BaseFreq => self.base_freq = value,
+            Param::FreqLimit => self.freq_limit = value,
+            Param::FreqRamp => self.freq_ramp = value,
+            Param::FreqDramp => self.freq_dramp = value,
+            Param::Duty => self.duty = value as f32,
+            Param::DutyRamp => self.duty_ramp = value as f32,
+            Param::VibStrength => self.vib_strength = value,
+            Param::VibSpeed => self.vib_speed = value,
+            Param::VibDelay => self.vib_delay = value as f32,
+            Param::EnvAttack => self.env_attack = value as f32,
+            Param::EnvSustain => self.env_sustain = value as f32,
+            Param::EnvDecay => self.env_decay = value as f32,
+            Param::EnvPunch => self.env_punch = value as f32,
+            Param::LpfResonance => self.lpf_resonance = value as f32,
+            Param::LpfFreq => self.lpf_freq = value as f32,
+            Param::LpfRamp => self.lpf_ramp = value as f32,
+            Param::HpfFreq => self.hpf_freq = value as f32,
+            Param::HpfRamp => self.hpf_ramp = value as f32,
+            Param::PhaOffset => self.pha_offset = value as f32,
+            Param::PhaRamp => self.pha_ramp = value as f32,
+            Param::RepeatSpeed => self.repeat_speed = value as f32,
+            Param::ArpSpeed => self.arp_speed = value as f32,
+            Param::ArpMod => self.arp_mod = value,
+            Param::UnisonDetune => self.unison_detune = value as f32,
+            Param::WaveType => {
+                if let Some(wave_type) = WaveType::from_index(value.round() as u8) {
+                    self.wave_type = wave_type;
+                }
+            }
+            Param::UnisonVoices => self.unison_voices = value.round() as u8,
+            Param::Volume => self.volume = value as f32,
+            Param::NoiseRetrigger => self.noise_retrigger = value.round() != 0.0,
+            Param::SampleHoldSecs => self.sample_hold_secs = value as f32,
+            Param::PhaLength => self.pha_length = value.round() as u16,
+            Param::PhaStereo => self.pha_stereo = value as f32,
+            Param::GainDb => self.gain_db = value as f32,
+        }
+    }
+}
+
+/// Caller defined ranges for randomizing Samples with `Sample::random_in`
+///
+/// # Examples
+///
+/// A "house style" of soft, low blips
+///
+/// ``` rust
+/// use rand::rngs::SmallRng;
+/// use rand::SeedableRng;
+/// use sfxr::{Param, ParamRanges, Sample, WaveType};
+/// let mut ranges = ParamRanges::new();
+/// ranges.wave_types = vec![WaveType::Sine, WaveType::Triangle];
+/// ranges.set(Param::BaseFreq, 0.1, 0.3);
+/// ranges.set(Param::EnvAttack, 0.0, 0.0);
+/// ranges.set(Param::EnvDecay, 0.1, 0.3);
+///
+/// let rng = &mut SmallRng::seed_from_u64(0);
+/// let sample = Sample::random_in(&ranges, rng);
+/// assert!(sample.base_freq >= 0.1 && sample.base_freq <= 0.3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ParamRanges {
+    /// Wave types to choose from with equal probability. Must not be empty.
+    pub wave_types: Vec<WaveType>,
+    ranges: [(f64, f64); Param::ALL.len()],
+}
+
+#[allow(clippy::new_without_default)]
+impl ParamRanges {
+    /// Constructs new ParamRanges with every field fixed to its `Sample::new` value
+    pub fn new() -> ParamRanges {
+        ParamRanges::from_sample(&Sample::new())
+    }
+    /// Constructs new ParamRanges with every field fixed to its value in `sample`
+    pub fn from_sample(sample: &Sample) -> ParamRanges {
+        let mut ranges = [(0.0, 0.0); Param::ALL.len()];
+        for &param in Param::ALL.iter() {
+            let value = sample.param(param);
+            ranges[param as usize] = (value, value);
+        }
+        ParamRanges {
+            wave_types: vec![sample.wave_type],
+            ranges,
+        }
+    }
+    /// Constructs new ParamRanges covering every valid value of every `Param::RANDOMIZED` field
+    pub fn full() -> ParamRanges {
+        let mut ranges = ParamRanges::new();
+        ranges.wave_types = vec![
+            WaveType::Square,
+            WaveType::Sawtooth,
+            WaveType::Sine,
+            WaveType::Noise,
+            WaveType::Triangle,
+        ];
+        for &param in Param::RANDOMIZED.iter() {
+            let (min, max) = param.range();
+            ranges.set(param, min, max);
+        }
+        ranges
+    }
+    /// Returns the `(min, max)` range of `param`
+    pub fn get(&self, param: Param) -> (f64, f64) {
+        self.ranges[param as usize]
+    }
+    /// Sets the range of `param`. Values outside the valid range of the field are clamped when
+    /// randomizing. Only `Param::RANDOMIZED` parameters are randomized, the others keep their
+    /// `Sample::new` values.
+    pub fn set(&mut self, param: Param, min: f64, max: f64) {
+        assert!(min <= max, "min must not be greater than max");
+        self.ranges[param as usize] = (min, max);
+    }
+}
+
+/// A set of Params, such as the fields locked by `Sample::reroll`
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{FieldMask, Param};
+/// let mut locked = FieldMask::new().with(Param::WaveType);
+/// locked.insert(Param::EnvDecay);
+/// assert!(locked.contains(Param::EnvDecay));
+/// assert_eq!(locked.iter().collect::<Vec<_>>(), [Param::WaveType, Param::EnvDecay]);
+/// ```
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Default)]
+pub struct FieldMask {
+    bits: u64,
+}
+
+impl FieldMask {
+    /// Mask of every Param
+    pub const ALL: FieldMask = FieldMask {
+        bits: u64::MAX >> (64 - Param::ALL.len()),
+    };
+
+    /// Constructs an empty FieldMask
+    pub const fn new() -> FieldMask {
+        FieldMask { bits: 0 }
+    }
+    /// Returns the mask with `param` added
+    pub const fn with(self, param: Param) -> FieldMask {
+        FieldMask {
+            bits: self.bits | (1 << param as u64),
+        }
+    }
+    /// Adds `param`
+    pub fn insert(&mut self, param: Param) {
+        *self = self.with(param);
+    }
+    /// Removes `param`
+    pub fn remove(&mut self, param: Param) {
+        self.bits &= !(1 << param as u64);
+    }
+    /// Returns `true` if the mask has `param`
+    pub const fn contains(self, param: Param) -> bool {
+        self.bits & (1 << param as u64) != 0
+    }
+    /// Returns the Params of the mask in the order of `Param::ALL`
+    pub fn iter(self) -> impl Iterator<Item = Param> {
+        Param::ALL
+            .iter()
+            .copied()
+            .filter(move |&param| self.contains(param))
+    }
+    /// Returns the number of Params in the mask
+    pub fn len(self) -> usize {
+        self.bits.count_ones() as usize
+    }
+    /// Returns `true` if the mask has no Params
+    pub fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+}
+
+impl std::iter::FromIterator<Param> for FieldMask {
+    fn from_iter<I: IntoIterator<Item = Param>>(iter: I) -> FieldMask {
+        iter.into_iter()
+            .fold(FieldMask::new(), |mask, param| mask.with(param))
+    }
+}
+
+/// A field changed by `Sample::mutate_with_rng`
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct FieldChange {
+    /// The changed field
+    pub param: Param,
+    /// Value before the change
+    pub old: f64,
+    /// Value after the change
+    pub new: f64,
+}
+
+impl FieldChange {
+    /// Returns how much the value changed
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// The fields changed by `Sample::mutate_with_rng`, in field order
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct MutationReport {
+    /// Changed fields. Fields that were left as is are not listed.
+    pub changes: Vec<FieldChange>,
+}
+
+impl MutationReport {
+    /// Restores the changed fields of `sample` to their values before the mutation
+    pub fn undo(&self, sample: &mut Sample) {
+        for change in self.changes.iter().rev() {
+            sample.set_param(change.param, change.old);
+        }
+    }
+}
+
+#[cfg(feature = "rand")]
+impl Sample {
+    /// Constructs a new random Sample with each field drawn uniformly from `ranges`. Fields with
+    /// a single possible value, and a single wave type, consume no random numbers, so adding
+    /// fields to Sample does not change the results for existing ranges.
+    pub fn random_in<R: Rng + ?Sized>(ranges: &ParamRanges, rng: &mut R) -> Sample {
+        assert!(
+            !ranges.wave_types.is_empty(),
+            "wave_types must not be empty"
+        );
+
+        let mut s = Sample::new();
+        s.wave_type = match ranges.wave_types.len() {
+            1 => ranges.wave_types[0],
+            _ => rand_element(rng, &ranges.wave_types),
+        };
+
+        for &param in Param::RANDOMIZED.iter() {
+            let (min, max) = ranges.get(param);
+            let (valid_min, valid_max) = param.range();
+            #[allow(clippy::float_cmp)]
+            let value = if min == max {
+                min
+            } else {
+                rand_f64(rng, min, max)
+            };
+            s.set_param(param, value.clamp(valid_min, valid_max));
+        }
+
+        s
+    }
+}
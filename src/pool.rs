@@ -0,0 +1,174 @@
+//! Fixed capacity voice management with handles that expire with their voice
+
+use crate::{Generator, Sample};
+
+/// Samples generated at a time by a voice of a GeneratorPool
+const CHUNK: usize = 256;
+
+/// Refers to a voice spawned in a GeneratorPool
+///
+/// A Handle stays valid while its voice plays. Once the voice finishes or is stopped, its slot
+/// may be reused by a later voice, but the old Handle won't refer to it, as the slot's
+/// generation has moved on.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub struct Handle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot {
+    generator: Option<Generator>,
+    generation: u32,
+    started: u64,
+}
+
+/// A fixed number of voices mixed together, for games triggering many short sounds
+///
+/// All slots are allocated by `new`, so spawning and mixing don't allocate. When every slot is
+/// playing, `spawn` replaces the voice that started first.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{GeneratorPool, Sample};
+/// let mut pool = GeneratorPool::new(16);
+/// let coin = pool.spawn(Sample::pickup(Some(1)));
+/// assert!(pool.is_playing(coin));
+///
+/// let mut frame = [0.0; 735];
+/// while pool.is_playing(coin) {
+///     frame.iter_mut().for_each(|v| *v = 0.0);
+///     pool.mix_into(&mut frame);
+/// }
+/// assert_eq!(pool.active_count(), 0);
+/// ```
+pub struct GeneratorPool {
+    slots: Vec<Slot>,
+    started: u64,
+}
+
+impl GeneratorPool {
+    /// Constructs a GeneratorPool playing at most `capacity` voices at once. `capacity` must be
+    /// greater than `0`.
+    pub fn new(capacity: usize) -> GeneratorPool {
+        assert!(capacity > 0, "capacity must be greater than zero");
+        assert!(
+            capacity <= u32::MAX as usize,
+            "capacity must fit in 32 bits"
+        );
+        GeneratorPool {
+            slots: (0..capacity)
+                .map(|_| Slot {
+                    generator: None,
+                    generation: 0,
+                    started: 0,
+                })
+                .collect(),
+            started: 0,
+        }
+    }
+    /// Starts playing `sample`, see `spawn_generator`
+    pub fn spawn(&mut self, sample: Sample) -> Handle {
+        self.spawn_generator(Generator::new(sample))
+    }
+    /// Starts playing `generator` from its current position in a free slot, or in place of the
+    /// voice that started first if all slots are playing, and returns its Handle
+    pub fn spawn_generator(&mut self, generator: Generator) -> Handle {
+        let index = match self.slots.iter().position(|slot| slot.generator.is_none()) {
+            Some(index) => index,
+            None => {
+                let (index, _) = self
+                    .slots
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, slot)| slot.started)
+                    .expect("the pool has at least one slot");
+                self.free(index);
+                index
+            }
+        };
+
+        self.started += 1;
+        let slot = &mut self.slots[index];
+        slot.generator = Some(generator);
+        slot.started = self.started;
+        Handle {
+            index: index as u32,
+            generation: slot.generation,
+        }
+    }
+    /// Returns `true` if the voice of `handle` is still playing
+    pub fn is_playing(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+    /// Returns the Generator of the voice of `handle`, or `None` if it has ended
+    pub fn get(&self, handle: Handle) -> Option<&Generator> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.generator.as_ref())
+    }
+    /// Returns the Generator of the voice of `handle`, for example to change its volume, or
+    /// `None` if it has ended
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut Generator> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.generator.as_mut())
+    }
+    /// Stops the voice of `handle`. Returns `false` if it had already ended.
+    pub fn stop(&mut self, handle: Handle) -> bool {
+        if self.is_playing(handle) {
+            self.free(handle.index as usize);
+            true
+        } else {
+            false
+        }
+    }
+    /// Stops all voices
+    pub fn stop_all(&mut self) {
+        for index in 0..self.slots.len() {
+            if self.slots[index].generator.is_some() {
+                self.free(index);
+            }
+        }
+    }
+    /// Returns the number of playing voices
+    pub fn active_count(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.generator.is_some())
+            .count()
+    }
+    /// Returns the largest number of voices playing at once
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+    /// Adds the next samples of all playing voices to `buffer`, then ends the voices whose
+    /// envelope has ended
+    pub fn mix_into(&mut self, buffer: &mut [f32]) {
+        let mut scratch = [0.0; CHUNK];
+        for index in 0..self.slots.len() {
+            let generator = match self.slots[index].generator {
+                Some(ref mut generator) => generator,
+                None => continue,
+            };
+            for chunk in buffer.chunks_mut(CHUNK) {
+                let scratch = &mut scratch[..chunk.len()];
+                generator.generate(scratch);
+                for (v, &voice) in chunk.iter_mut().zip(scratch.iter()) {
+                    *v += voice;
+                }
+            }
+            if generator.is_finished() {
+                self.free(index);
+            }
+        }
+    }
+    /// Empties slot `index` and invalidates its handles
+    fn free(&mut self, index: usize) {
+        let slot = &mut self.slots[index];
+        slot.generator = None;
+        slot.generation = slot.generation.wrapping_add(1);
+    }
+}
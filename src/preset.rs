@@ -0,0 +1,792 @@
+//! Declarative random preset definitions
+//!
+//! A `PresetDef` describes a random category, like the built-in `Sample::laser`, as a list of
+//! `Step`s that draw field values, pick wave types and branch randomly. The built-in categories
+//! are themselves defined this way, see `PresetDef::builtin`.
+//!
+//! With the `json` feature, definitions can be loaded from JSON at runtime:
+//!
+//! ``` json
+//! {
+//!   "name": "soft_blip",
+//!   "steps": [
+//!     { "op": "wave", "choices": ["sine", "triangle"] },
+//!     { "op": "set", "param": "base_freq", "value": [0.2, 0.4] },
+//!     { "op": "set", "param": "env_attack", "value": 0.0 },
+//!     { "op": "chance", "odds": [1, 2], "then": [
+//!       { "op": "set", "param": "hpf_freq", "value": 0.1 }
+//!     ] }
+//!   ]
+//! }
+//! ```
+
+#[cfg(feature = "rand")]
+use rand::Rng;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "json")]
+use crate::Error;
+#[cfg(feature = "rand")]
+use crate::{rand_bool, rand_element, rand_f32, rand_f64, seed_or_random, FieldMask, Prng, Sample};
+use crate::{Param, WaveType};
+
+/// A field value drawn by a `Step`
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(untagged))]
+pub enum Value {
+    /// A constant value. Serialized as a plain number.
+    Const(f64),
+    /// A uniformly distributed random value between the two bounds. Serialized as a
+    /// `[from, until]` pair.
+    Uniform(f64, f64),
+}
+
+/// A single operation of a `PresetDef`
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(tag = "op", rename_all = "snake_case")
+)]
+pub enum Step {
+    /// Sets `param` to `value`
+    Set {
+        /// Field to set
+        param: Param,
+        /// Value to set
+        value: Value,
+    },
+    /// Adds `value` to `param`
+    Add {
+        /// Field to change
+        param: Param,
+        /// Value to add
+        value: Value,
+    },
+    /// Copies the value of `from` to `param`
+    Copy {
+        /// Field to set
+        param: Param,
+        /// Field to copy
+        from: Param,
+    },
+    /// Squares the value of `param`
+    Square {
+        /// Field to change
+        param: Param,
+    },
+    /// Limits `param` between `min` and `max`. `min` must not be greater than `max`.
+    Clamp {
+        /// Field to change
+        param: Param,
+        /// Lower limit
+        min: f64,
+        /// Upper limit
+        max: f64,
+    },
+    /// Picks the wave type randomly from `choices`. No random number is drawn when there is
+    /// only one choice.
+    Wave {
+        /// Wave types to choose from with equal probability
+        choices: Vec<WaveType>,
+    },
+    /// Runs `then` with `odds.0`:`odds.1` odds, `otherwise` otherwise
+    Chance {
+        /// Odds of running `then` versus `otherwise`
+        odds: (u32, u32),
+        /// Steps to run on success
+        then: Vec<Step>,
+        /// Steps to run on failure
+        #[cfg_attr(feature = "serde", serde(default))]
+        otherwise: Vec<Step>,
+    },
+    /// Runs `then` if the wave type is `wave_type`, `otherwise` otherwise
+    IfWave {
+        /// Wave type to compare to
+        wave_type: WaveType,
+        /// Steps to run if the wave type matches
+        then: Vec<Step>,
+        /// Steps to run if the wave type doesn't match
+        #[cfg_attr(feature = "serde", serde(default))]
+        otherwise: Vec<Step>,
+    },
+}
+
+/// A named random Sample category
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PresetDef {
+    /// Category name
+    pub name: String,
+    /// Steps applied in order to a `Sample::new` to generate a Sample
+    pub steps: Vec<Step>,
+}
+
+/// Built-in random Sample categories
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Category {
+    /// "Coin" or "item pickup", see `Sample::pickup`
+    Pickup,
+    /// "Shoot" or "laser", see `Sample::laser`
+    Laser,
+    /// "Explosion", see `Sample::explosion`
+    Explosion,
+    /// "Powerup", see `Sample::powerup`
+    Powerup,
+    /// "Hit" or "damage", see `Sample::hit`
+    Hit,
+    /// "Jump", see `Sample::jump`
+    Jump,
+    /// "Blip" or "menu navigation", see `Sample::blip`
+    Blip,
+    /// "Alien" or "bird", see `Sample::alien`
+    Alien,
+}
+
+impl Category {
+    /// All built-in categories
+    pub const ALL: [Category; 8] = [
+        Category::Pickup,
+        Category::Laser,
+        Category::Explosion,
+        Category::Powerup,
+        Category::Hit,
+        Category::Jump,
+        Category::Blip,
+        Category::Alien,
+    ];
+
+    /// Returns the name of the category as used by `PresetDef::builtin`
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Pickup => "pickup",
+            Category::Laser => "laser",
+            Category::Explosion => "explosion",
+            Category::Powerup => "powerup",
+            Category::Hit => "hit",
+            Category::Jump => "jump",
+            Category::Blip => "blip",
+            Category::Alien => "alien",
+        }
+    }
+
+    /// Returns the definition of the category
+    pub fn def(self) -> PresetDef {
+        PresetDef::builtin(self.name()).expect("built-in preset")
+    }
+}
+
+impl PresetDef {
+    /// Returns the definition of the built-in category `name`, or `None` if there is no such
+    /// category. See `Category::name`.
+    pub fn builtin(name: &str) -> Option<PresetDef> {
+        use self::Value::*;
+        use crate::Param::*;
+        use crate::WaveType::*;
+
+        fn set(param: Param, from: f64, until: f64) -> Step {
+            Step::Set {
+                param,
+                value: Uniform(from, until),
+            }
+        }
+        fn fix(param: Param, value: f64) -> Step {
+            Step::Set {
+                param,
+                value: Const(value),
+            }
+        }
+        fn chance(odds: (u32, u32), then: Vec<Step>, otherwise: Vec<Step>) -> Step {
+            Step::Chance {
+                odds,
+                then,
+                otherwise,
+            }
+        }
+        fn wave(choices: Vec<crate::WaveType>) -> Step {
+            Step::Wave { choices }
+        }
+
+        let steps = match name {
+            "pickup" => vec![
+                set(BaseFreq, 0.4, 0.9),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.0, 0.1),
+                set(EnvDecay, 0.1, 0.5),
+                set(EnvPunch, 0.3, 0.6),
+                chance(
+                    (1, 1),
+                    vec![set(ArpSpeed, 0.5, 0.7), set(ArpMod, 0.2, 0.6)],
+                    vec![],
+                ),
+            ],
+            "laser" => vec![
+                wave(vec![Square, Square, Sine, Sine, Triangle]),
+                chance(
+                    (1, 2),
+                    vec![
+                        set(BaseFreq, 0.3, 0.9),
+                        set(FreqLimit, 0.0, 0.1),
+                        set(FreqRamp, -0.35, -0.65),
+                    ],
+                    vec![
+                        set(BaseFreq, 0.5, 1.0),
+                        Step::Copy {
+                            param: FreqLimit,
+                            from: BaseFreq,
+                        },
+                        Step::Add {
+                            param: FreqLimit,
+                            value: Uniform(-0.2, -0.8),
+                        },
+                        Step::Clamp {
+                            param: FreqLimit,
+                            min: 0.2,
+                            max: 1.0,
+                        },
+                        set(FreqRamp, -0.15, -0.35),
+                    ],
+                ),
+                chance(
+                    (1, 1),
+                    vec![set(Duty, 0.0, 0.5), set(DutyRamp, 0.0, 0.2)],
+                    vec![set(Duty, 0.4, 0.9), set(DutyRamp, 0.0, -0.7)],
+                ),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.1, 0.3),
+                set(EnvDecay, 0.0, 0.4),
+                chance((1, 1), vec![set(EnvPunch, 0.0, 0.3)], vec![]),
+                chance(
+                    (1, 2),
+                    vec![set(PhaOffset, 0.0, 0.2), set(PhaRamp, 0.0, -0.2)],
+                    vec![],
+                ),
+                chance((1, 1), vec![set(HpfFreq, 0.0, 0.3)], vec![]),
+            ],
+            "explosion" => vec![
+                wave(vec![Noise]),
+                chance(
+                    (1, 1),
+                    vec![set(BaseFreq, 0.1, 0.5), set(FreqRamp, -0.1, 0.3)],
+                    vec![set(BaseFreq, 0.2, 0.9), set(FreqRamp, -0.2, -0.4)],
+                ),
+                Step::Square { param: BaseFreq },
+                chance((1, 4), vec![fix(FreqRamp, 0.0)], vec![]),
+                chance((1, 2), vec![set(RepeatSpeed, 0.3, 0.8)], vec![]),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.1, 0.4),
+                set(EnvDecay, 0.0, 0.5),
+                chance(
+                    (1, 1),
+                    vec![set(PhaOffset, -0.3, 0.6), set(PhaRamp, -0.3, 0.0)],
+                    vec![],
+                ),
+                set(EnvPunch, 0.2, 0.8),
+                chance(
+                    (1, 1),
+                    vec![set(VibStrength, 0.0, 0.7), set(VibSpeed, 0.0, 0.6)],
+                    vec![],
+                ),
+                chance(
+                    (1, 2),
+                    vec![set(ArpSpeed, 0.6, 0.9), set(ArpMod, -0.8, 0.8)],
+                    vec![],
+                ),
+            ],
+            "powerup" => vec![
+                chance((1, 1), vec![wave(vec![Sine])], vec![set(Duty, 0.0, 0.6)]),
+                set(BaseFreq, 0.2, 0.5),
+                chance(
+                    (1, 1),
+                    vec![set(FreqRamp, 0.1, 0.5), set(RepeatSpeed, 0.4, 0.8)],
+                    vec![
+                        set(FreqRamp, 0.05, 0.25),
+                        chance(
+                            (1, 1),
+                            vec![set(VibStrength, 0.0, 0.7), set(VibSpeed, 0.0, 0.6)],
+                            vec![],
+                        ),
+                    ],
+                ),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.0, 0.4),
+                set(EnvDecay, 0.1, 0.5),
+            ],
+            "hit" => vec![
+                wave(vec![Square, Sine, Noise]),
+                Step::IfWave {
+                    wave_type: Square,
+                    then: vec![set(Duty, 0.0, 0.6)],
+                    otherwise: vec![],
+                },
+                set(BaseFreq, 0.2, 0.8),
+                set(FreqRamp, -0.3, -0.7),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.0, 0.1),
+                set(EnvDecay, 0.1, 0.3),
+                chance((1, 1), vec![set(HpfFreq, 0.0, 0.3)], vec![]),
+            ],
+            "jump" => vec![
+                wave(vec![Square]),
+                set(Duty, 0.0, 0.6),
+                set(BaseFreq, 0.3, 0.6),
+                set(FreqRamp, 0.1, 0.3),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.1, 0.4),
+                set(EnvDecay, 0.1, 0.3),
+                chance((1, 1), vec![set(HpfFreq, 0.0, 0.3)], vec![]),
+                chance((1, 1), vec![set(LpfFreq, 0.4, 1.0)], vec![]),
+            ],
+            "blip" => vec![
+                wave(vec![Square, Sine]),
+                Step::IfWave {
+                    wave_type: Square,
+                    then: vec![set(Duty, 0.0, 0.6)],
+                    otherwise: vec![],
+                },
+                set(BaseFreq, 0.2, 0.6),
+                fix(EnvAttack, 0.0),
+                set(EnvSustain, 0.1, 0.2),
+                set(EnvDecay, 0.0, 0.2),
+                fix(HpfFreq, 0.1),
+            ],
+            "alien" => vec![
+                wave(vec![Sine, Sine, Square, Triangle]),
+                Step::IfWave {
+                    wave_type: Square,
+                    then: vec![set(Duty, 0.2, 0.6)],
+                    otherwise: vec![],
+                },
+                set(BaseFreq, 0.4, 0.8),
+                set(FreqRamp, -0.2, 0.3),
+                chance((1, 1), vec![set(FreqDramp, -0.2, 0.2)], vec![]),
+                set(VibStrength, 0.3, 0.8),
+                set(VibSpeed, 0.4, 0.9),
+                set(EnvAttack, 0.0, 0.1),
+                set(EnvSustain, 0.1, 0.3),
+                set(EnvDecay, 0.1, 0.4),
+                chance((1, 1), vec![set(RepeatSpeed, 0.3, 0.7)], vec![]),
+                chance(
+                    (1, 2),
+                    vec![set(ArpSpeed, 0.4, 0.8), set(ArpMod, -0.5, 0.5)],
+                    vec![],
+                ),
+            ],
+            _ => return None,
+        };
+
+        Some(PresetDef {
+            name: name.to_string(),
+            steps,
+        })
+    }
+
+    /// Parses a definition from JSON. Fails if a `Step::Chance` has zero odds on both sides or
+    /// a `Step::Clamp` has `min` greater than `max`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::PresetDef;
+    /// let json = r#"{
+    ///     "name": "never",
+    ///     "steps": [ { "op": "chance", "odds": [0, 0], "then": [] } ]
+    /// }"#;
+    /// assert!(PresetDef::from_json(json).is_err());
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<PresetDef, Error> {
+        let def: PresetDef = serde_json::from_str(json)?;
+        def.validate()?;
+        Ok(def)
+    }
+
+    /// Loads a definition from JSON data read from `reader`, checked like `from_json`
+    #[cfg(feature = "json")]
+    pub fn load<R: std::io::Read>(reader: R) -> Result<PresetDef, Error> {
+        let def: PresetDef = serde_json::from_reader(reader)?;
+        def.validate()?;
+        Ok(def)
+    }
+
+    #[cfg(feature = "json")]
+    fn validate(&self) -> Result<(), Error> {
+        check(&self.steps).map_err(|message| Error::Parse {
+            format: "JSON",
+            message: format!("preset {:?}: {}", self.name, message),
+        })
+    }
+
+    /// Generates a new random Sample in this category. All fields are clamped to their valid
+    /// ranges afterwards.
+    #[cfg(feature = "rand")]
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Sample {
+        self.generate_with(Sample::new(), rng, &mut Tracer::new(&[]))
+    }
+    /// Generates a Sample like `generate` with a Prng seeded with optional random seed (random
+    /// if `None`), and returns it with the trace of its random decisions
+    ///
+    /// `Category::def` of a built-in category generates the same Sample as its constructor for
+    /// the same seed, such as `Sample::laser`.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust
+    /// use sfxr::preset::Choice;
+    /// use sfxr::{Category, Sample};
+    /// let def = Category::Powerup.def();
+    /// let (sample, mut trace) = def.generate_traced(Some(5));
+    /// assert_eq!(sample.base_freq, Sample::powerup(Some(5)).base_freq);
+    ///
+    /// // Force the first branch of the preset to the other way and keep everything else
+    /// let branch = trace.decisions.iter().find_map(|decision| match decision.choice {
+    ///     Choice::Branch(taken) => Some((decision.step, taken)),
+    ///     _ => None,
+    /// });
+    /// let (step, taken) = branch.unwrap();
+    /// trace.force(step, Choice::Branch(!taken));
+    /// let (tweaked, _) = def.regenerate(&trace);
+    /// ```
+    #[cfg(feature = "rand")]
+    pub fn generate_traced(&self, seed: Option<u64>) -> (Sample, GenerationTrace) {
+        let trace = GenerationTrace {
+            seed: seed_or_random(seed),
+            decisions: Vec::new(),
+        };
+        self.regenerate(&trace)
+    }
+    /// Generates a Sample again from `trace.seed`, using the recorded choices of
+    /// `trace.decisions` for the steps they belong to. Returns the Sample with its own trace.
+    ///
+    /// Random numbers are drawn for forced steps as well, so steps without a recorded choice
+    /// draw the same values as before, unless a forced branch runs other steps before them.
+    #[cfg(feature = "rand")]
+    pub fn regenerate(&self, trace: &GenerationTrace) -> (Sample, GenerationTrace) {
+        let mut tracer = Tracer::new(&trace.decisions);
+        let sample = self.generate_with(Sample::new(), &mut Prng::new(trace.seed), &mut tracer);
+        let trace = GenerationTrace {
+            seed: trace.seed,
+            decisions: tracer.decisions,
+        };
+        (sample, trace)
+    }
+    /// Returns the step numbered `index` in the order decisions are numbered in, depth first
+    /// with `then` before `otherwise`, see `Decision::step`
+    pub fn step_at(&self, index: usize) -> Option<&Step> {
+        find_step(&self.steps, index)
+    }
+    /// Regenerates the fields of `sample` except the `locked` ones, like `generate`. Locked
+    /// fields keep their values, and steps setting them are skipped without changing the
+    /// random numbers drawn for the others. Fields without a Param, such as `slide_mode`,
+    /// are kept as well.
+    #[cfg(feature = "rand")]
+    pub fn reroll<R: Rng + ?Sized>(&self, sample: &mut Sample, locked: FieldMask, rng: &mut R) {
+        let defaults = Sample::new();
+        let mut base = sample.clone();
+        for &param in Param::ALL.iter().filter(|&&param| !locked.contains(param)) {
+            base.set_param(param, defaults.param(param));
+        }
+        let mut tracer = Tracer::new(&[]);
+        tracer.locked = locked;
+        *sample = self.generate_with(base, rng, &mut tracer);
+    }
+    #[cfg(feature = "rand")]
+    fn generate_with<R: Rng + ?Sized>(
+        &self,
+        mut s: Sample,
+        rng: &mut R,
+        tracer: &mut Tracer<'_>,
+    ) -> Sample {
+        run(&self.steps, &mut s, rng, tracer);
+
+        for &param in Param::RANDOMIZED.iter() {
+            let (min, max) = param.range();
+            let value = s.param(param).clamp(min, max);
+            tracer.set(&mut s, param, value);
+        }
+
+        s
+    }
+}
+
+/// A choice made by a step of a `PresetDef` while generating a Sample
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Choice {
+    /// The value of a `Step::Set` or `Step::Add`, before clamping to the range of the field
+    Value(f64),
+    /// The wave type picked by a `Step::Wave`
+    Wave(WaveType),
+    /// Whether a `Step::Chance` or `Step::IfWave` ran `then` rather than `otherwise`
+    Branch(bool),
+}
+
+/// A choice with the step that made it
+#[derive(PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Decision {
+    /// Index of the step, see `PresetDef::step_at`
+    pub step: usize,
+    /// What the step chose
+    pub choice: Choice,
+}
+
+/// The seed and random decisions of a Sample generated by a `PresetDef`, returned by
+/// `PresetDef::generate_traced`
+///
+/// Changing or forcing a decision and passing the trace to `PresetDef::regenerate` generates
+/// the Sample again with that decision, for guiding random design one choice at a time.
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GenerationTrace {
+    /// Seed of the Prng the Sample was generated with
+    pub seed: u64,
+    /// Every random value drawn, wave type picked and branch taken, in order
+    pub decisions: Vec<Decision>,
+}
+
+impl GenerationTrace {
+    /// Returns the recorded choice of step `step`
+    pub fn choice(&self, step: usize) -> Option<Choice> {
+        self.decisions
+            .iter()
+            .find(|decision| decision.step == step)
+            .map(|decision| decision.choice)
+    }
+    /// Makes step `step` choose `choice` when regenerating
+    pub fn force(&mut self, step: usize, choice: Choice) {
+        match self
+            .decisions
+            .iter_mut()
+            .find(|decision| decision.step == step)
+        {
+            Some(decision) => decision.choice = choice,
+            None => self.decisions.push(Decision { step, choice }),
+        }
+    }
+    /// Lets step `step` choose randomly again when regenerating
+    pub fn release(&mut self, step: usize) {
+        self.decisions.retain(|decision| decision.step != step);
+    }
+}
+
+/// Numbers the steps as they run, applying forced choices and locked fields, and records the
+/// choices made
+#[cfg(feature = "rand")]
+struct Tracer<'a> {
+    next: usize,
+    forced: &'a [Decision],
+    decisions: Vec<Decision>,
+    locked: FieldMask,
+}
+
+#[cfg(feature = "rand")]
+impl<'a> Tracer<'a> {
+    fn new(forced: &'a [Decision]) -> Tracer<'a> {
+        Tracer {
+            next: 0,
+            forced,
+            decisions: Vec::new(),
+            locked: FieldMask::new(),
+        }
+    }
+    /// Sets `param` of `s` to `value`, unless it is locked
+    fn set(&self, s: &mut Sample, param: Param, value: f64) {
+        if !self.locked.contains(param) {
+            s.set_param(param, value);
+        }
+    }
+    /// Returns the forced choice of `step`, if it is of the same kind as `choice`, or else
+    /// `choice`. The returned choice is recorded if it was forced or `record` is `true`.
+    fn choose(&mut self, step: usize, choice: Choice, record: bool) -> Choice {
+        let forced = self.forced.iter().find(|decision| {
+            decision.step == step
+                && std::mem::discriminant(&decision.choice) == std::mem::discriminant(&choice)
+        });
+        let choice = forced.map_or(choice, |decision| decision.choice);
+        if forced.is_some() || record {
+            self.decisions.push(Decision { step, choice });
+        }
+        choice
+    }
+    /// Returns the forced value of `step`, or else `drawn`, the draw of `value`
+    fn value(&mut self, step: usize, value: Value, drawn: f64) -> f64 {
+        let random = matches!(value, Value::Uniform(..));
+        match self.choose(step, Choice::Value(drawn), random) {
+            Choice::Value(value) => value,
+            _ => drawn,
+        }
+    }
+}
+
+/// Checks that `steps` and the steps nested in them can run
+#[cfg(feature = "json")]
+fn check(steps: &[Step]) -> Result<(), String> {
+    for step in steps.iter() {
+        match step {
+            Step::Clamp { param, min, max } if min.is_nan() || max.is_nan() || min > max => {
+                return Err(format!(
+                    "clamp of {:?} needs min <= max, got {} and {}",
+                    param, min, max
+                ));
+            }
+            Step::Chance { odds: (0, 0), .. } => {
+                return Err("chance odds must not both be zero".to_string());
+            }
+            Step::Chance {
+                then, otherwise, ..
+            }
+            | Step::IfWave {
+                then, otherwise, ..
+            } => {
+                check(then)?;
+                check(otherwise)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Number of steps in `steps`, including nested ones
+fn count(steps: &[Step]) -> usize {
+    steps
+        .iter()
+        .map(|step| match step {
+            Step::Chance {
+                then, otherwise, ..
+            }
+            | Step::IfWave {
+                then, otherwise, ..
+            } => 1 + count(then) + count(otherwise),
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Returns the step numbered `index` in `steps`, see `PresetDef::step_at`
+fn find_step(steps: &[Step], mut index: usize) -> Option<&Step> {
+    for step in steps.iter() {
+        if index == 0 {
+            return Some(step);
+        }
+        index -= 1;
+        if let Step::Chance {
+            then, otherwise, ..
+        }
+        | Step::IfWave {
+            then, otherwise, ..
+        } = step
+        {
+            let nested = count(then) + count(otherwise);
+            if index < nested {
+                let then_len = count(then);
+                return if index < then_len {
+                    find_step(then, index)
+                } else {
+                    find_step(otherwise, index - then_len)
+                };
+            }
+            index -= nested;
+        }
+    }
+    None
+}
+
+#[cfg(feature = "rand")]
+fn run<R: Rng + ?Sized>(steps: &[Step], s: &mut Sample, rng: &mut R, tracer: &mut Tracer<'_>) {
+    for step in steps.iter() {
+        let index = tracer.next;
+        tracer.next += 1;
+        match step {
+            Step::Set { param, value } => {
+                let value = tracer.value(index, *value, draw(*param, *value, rng));
+                tracer.set(s, *param, value);
+            }
+            Step::Add { param, value } => {
+                let value = tracer.value(index, *value, draw(*param, *value, rng));
+                tracer.set(s, *param, s.param(*param) + value);
+            }
+            Step::Copy { param, from } => tracer.set(s, *param, s.param(*from)),
+            Step::Square { param } => tracer.set(s, *param, s.param(*param).powi(2)),
+            Step::Clamp { param, min, max } => {
+                tracer.set(s, *param, s.param(*param).max(*min).min(*max))
+            }
+            Step::Wave { choices } => {
+                let wave_type = match choices.len() {
+                    0 => s.wave_type,
+                    1 => choices[0],
+                    _ => rand_element(rng, choices),
+                };
+                if let Choice::Wave(wave_type) =
+                    tracer.choose(index, Choice::Wave(wave_type), choices.len() > 1)
+                {
+                    tracer.set(s, Param::WaveType, f64::from(wave_type.as_index()));
+                }
+            }
+            Step::Chance {
+                odds,
+                then,
+                otherwise,
+            } => {
+                let taken = rand_bool(rng, odds.0, odds.1);
+                branch(index, taken, then, otherwise, s, rng, tracer);
+            }
+            Step::IfWave {
+                wave_type,
+                then,
+                otherwise,
+            } => {
+                let taken = s.wave_type == *wave_type;
+                branch(index, taken, then, otherwise, s, rng, tracer);
+            }
+        }
+    }
+}
+
+/// Runs `then` or `otherwise` of the step numbered `index`, by `taken` unless forced
+#[cfg(feature = "rand")]
+fn branch<R: Rng + ?Sized>(
+    index: usize,
+    taken: bool,
+    then: &[Step],
+    otherwise: &[Step],
+    s: &mut Sample,
+    rng: &mut R,
+    tracer: &mut Tracer<'_>,
+) {
+    let taken = match tracer.choose(index, Choice::Branch(taken), true) {
+        Choice::Branch(taken) => taken,
+        _ => taken,
+    };
+    let then_start = index + 1;
+    let otherwise_start = then_start + count(then);
+    if taken {
+        tracer.next = then_start;
+        run(then, s, rng, tracer);
+    } else {
+        tracer.next = otherwise_start;
+        run(otherwise, s, rng, tracer);
+    }
+    tracer.next = otherwise_start + count(otherwise);
+}
+
+/// Draws `value` with the precision of the field `param`
+#[cfg(feature = "rand")]
+fn draw<R: Rng + ?Sized>(param: Param, value: Value, rng: &mut R) -> f64 {
+    match value {
+        Value::Const(v) => v,
+        Value::Uniform(from, until) if param.is_f64() => rand_f64(rng, from, until),
+        Value::Uniform(from, until) => f64::from(rand_f32(rng, from as f32, until as f32)),
+    }
+}
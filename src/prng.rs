@@ -0,0 +1,114 @@
+#[cfg(feature = "rand")]
+use rand::{RngCore, SeedableRng};
+
+/// The pseudo random number generator used for noise and seeded Samples
+///
+/// The algorithm is xoshiro128** by Blackman and Vigna. The 128 bit state is filled from two
+/// consecutive outputs of SplitMix64 started at the seed, each split into its low and high 32
+/// bits in that order. Seeded constructors like `Sample::laser(Some(8812))` only use this
+/// generator and draw values from it as follows, so a seed produces the same Sample in every
+/// version of this crate:
+///
+/// - `f32` in `[0, 1)`: `(next_u32() >> 8) * 2^-24`
+/// - `f64` in `[0, 1)`: `(next_u64() >> 11) * 2^-53`, where `next_u64` takes the low and then
+///   the high 32 bits from two `next_u32` calls
+/// - integer in `[0, n)`: `next_u32() % n`
+///
+/// With the `rand` feature `Prng` implements `RngCore` and `SeedableRng`, and any other `Rng`
+/// can be passed to the generic APIs like `PresetDef::generate` instead.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::Prng;
+/// let mut rng = Prng::new(8812);
+/// assert_eq!(rng.next_u32(), Prng::new(8812).next_u32());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prng {
+    state: [u32; 4],
+}
+
+impl Prng {
+    /// Constructs a new generator from `seed`
+    pub fn new(seed: u64) -> Prng {
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let a = next();
+        let b = next();
+        Prng {
+            state: [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32],
+        }
+    }
+    /// Returns the next 32 bit output
+    pub fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+        result
+    }
+    /// Returns the next 64 bit output, built from two 32 bit outputs low half first
+    pub fn next_u64(&mut self) -> u64 {
+        let low = u64::from(self.next_u32());
+        let high = u64::from(self.next_u32());
+        high << 32 | low
+    }
+    /// Returns a uniformly distributed value in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+}
+
+#[cfg(feature = "rand")]
+impl RngCore for Prng {
+    fn next_u32(&mut self) -> u32 {
+        Prng::next_u32(self)
+    }
+    fn next_u64(&mut self) -> u64 {
+        Prng::next_u64(self)
+    }
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = Prng::next_u32(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rand")]
+impl SeedableRng for Prng {
+    type Seed = [u8; 16];
+
+    /// Constructs a generator with `seed` as its state, as four little endian 32 bit words.
+    /// An all zero seed is replaced with the state of `Prng::new(0)`.
+    fn from_seed(seed: [u8; 16]) -> Prng {
+        if seed == [0; 16] {
+            return Prng::new(0);
+        }
+        let mut state = [0; 4];
+        for (word, bytes) in state.iter_mut().zip(seed.chunks(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        Prng { state }
+    }
+    fn seed_from_u64(seed: u64) -> Prng {
+        Prng::new(seed)
+    }
+}
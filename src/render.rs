@@ -0,0 +1,128 @@
+//! Rendering a sound effect a few samples at a time
+
+use crate::Generator;
+
+/// Renders a whole sound effect like `Generator::render_to_vec_with`, but in steps of a given
+/// number of samples, created by `Generator::renderer`
+///
+/// An editor can render one step per UI frame, so long sounds such as drones render without
+/// blocking, and show the progress reported to `on_progress`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Generator, Sample};
+/// let mut renderer = Generator::new(Sample::explosion(Some(1))).renderer();
+/// while !renderer.step(4_096) {
+///     // Draw a frame
+/// }
+/// let samples = renderer.into_vec();
+/// assert_eq!(samples, Generator::new(Sample::explosion(Some(1))).render_to_vec());
+/// ```
+pub struct Renderer {
+    generator: Generator,
+    threshold: f32,
+    hold_samples: usize,
+    max_samples: usize,
+    buffer: Vec<f32>,
+    quiet: usize,
+    finished: bool,
+    progress_handler: Option<Box<dyn FnMut(f32) + Send>>,
+}
+
+impl Generator {
+    /// Returns a Renderer rendering the sound effect from the beginning like `render_to_vec`
+    pub fn renderer(self) -> Renderer {
+        self.renderer_with(1.0e-3, 1_024)
+    }
+    /// Returns a Renderer rendering the sound effect from the beginning like
+    /// `render_to_vec_with`
+    pub fn renderer_with(mut self, threshold: f32, hold_samples: usize) -> Renderer {
+        // Longer than any Sample can last, in case the tail never decays
+        let max_samples = 60 * self.config().sample_rate as usize;
+
+        self.reset();
+        self.resume();
+        Renderer {
+            generator: self,
+            threshold,
+            hold_samples,
+            max_samples,
+            buffer: Vec::new(),
+            quiet: 0,
+            finished: false,
+            progress_handler: None,
+        }
+    }
+}
+
+impl Renderer {
+    /// Size of the chunks a step is generated in
+    const CHUNK: usize = 1_024;
+
+    /// Renders at most `max_samples` more samples. Returns `true` once the sound has been
+    /// rendered completely, after which steps render nothing.
+    pub fn step(&mut self, max_samples: usize) -> bool {
+        let mut chunk = [0.0; Renderer::CHUNK];
+        let mut left = max_samples;
+        while !self.finished && left > 0 {
+            let len = left
+                .min(Renderer::CHUNK)
+                .min(self.max_samples - self.buffer.len());
+            self.generator.generate(&mut chunk[..len]);
+            for &v in chunk[..len].iter() {
+                self.quiet = if v.abs() < self.threshold {
+                    self.quiet + 1
+                } else {
+                    0
+                };
+            }
+            self.buffer.extend_from_slice(&chunk[..len]);
+            left -= len;
+            self.finished = self.buffer.len() >= self.max_samples
+                || (self.generator.is_finished() && self.quiet >= self.hold_samples);
+        }
+        if self.finished {
+            let end = self
+                .buffer
+                .iter()
+                .rposition(|v| v.abs() >= self.threshold)
+                .map_or(0, |i| i + 1);
+            self.buffer.truncate(end);
+        }
+        let progress = self.progress();
+        if let Some(handler) = self.progress_handler.as_mut() {
+            handler(progress);
+        }
+        self.finished
+    }
+    /// Returns `true` once the sound has been rendered completely
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    /// Returns how far rendering has got, from `0.0` to `1.0` once finished. Follows the
+    /// envelope, so it stays just below `1.0` while the tail after the envelope is rendered.
+    pub fn progress(&self) -> f32 {
+        if self.finished {
+            1.0
+        } else {
+            self.generator.progress().min(0.99)
+        }
+    }
+    /// Sets a handler called with `progress` after each step, replacing any previous one
+    pub fn on_progress<F: FnMut(f32) + Send + 'static>(&mut self, handler: F) {
+        self.progress_handler = Some(Box::new(handler));
+    }
+    /// Returns the samples rendered so far. Trailing silence is trimmed once finished.
+    pub fn rendered(&self) -> &[f32] {
+        &self.buffer
+    }
+    /// Returns the samples rendered so far, see `rendered`
+    pub fn into_vec(self) -> Vec<f32> {
+        self.buffer
+    }
+    /// Returns the Generator rendering the sound
+    pub fn generator(&self) -> &Generator {
+        &self.generator
+    }
+}
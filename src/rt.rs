@@ -0,0 +1,132 @@
+//! Handing Generators to a real-time audio thread
+//!
+//! Locking a `Mutex` in an audio callback can block the callback while a lower priority thread
+//! holds the lock, which is heard as a glitch under load. `SharedGenerator` instead passes
+//! Generators from the game thread to the audio thread through a lock-free triple buffer. The
+//! audio thread never waits, allocates or frees memory: replaced Generators are handed back and
+//! dropped on the game thread.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::rt::SharedGenerator;
+//! use sfxr::Sample;
+//! use std::thread;
+//!
+//! let (mut sender, mut shared) = SharedGenerator::new();
+//! let audio = thread::spawn(move || {
+//!     let mut buffer = [0.0; 512];
+//!     for _ in 0..100 {
+//!         // In the audio callback
+//!         shared.generate(&mut buffer);
+//!     }
+//! });
+//! sender.play(Sample::pickup(Some(1)));
+//! audio.join().unwrap();
+//! ```
+
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use crate::{Generator, Sample};
+
+/// Set in `TripleBuffer::middle` when the middle slot holds a value the reader hasn't taken
+const FRESH: u8 = 0b100;
+/// Mask of the slot index in `TripleBuffer::middle`
+const INDEX: u8 = 0b011;
+
+/// Three slots, of which the writer and the reader each own one and exchange the third
+///
+/// The slot indices are only exchanged through the atomic `middle`, so a slot is never
+/// accessed by both threads at once.
+struct TripleBuffer {
+    slots: [UnsafeCell<Option<Generator>>; 3],
+    middle: AtomicU8,
+}
+
+// Each slot is only accessed by the thread owning its index, see `TripleBuffer`
+unsafe impl Sync for TripleBuffer {}
+
+/// Game thread side of a `SharedGenerator`, created by `SharedGenerator::new`
+pub struct GeneratorSender {
+    buffer: Arc<TripleBuffer>,
+    back: u8,
+}
+
+impl GeneratorSender {
+    /// Replaces the Generator playing on the audio thread with `generator`, or stops it if
+    /// `None`. The audio thread picks the latest one up on its next `receive`, skipping any
+    /// sent before it.
+    pub fn send(&mut self, generator: Option<Generator>) {
+        // Drops the Generator previously in the slot, possibly one the audio thread replaced
+        // SAFETY: the back slot is owned by the writer until it is published below
+        unsafe { *self.buffer.slots[usize::from(self.back)].get() = generator };
+        let previous = self.buffer.middle.swap(self.back | FRESH, Ordering::AcqRel);
+        self.back = previous & INDEX;
+    }
+    /// Plays `sample` with a new Generator on the audio thread
+    pub fn play(&mut self, sample: Sample) {
+        self.send(Some(Generator::new(sample)));
+    }
+    /// Stops playback on the audio thread
+    pub fn stop(&mut self) {
+        self.send(None);
+    }
+}
+
+/// Audio thread side of a Generator handoff without locks
+///
+/// The game thread sends Generators with the `GeneratorSender` returned by `new`, and the
+/// audio callback calls `generate`, or `receive` to process the current Generator itself.
+pub struct SharedGenerator {
+    buffer: Arc<TripleBuffer>,
+    front: u8,
+    generator: Option<Generator>,
+}
+
+impl SharedGenerator {
+    /// Constructs a handoff with no Generator playing. Returns the sender for the game thread
+    /// and the SharedGenerator for the audio thread.
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new() -> (GeneratorSender, SharedGenerator) {
+        let buffer = Arc::new(TripleBuffer {
+            slots: [
+                UnsafeCell::new(None),
+                UnsafeCell::new(None),
+                UnsafeCell::new(None),
+            ],
+            middle: AtomicU8::new(1),
+        });
+        let sender = GeneratorSender {
+            buffer: buffer.clone(),
+            back: 0,
+        };
+        let shared = SharedGenerator {
+            buffer,
+            front: 2,
+            generator: None,
+        };
+        (sender, shared)
+    }
+    /// Switches to the latest Generator sent, if any, and returns the current Generator
+    pub fn receive(&mut self) -> Option<&mut Generator> {
+        if self.buffer.middle.load(Ordering::Acquire) & FRESH != 0 {
+            let previous = self.buffer.middle.swap(self.front, Ordering::AcqRel);
+            self.front = previous & INDEX;
+            // SAFETY: the front slot is owned by the reader until it is published again
+            let slot = unsafe { &mut *self.buffer.slots[usize::from(self.front)].get() };
+            // The replaced Generator goes back to the sender to be dropped
+            std::mem::swap(&mut self.generator, slot);
+        }
+        self.generator.as_mut()
+    }
+    /// Fills `buffer` from the current Generator after `receive`, or with silence if none
+    /// is playing
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        match self.receive() {
+            Some(generator) => generator.generate(buffer),
+            None => buffer.iter_mut().for_each(|v| *v = 0.0),
+        }
+    }
+}
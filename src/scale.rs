@@ -0,0 +1,153 @@
+//! Musical keys for snapping pitches to scale notes
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{OVERSAMPLING, SAMPLE_RATE};
+
+/// Pitch class of a note, in semitones above C
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Note {
+    /// C
+    C,
+    /// C♯ or D♭
+    CSharp,
+    /// D
+    D,
+    /// D♯ or E♭
+    DSharp,
+    /// E
+    E,
+    /// F
+    F,
+    /// F♯ or G♭
+    FSharp,
+    /// G
+    G,
+    /// G♯ or A♭
+    GSharp,
+    /// A
+    A,
+    /// A♯ or B♭
+    ASharp,
+    /// B
+    B,
+}
+
+impl Note {
+    /// Returns the number of semitones above C, from `0` to `11`
+    pub fn semitones(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Notes of an octave used by a Key
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Scale {
+    /// All twelve semitones
+    Chromatic,
+    /// Major, or Ionian, scale
+    Major,
+    /// Natural minor, or Aeolian, scale
+    Minor,
+    /// Minor scale with a raised seventh
+    HarmonicMinor,
+    /// Five note major scale without the fourth and seventh
+    MajorPentatonic,
+    /// Five note minor scale without the second and sixth
+    MinorPentatonic,
+    /// Minor pentatonic scale with the flat fifth
+    Blues,
+    /// Six notes a whole tone apart
+    WholeTone,
+}
+
+impl Scale {
+    /// Returns the semitones of the scale notes above the root, in ascending order starting
+    /// with `0`
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+            Scale::WholeTone => &[0, 2, 4, 6, 8, 10],
+        }
+    }
+}
+
+/// A scale starting at a root note, such as C major or A minor
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Key, Note, Scale};
+/// let c_major = Key::new(Note::C, Scale::Major);
+/// // C#4 is between C4 and D4, and snaps to C4
+/// assert_eq!(c_major.snap(61.0), 60.0);
+/// assert_eq!(c_major.snap(61.6), 62.0);
+/// ```
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Key {
+    /// First note of the scale
+    pub root: Note,
+    /// Notes of the key relative to the root
+    pub scale: Scale,
+}
+
+impl Key {
+    /// Constructs a Key
+    pub const fn new(root: Note, scale: Scale) -> Key {
+        Key { root, scale }
+    }
+    /// Returns the note of the key closest to `midi`, a possibly fractional MIDI note number
+    /// where `69.0` is A4 at 440 Hz. Ties snap down.
+    pub fn snap(&self, midi: f64) -> f64 {
+        let relative = midi - f64::from(self.root.semitones());
+        let octave = (relative / 12.0).floor();
+        let within = relative - octave * 12.0;
+        // The root of the next octave is a candidate too
+        let nearest = self
+            .scale
+            .intervals()
+            .iter()
+            .map(|&interval| f64::from(interval))
+            .chain(Some(12.0))
+            .min_by(|a, b| (a - within).abs().total_cmp(&(b - within).abs()))
+            .unwrap_or(0.0);
+        f64::from(self.root.semitones()) + octave * 12.0 + nearest
+    }
+    /// Returns the frequency of the note of the key closest to `hz`
+    pub fn snap_hz(&self, hz: f64) -> f64 {
+        midi_to_hz(self.snap(hz_to_midi(hz)))
+    }
+    /// Returns the oscillator period in supersamples of the note closest to `period`
+    pub(crate) fn snap_period(&self, period: f64) -> f64 {
+        let rate = (SAMPLE_RATE as usize * OVERSAMPLING) as f64;
+        rate / self.snap_hz(rate / period)
+    }
+}
+
+/// Converts a frequency in Hz to a fractional MIDI note number
+pub(crate) fn hz_to_midi(hz: f64) -> f64 {
+    69.0 + 12.0 * (hz / 440.0).log2()
+}
+
+/// Converts a fractional MIDI note number to a frequency in Hz
+pub(crate) fn midi_to_hz(midi: f64) -> f64 {
+    440.0 * ((midi - 69.0) / 12.0).exp2()
+}
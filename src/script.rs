@@ -0,0 +1,115 @@
+//! Building sound effects procedurally with Rhai scripts
+//!
+//! Scripts let mods ship their sounds as small recipes instead of fixed parameters. A script
+//! runs once at load time and adds its Samples to a SoundBank with `add(name, sample)`.
+//!
+//! The following are available to scripts, besides the Rhai language itself:
+//!
+//! - `sample()` constructs a `Sample::new`.
+//! - `pickup`, `laser`, `explosion`, `powerup`, `hit`, `jump`, `blip` and `alien` construct
+//!   random Samples, with an integer seed argument or without for a random one.
+//! - Every field named by a `Param` is a property of a Sample, like `s.base_freq`. Setting a
+//!   value outside its range is an error. `wave_type` is the index of a WaveType.
+//! - `s.mutate(seed)` changes the fields of `s` randomly by a little.
+//! - `freq_from_hz(hz)` converts a frequency in Hz to a `base_freq`.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::script;
+//! let bank = script::run_sound_script(r#"
+//!     for i in 0..3 {
+//!         let s = pickup(i);
+//!         s.base_freq = freq_from_hz(440.0 + 220.0 * i);
+//!         s.env_decay = 0.2;
+//!         add("coin" + i, s);
+//!     }
+//! "#).unwrap();
+//! assert_eq!(bank.len(), 3);
+//! assert_eq!(bank.get("coin2").unwrap().env_decay, 0.2);
+//! ```
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::rc::Rc;
+
+use rhai::{Engine, EvalAltResult, FLOAT, INT};
+
+use crate::{Error, Param, Sample, SoundBank};
+
+/// Largest number of operations a script may run, so a runaway loop fails instead of hanging
+const MAX_OPERATIONS: u64 = 10_000_000;
+
+/// Random Sample constructor taking an optional seed, like `Sample::pickup`
+type Constructor = fn(Option<u64>) -> Sample;
+
+/// Random Sample constructors available to scripts
+const PRESETS: [(&str, Constructor); 8] = [
+    ("pickup", Sample::pickup),
+    ("laser", Sample::laser),
+    ("explosion", Sample::explosion),
+    ("powerup", Sample::powerup),
+    ("hit", Sample::hit),
+    ("jump", Sample::jump),
+    ("blip", Sample::blip),
+    ("alien", Sample::alien),
+];
+
+/// Runs the script file at `path` and returns the Samples it added. Fails if the file can't
+/// be read, or the script doesn't compile or fails while running.
+pub fn load_sound_script<P: AsRef<Path>>(path: P) -> Result<SoundBank, Error> {
+    let source = std::fs::read_to_string(path)?;
+    run_sound_script(&source)
+}
+
+/// Runs the script `source` and returns the Samples it added, see `load_sound_script`
+pub fn run_sound_script(source: &str) -> Result<SoundBank, Error> {
+    let bank = Rc::new(RefCell::new(SoundBank::new()));
+    let engine = engine(&bank);
+    engine.run(source).map_err(|e| Error::Parse {
+        format: "script",
+        message: e.to_string(),
+    })?;
+    drop(engine);
+
+    let bank = Rc::try_unwrap(bank).expect("the engine holding the bank has been dropped");
+    Ok(bank.into_inner())
+}
+
+/// Constructs an Engine with the sfxr functions, adding Samples to `bank`
+fn engine(bank: &Rc<RefCell<SoundBank>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+
+    engine.register_type_with_name::<Sample>("Sample");
+    engine.register_fn("sample", Sample::new);
+    for &(name, constructor) in PRESETS.iter() {
+        engine.register_fn(name, move || constructor(None));
+        engine.register_fn(name, move |seed: INT| constructor(Some(seed as u64)));
+    }
+    engine.register_fn("mutate", |sample: &mut Sample, seed: INT| {
+        sample.mutate(Some(seed as u64))
+    });
+    engine.register_fn("freq_from_hz", Sample::freq_from_hz);
+
+    for &param in Param::ALL.iter() {
+        engine.register_get(param.name(), move |sample: &mut Sample| sample.get(param));
+        engine.register_set(param.name(), move |sample: &mut Sample, value: FLOAT| {
+            set(sample, param, value)
+        });
+        engine.register_set(param.name(), move |sample: &mut Sample, value: INT| {
+            set(sample, param, value as FLOAT)
+        });
+    }
+
+    let bank = Rc::clone(bank);
+    engine.register_fn("add", move |name: &str, sample: Sample| {
+        bank.borrow_mut().insert(name, sample);
+    });
+    engine
+}
+
+/// Sets `param` of `sample` to `value`, failing the script if it is out of range
+fn set(sample: &mut Sample, param: Param, value: f64) -> Result<(), Box<EvalAltResult>> {
+    sample.set(param, value).map_err(|e| e.to_string().into())
+}
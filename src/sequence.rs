@@ -0,0 +1,138 @@
+//! Scheduling of multiple sound effects on a shared timeline
+
+use crate::stereo::wide;
+use crate::{pan_gains, Generator, Sample, Widener};
+
+/// A composite sound effect made of Samples started at fixed sample offsets
+///
+/// Each entry has its own gain and stereo pan. The mix is generated as interleaved stereo
+/// (left, right) frames, either in chunks with `generate` or all at once with `render`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Sample, Sequence};
+/// let mut reload = Sequence::new();
+/// reload.add(0, Sample::hit(Some(1)), 1.0, -0.5);
+/// reload.add(8_000, Sample::blip(Some(2)), 0.8, 0.5);
+/// let stereo = reload.render();
+/// assert_eq!(stereo.len(), reload.len() * 2);
+/// ```
+pub struct Sequence {
+    entries: Vec<SequenceEntry>,
+    position: usize,
+    scratch: Vec<f32>,
+}
+
+struct SequenceEntry {
+    offset: usize,
+    length: usize,
+    gain: f32,
+    pan: f32,
+    generator: Generator,
+    /// Only for entries with a non-zero width, as the delay line is large
+    widener: Option<Box<Widener>>,
+}
+
+#[allow(clippy::new_without_default)]
+impl Sequence {
+    /// Constructs a new empty Sequence
+    pub fn new() -> Sequence {
+        Sequence {
+            entries: Vec::new(),
+            position: 0,
+            scratch: Vec::new(),
+        }
+    }
+    /// Schedules `sample` to start `offset` frames from the beginning of the sequence.
+    /// `pan` must be between `-1.0` (left) and `1.0` (right).
+    pub fn add(&mut self, offset: usize, sample: Sample, gain: f32, pan: f32) {
+        self.add_with_width(offset, sample, gain, pan, 0.0);
+    }
+    /// Schedules `sample` like `add`, spread across the stereo field by a `Widener` with
+    /// `width` between `0.0` (mono) and `1.0` (widest)
+    pub fn add_with_width(
+        &mut self,
+        offset: usize,
+        sample: Sample,
+        gain: f32,
+        pan: f32,
+        width: f32,
+    ) {
+        assert!(
+            (-1.0..=1.0).contains(&pan),
+            "pan must be between -1.0 and 1.0"
+        );
+        self.entries.push(SequenceEntry {
+            offset,
+            length: sample.envelope_length(),
+            gain,
+            pan,
+            generator: Generator::new(sample),
+            widener: wide(width),
+        });
+    }
+    /// Returns the length of the sequence in frames
+    pub fn len(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| entry.offset + entry.length)
+            .max()
+            .unwrap_or(0)
+    }
+    /// Returns `true` if the sequence contains no Samples
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Fills `buffer` with interleaved stereo frames of the mix. Subsequent calls continue where
+    /// the last left off. Call `reset` first to start generating from the beginning.
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        assert!(
+            buffer.len().is_multiple_of(2),
+            "buffer must hold a whole number of stereo frames"
+        );
+        buffer.iter_mut().for_each(|v| *v = 0.0);
+
+        let start = self.position;
+        let end = start + buffer.len() / 2;
+
+        for entry in self.entries.iter_mut() {
+            let from = entry.offset.max(start);
+            let until = (entry.offset + entry.length).min(end);
+            if from >= until {
+                continue;
+            }
+
+            self.scratch.resize(until - from, 0.0);
+            entry.generator.generate(&mut self.scratch);
+
+            let (left, right) = pan_gains(entry.pan);
+            let frames = buffer[(from - start) * 2..(until - start) * 2].chunks_mut(2);
+            for (frame, &v) in frames.zip(self.scratch.iter()) {
+                let v = v * entry.gain;
+                let (v_left, v_right) = entry.widener.as_mut().map_or((v, v), |w| w.process(v));
+                frame[0] += v_left * left;
+                frame[1] += v_right * right;
+            }
+        }
+
+        self.position = end;
+    }
+    /// Resets the sequence to the beginning
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.entries.iter_mut().for_each(|entry| {
+            entry.generator.reset();
+            if let Some(widener) = entry.widener.as_mut() {
+                **widener = Widener::new(widener.width());
+            }
+        });
+    }
+    /// Renders the whole sequence from the beginning into interleaved stereo frames
+    pub fn render(&mut self) -> Vec<f32> {
+        let mut buffer = vec![0.0; self.len() * 2];
+        self.reset();
+        self.generate(&mut buffer);
+        buffer
+    }
+}
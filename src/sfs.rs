@@ -0,0 +1,181 @@
+//! Reading and writing the `.sfs` settings files of original sfxr
+//!
+//! Versions 100, 101 and 102 of the format are read. Version 101 added `freq_dramp` and the
+//! arpeggio, and version 102 the per-sound `volume`. Files are written as version 102.
+//!
+//! The format only has the fields of original sfxr, so other fields such as `unison_voices` are
+//! written as if at their `Sample::new` values and read as those.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::{sfs, Sample};
+//! let mut coin = Sample::pickup(Some(3));
+//! coin.volume = 0.3;
+//!
+//! let mut bytes = Vec::new();
+//! sfs::write(&mut bytes, &coin).unwrap();
+//! let loaded = sfs::read(&bytes[..]).unwrap();
+//! assert_eq!(loaded.volume, 0.3);
+//! ```
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{Error, Sample, TypedSample, WaveType};
+
+/// Version written by `write`
+const VERSION: i32 = 102;
+
+/// Reads a Sample from `.sfs` data. Fails if the data is truncated, has an unknown version or
+/// wave type, or has values outside their valid ranges.
+pub fn read<R: Read>(mut reader: R) -> Result<Sample, Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut data = Data(&bytes[..]);
+
+    let version = data.i32()?;
+    if !(100..=102).contains(&version) {
+        return Err(parse_error(format!("unsupported version {}", version)));
+    }
+
+    let mut s = Sample::new();
+    let wave_type = data.i32()?;
+    s.wave_type = u8::try_from(wave_type)
+        .ok()
+        .and_then(WaveType::from_index)
+        .ok_or_else(|| parse_error(format!("unknown wave type {}", wave_type)))?;
+    if version == 102 {
+        s.volume = data.f32()?;
+    }
+
+    s.base_freq = data.f64()?;
+    s.freq_limit = data.f64()?;
+    s.freq_ramp = data.f64()?;
+    if version >= 101 {
+        s.freq_dramp = data.f64()?;
+    }
+    s.duty = data.f32()?;
+    s.duty_ramp = data.f32()?;
+
+    s.vib_strength = data.f64()?;
+    s.vib_speed = data.f64()?;
+    s.vib_delay = data.f32()?;
+
+    s.env_attack = data.f32()?;
+    s.env_sustain = data.f32()?;
+    s.env_decay = data.f32()?;
+    s.env_punch = data.f32()?;
+
+    // `filter_on`, which original sfxr stores but never uses
+    data.take(1)?;
+    s.lpf_resonance = data.f32()?;
+    s.lpf_freq = data.f32()?;
+    s.lpf_ramp = data.f32()?;
+    s.hpf_freq = data.f32()?;
+    s.hpf_ramp = data.f32()?;
+
+    s.pha_offset = data.f32()?;
+    s.pha_ramp = data.f32()?;
+
+    s.repeat_speed = data.f32()?;
+
+    if version >= 101 {
+        s.arp_speed = data.f32()?;
+        s.arp_mod = data.f64()?;
+    }
+
+    TypedSample::try_from(s.clone())?;
+    Ok(s)
+}
+
+/// Writes `sample` as version 102 `.sfs` data
+pub fn write<W: Write>(mut writer: W, sample: &Sample) -> io::Result<()> {
+    let s = sample;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&i32::from(s.wave_type.as_index()).to_le_bytes())?;
+
+    let fields = [
+        s.volume,
+        s.base_freq as f32,
+        s.freq_limit as f32,
+        s.freq_ramp as f32,
+        s.freq_dramp as f32,
+        s.duty,
+        s.duty_ramp,
+        s.vib_strength as f32,
+        s.vib_speed as f32,
+        s.vib_delay,
+        s.env_attack,
+        s.env_sustain,
+        s.env_decay,
+        s.env_punch,
+    ];
+    for v in fields.iter() {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+
+    writer.write_all(&[0])?; // filter_on
+    let fields = [
+        s.lpf_resonance,
+        s.lpf_freq,
+        s.lpf_ramp,
+        s.hpf_freq,
+        s.hpf_ramp,
+        s.pha_offset,
+        s.pha_ramp,
+        s.repeat_speed,
+        s.arp_speed,
+        s.arp_mod as f32,
+    ];
+    for v in fields.iter() {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Reads a Sample from the `.sfs` file at `path`, see `read`
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Sample, Error> {
+    read(BufReader::new(File::open(path)?))
+}
+
+/// Writes `sample` to an `.sfs` file at `path`, see `write`
+pub fn save<P: AsRef<Path>>(path: P, sample: &Sample) -> io::Result<()> {
+    write(BufWriter::new(File::create(path)?), sample)
+}
+
+/// Remaining unread `.sfs` data
+struct Data<'a>(&'a [u8]);
+
+impl<'a> Data<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(parse_error("unexpected end of data".to_string()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+    fn i32(&mut self) -> Result<i32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(i32::from_le_bytes(bytes))
+    }
+    fn f32(&mut self) -> Result<f32, Error> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(f32::from_le_bytes(bytes))
+    }
+    fn f64(&mut self) -> Result<f64, Error> {
+        self.f32().map(f64::from)
+    }
+}
+
+fn parse_error(message: String) -> Error {
+    Error::Parse {
+        format: ".sfs",
+        message,
+    }
+}
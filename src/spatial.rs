@@ -0,0 +1,170 @@
+//! Simple distance attenuation, panning and Doppler for positioned sound effects
+//!
+//! This is not a 3D audio engine. It computes gain, pan and pitch factors from listener and
+//! emitter positions, which are then applied to a Generator through `GeneratorOptions`.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::spatial::{Emitter, Listener, Spatial};
+//! use sfxr::{Generator, Sample};
+//! let listener = Listener::new([0.0, 0.0, 0.0]);
+//! let mut emitter = Emitter::new([10.0, 0.0, 0.0]);
+//! emitter.velocity = [-20.0, 0.0, 0.0];
+//!
+//! let spatial = Spatial::new(&listener, &emitter);
+//! assert!(spatial.pan > 0.0);
+//! assert!(spatial.pitch_offset_semitones > 0.0);
+//! let generator = Generator::new_with(Sample::laser(None), spatial.options());
+//! ```
+
+use crate::GeneratorOptions;
+
+/// Speed of sound in air in units per second, assuming units are meters
+pub const SPEED_OF_SOUND: f32 = 343.0;
+
+/// Position, velocity and orientation of the listener
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Listener {
+    /// Position in world units
+    pub position: [f32; 3],
+    /// Velocity in world units per second
+    pub velocity: [f32; 3],
+    /// Unit vector pointing to the listener's right
+    pub right: [f32; 3],
+}
+
+/// Position and velocity of a sound emitter
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Emitter {
+    /// Position in world units
+    pub position: [f32; 3],
+    /// Velocity in world units per second
+    pub velocity: [f32; 3],
+}
+
+impl Emitter {
+    /// Constructs a new stationary Emitter at `position`
+    pub fn new(position: [f32; 3]) -> Emitter {
+        Emitter {
+            position,
+            velocity: [0.0; 3],
+        }
+    }
+}
+
+impl Listener {
+    /// Constructs a new stationary Listener at `position` with positive x to the right
+    pub fn new(position: [f32; 3]) -> Listener {
+        Listener {
+            position,
+            velocity: [0.0; 3],
+            right: [1.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Distance rolloff settings
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Rolloff {
+    /// Distance within which the sound plays at full volume
+    pub min_distance: f32,
+    /// Distance beyond which the sound is no longer attenuated further
+    pub max_distance: f32,
+    /// How quickly volume falls off beyond `min_distance`. `1.0` is physically based inverse
+    /// distance attenuation.
+    pub factor: f32,
+}
+
+impl Default for Rolloff {
+    fn default() -> Rolloff {
+        Rolloff {
+            min_distance: 1.0,
+            max_distance: 100.0,
+            factor: 1.0,
+        }
+    }
+}
+
+/// Gain, pan and pitch factors for an emitter as heard by a listener
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Spatial {
+    /// Volume multiplier between `0.0` and `1.0`
+    pub gain: f32,
+    /// Stereo position between `-1.0` (left) and `1.0` (right)
+    pub pan: f32,
+    /// Doppler pitch shift in semitones
+    pub pitch_offset_semitones: f32,
+}
+
+impl Spatial {
+    /// Computes spatial factors with default rolloff
+    pub fn new(listener: &Listener, emitter: &Emitter) -> Spatial {
+        Spatial::with_rolloff(listener, emitter, &Rolloff::default())
+    }
+    /// Computes spatial factors using `rolloff` for distance attenuation. Panics unless
+    /// `0.0 <= rolloff.min_distance <= rolloff.max_distance`.
+    pub fn with_rolloff(listener: &Listener, emitter: &Emitter, rolloff: &Rolloff) -> Spatial {
+        assert!(
+            rolloff.min_distance >= 0.0,
+            "min_distance must not be negative"
+        );
+        assert!(
+            rolloff.min_distance <= rolloff.max_distance,
+            "min_distance must not be greater than max_distance"
+        );
+        let offset = sub(emitter.position, listener.position);
+        let distance = dot(offset, offset).sqrt();
+
+        let clamped = distance.min(rolloff.max_distance);
+        let gain = if clamped <= rolloff.min_distance || rolloff.factor == 0.0 {
+            1.0
+        } else {
+            rolloff.min_distance
+                / (rolloff.min_distance + rolloff.factor * (clamped - rolloff.min_distance))
+        };
+
+        let (pan, pitch_offset_semitones) = if distance > f32::EPSILON {
+            let direction = [
+                offset[0] / distance,
+                offset[1] / distance,
+                offset[2] / distance,
+            ];
+            let pan = dot(direction, listener.right).clamp(-1.0, 1.0);
+
+            // Velocities along the direction from listener to emitter
+            let listener_speed = dot(listener.velocity, direction);
+            let emitter_speed = dot(emitter.velocity, direction);
+            let ratio = (SPEED_OF_SOUND + listener_speed) / (SPEED_OF_SOUND + emitter_speed);
+            let semitones = if ratio > 0.0 {
+                12.0 * ratio.log2()
+            } else {
+                0.0
+            };
+            (pan, semitones)
+        } else {
+            (0.0, 0.0)
+        };
+
+        Spatial {
+            gain: gain.clamp(0.0, 1.0),
+            pan,
+            pitch_offset_semitones,
+        }
+    }
+    /// Returns GeneratorOptions applying the gain and pitch. Pan must be applied when mixing.
+    pub fn options(&self) -> GeneratorOptions {
+        let mut options = GeneratorOptions::new();
+        options.gain = self.gain;
+        options.pitch_offset_semitones = self.pitch_offset_semitones;
+        options
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
@@ -0,0 +1,74 @@
+//! Stereo widening of mono sound effects
+
+use crate::SAMPLE_RATE;
+
+/// Haas delay of the widened side signal, 12 ms
+const DELAY: usize = SAMPLE_RATE as usize * 12 / 1_000;
+
+/// Spreads a mono signal across the stereo field
+///
+/// The side signal is the difference between the sound and a copy delayed by 12 ms, the Haas
+/// effect, and is scaled by `width` before converting mid and side to left and right. Width
+/// `0.0` leaves the sound centered and unchanged, so UI blips stay focused while explosions
+/// can feel wide. Used by `Mixer::play_with_width` and `Sequence::add_with_width`.
+///
+/// # Examples
+///
+/// ``` rust
+/// use sfxr::{Generator, Sample, Widener};
+/// let mut widener = Widener::new(0.8);
+/// let stereo: Vec<(f32, f32)> = Generator::new(Sample::explosion(Some(3)))
+///     .map(|v| widener.process(v))
+///     .take(44_100)
+///     .collect();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Widener {
+    width: f32,
+    delay: [f32; DELAY],
+    position: usize,
+}
+
+impl Widener {
+    /// Constructs a Widener with `width` between `0.0` (mono) and `1.0` (widest)
+    pub fn new(width: f32) -> Widener {
+        let mut widener = Widener {
+            width: 0.0,
+            delay: [0.0; DELAY],
+            position: 0,
+        };
+        widener.set_width(width);
+        widener
+    }
+    /// Returns the width
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+    /// Sets the width, between `0.0` and `1.0`. The delay line is kept, so the width can be
+    /// changed while playing.
+    pub fn set_width(&mut self, width: f32) {
+        assert!(
+            (0.0..=1.0).contains(&width),
+            "width must be between 0.0 and 1.0"
+        );
+        self.width = width;
+    }
+    /// Processes one mono sample into a (left, right) pair
+    pub fn process(&mut self, sample: f32) -> (f32, f32) {
+        let delayed = self.delay[self.position];
+        self.delay[self.position] = sample;
+        self.position = (self.position + 1) % DELAY;
+
+        let side = (sample - delayed) * 0.5 * self.width;
+        (sample + side, sample - side)
+    }
+}
+
+/// Returns a Widener with `width`, or `None` for width `0.0`, which leaves the sound unchanged
+pub(crate) fn wide(width: f32) -> Option<Box<Widener>> {
+    if width == 0.0 {
+        None
+    } else {
+        Some(Box::new(Widener::new(width)))
+    }
+}
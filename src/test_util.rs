@@ -0,0 +1,69 @@
+//! Deterministic rendering for automated tests of game audio
+//!
+//! `capture` renders a Sample the same way on every run, without an audio device, and
+//! `snapshot_hash` reduces the result to a number that can be stored as a golden value.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::test_util::{assert_snapshot, capture, snapshot_hash};
+//! use sfxr::Sample;
+//!
+//! let jump = Sample::jump(Some(3));
+//! let audio = capture(&jump, 0.5, 44_100);
+//! assert_eq!(audio.len(), 22_050);
+//! // A golden test stores the hash as a literal
+//! let golden = snapshot_hash(&audio);
+//! // Capturing again gives the same audio
+//! assert_snapshot(&capture(&jump, 0.5, 44_100), golden);
+//! ```
+
+use crate::{Generator, GeneratorConfig, GeneratorOptions, Sample};
+
+/// Bits each sample is quantized to before hashing. Coarser than 16 bit audio, so rounding
+/// differences of floating point math between platforms rarely change the hash.
+const SNAPSHOT_BITS: u32 = 12;
+
+/// Renders `secs` seconds of `sample` at `rate` Hz with the default volume, including any
+/// silence after the sound ends
+///
+/// A Sample without a `noise_seed` is rendered with seed `0`, so noise is the same on every
+/// call instead of continuing the shared noise sequence.
+pub fn capture(sample: &Sample, secs: f32, rate: u32) -> Vec<f32> {
+    assert!(secs >= 0.0, "secs must not be negative");
+    let mut sample = sample.clone();
+    sample.noise_seed.get_or_insert(0);
+    let config = GeneratorConfig {
+        sample_rate: rate,
+        ..GeneratorConfig::new()
+    };
+    let mut generator = Generator::new_with_config(sample, GeneratorOptions::new(), config);
+    let mut buffer = vec![0.0; (secs * rate as f32).round() as usize];
+    generator.generate(&mut buffer);
+    buffer
+}
+
+/// Returns a 64 bit FNV-1a hash of `buffer` quantized to 12 bits per sample
+///
+/// Equal audio gives equal hashes on every platform and version of Rust, so the hash can be
+/// stored in a test and compared with `assert_snapshot`.
+pub fn snapshot_hash(buffer: &[f32]) -> u64 {
+    let scale = (1 << (SNAPSHOT_BITS - 1)) as f32;
+    buffer.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &value| {
+        let quantized = (value.clamp(-1.0, 1.0) * scale).round() as i16;
+        quantized.to_le_bytes().iter().fold(hash, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    })
+}
+
+/// Panics, showing the actual hash, if `snapshot_hash(buffer)` differs from `expected`
+pub fn assert_snapshot(buffer: &[f32], expected: u64) {
+    let actual = snapshot_hash(buffer);
+    assert!(
+        actual == expected,
+        "audio snapshot hash {:#018x} differs from expected {:#018x}",
+        actual,
+        expected
+    );
+}
@@ -0,0 +1,216 @@
+//! Minimal fixed tempo music tracker
+//!
+//! A `Song` is a list of rows, each with an optional `Note` for each of the `CHANNELS` channels.
+//! A note plays one of the song's instruments, which are plain Samples, at a given pitch until
+//! the next note on the same channel. Each channel reuses a single Generator for all its notes.
+//!
+//! # Examples
+//!
+//! ``` rust
+//! use sfxr::tracker::{Effect, Note, Song, Tracker};
+//! use sfxr::{Sample, WaveType};
+//! let mut lead = Sample::new();
+//! lead.wave_type = WaveType::Square;
+//! lead.env_attack = 0.0;
+//!
+//! let mut song = Song::new(vec![lead], 120.0, 4);
+//! for &pitch in &[60, 64, 67, 72] {
+//!     let note = Note { pitch, instrument: 0, effect: Effect::None };
+//!     song.rows.push([Some(note), None, None, None]);
+//! }
+//!
+//! let mut tracker = Tracker::new(song);
+//! let mut buffer = [0.0; 44_100];
+//! tracker.generate(&mut buffer);
+//! ```
+
+use crate::{Generator, Param, Sample, SAMPLE_RATE};
+
+/// Number of simultaneously playing channels
+pub const CHANNELS: usize = 4;
+
+/// One row of a song, with an optional note for each channel
+pub type Row = [Option<Note>; CHANNELS];
+
+/// Effect applied to a single note
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Effect {
+    /// Play the instrument as is
+    None,
+    /// Override the instrument's arpeggio, with values as in `Sample::arp_speed` and
+    /// `Sample::arp_mod`
+    Arpeggio {
+        /// Arpeggio interval
+        speed: f32,
+        /// Arpeggio step in frequency
+        step: f64,
+    },
+    /// Override the instrument's frequency slide, with values as in `Sample::freq_ramp`
+    Slide(f64),
+}
+
+/// A note played on a channel
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Note {
+    /// Pitch as a MIDI note number, where `69` is A4 at 440 Hz
+    pub pitch: u8,
+    /// Index of the played instrument in `Song::instruments`. Notes of missing instruments are
+    /// skipped.
+    pub instrument: usize,
+    /// Effect applied to the note
+    pub effect: Effect,
+}
+
+impl Note {
+    /// Returns the Sample for playing this note with `instrument`. Effect values are limited
+    /// to the valid ranges of the fields they override.
+    pub fn apply(&self, instrument: &Sample) -> Sample {
+        let mut s = instrument.clone();
+        let hz = 440.0 * 2f64.powf((f64::from(self.pitch) - 69.0) / 12.0);
+        s.base_freq = Sample::freq_from_hz(hz);
+
+        match self.effect {
+            Effect::None => {}
+            Effect::Arpeggio { speed, step } => {
+                s.arp_speed = limit(Param::ArpSpeed, f64::from(speed)) as f32;
+                s.arp_mod = limit(Param::ArpMod, step);
+            }
+            Effect::Slide(ramp) => s.freq_ramp = limit(Param::FreqRamp, ramp),
+        }
+
+        s
+    }
+}
+
+/// Limits `value` to the range of `param`, with NaN becoming the lower limit
+fn limit(param: Param, value: f64) -> f64 {
+    let (min, max) = param.range();
+    value.max(min).min(max)
+}
+
+/// Song data played by a Tracker
+#[derive(Clone, Debug)]
+pub struct Song {
+    /// Instruments referred to by `Note::instrument`
+    pub instruments: Vec<Sample>,
+    /// Rows played in order
+    pub rows: Vec<Row>,
+    row_length: usize,
+    /// Whether to start over from the first row after the last one
+    pub looping: bool,
+}
+
+impl Song {
+    /// Constructs a new Song without rows with tempo `bpm` and `rows_per_beat` rows per beat.
+    /// Panics if a row would be shorter than one sample.
+    pub fn new(instruments: Vec<Sample>, bpm: f32, rows_per_beat: u32) -> Song {
+        assert!(bpm > 0.0, "bpm must be greater than zero");
+        assert!(rows_per_beat > 0, "rows_per_beat must be greater than zero");
+        let row_length = (SAMPLE_RATE as f32 * 60.0 / bpm / rows_per_beat as f32) as usize;
+        assert!(row_length > 0, "row_length must be greater than zero");
+        Song {
+            instruments,
+            rows: Vec::new(),
+            row_length,
+            looping: false,
+        }
+    }
+    /// Returns the length of each row in samples
+    pub fn row_length(&self) -> usize {
+        self.row_length
+    }
+}
+
+/// Plays a Song
+///
+/// Like `Generator`, the tracker fills buffers with mono sound data and maintains its position
+/// from one call to `generate` to the next.
+pub struct Tracker {
+    /// The song being played
+    pub song: Song,
+    channels: [Option<Generator>; CHANNELS],
+    row: usize,
+    row_position: usize,
+    scratch: Vec<f32>,
+}
+
+impl Tracker {
+    /// Constructs a new Tracker playing `song` from the beginning
+    pub fn new(song: Song) -> Tracker {
+        Tracker {
+            song,
+            channels: [None, None, None, None],
+            row: 0,
+            row_position: 0,
+            scratch: Vec::new(),
+        }
+    }
+    /// Returns `true` once a non-looping song has played all its rows
+    pub fn is_finished(&self) -> bool {
+        self.row >= self.song.rows.len()
+    }
+    /// Fills `buffer` with the mix of all channels. Subsequent calls continue where the last
+    /// left off. Call `reset` first to start playing from the beginning.
+    pub fn generate(&mut self, buffer: &mut [f32]) {
+        buffer.iter_mut().for_each(|v| *v = 0.0);
+
+        let mut done = 0;
+        while done < buffer.len() {
+            if self.row_position == 0 {
+                self.trigger_row();
+            }
+
+            let count = (buffer.len() - done).min(self.song.row_length - self.row_position);
+            let out = &mut buffer[done..done + count];
+            self.scratch.resize(count, 0.0);
+
+            for generator in self.channels.iter_mut().flatten() {
+                generator.generate(&mut self.scratch);
+                out.iter_mut()
+                    .zip(self.scratch.iter())
+                    .for_each(|(o, v)| *o += v);
+            }
+
+            done += count;
+            self.row_position += count;
+            if self.row_position == self.song.row_length {
+                self.row_position = 0;
+                self.row += 1;
+                if self.song.looping && self.row >= self.song.rows.len() {
+                    self.row = 0;
+                }
+            }
+        }
+
+        buffer.iter_mut().for_each(|v| *v = v.clamp(-1.0, 1.0));
+    }
+    /// Stops all channels and moves back to the first row
+    pub fn reset(&mut self) {
+        self.channels = [None, None, None, None];
+        self.row = 0;
+        self.row_position = 0;
+    }
+    fn trigger_row(&mut self) {
+        let row = match self.song.rows.get(self.row) {
+            Some(row) => *row,
+            None => return,
+        };
+
+        let instruments = &self.song.instruments;
+        for (channel, note) in self.channels.iter_mut().zip(row.iter()) {
+            // Notes of instruments missing from the song are skipped
+            let note = note.filter(|note| note.instrument < instruments.len());
+            if let Some(note) = note {
+                let sample = note.apply(&instruments[note.instrument]);
+                match channel {
+                    Some(generator) => {
+                        sample.assert_valid();
+                        generator.sample = sample;
+                        generator.reset();
+                    }
+                    None => *channel = Some(Generator::new(sample)),
+                }
+            }
+        }
+    }
+}
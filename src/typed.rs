@@ -0,0 +1,407 @@
+//! Range checked field types for `TypedSample`
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use crate::{
+    ArpMode, Compressor, Equalizer, FreqLimitBehavior, Key, NoiseMode, NoiseTable, Param,
+    RampTarget, RepeatMode, Sample, SampleHold, SlideMode, WaveType,
+};
+
+/// A value outside the valid range of a field
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct RangeError {
+    /// The field the value was for, if known
+    pub param: Option<Param>,
+    /// The rejected value
+    pub value: f64,
+    /// Smallest valid value
+    pub min: f64,
+    /// Largest valid value
+    pub max: f64,
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(param) = self.param {
+            write!(f, "{} ", param.name())?;
+        }
+        write!(
+            f,
+            "value {} is not between {} and {}",
+            self.value, self.min, self.max
+        )
+    }
+}
+
+impl error::Error for RangeError {}
+
+macro_rules! unit_type {
+    ($(#[$meta:meta])* $name:ident, $inner:ty, $min:expr) => {
+        $(#[$meta])*
+        #[derive(PartialEq, PartialOrd, Copy, Clone, Debug, Default)]
+        pub struct $name($inner);
+
+        impl $name {
+            /// Smallest valid value
+            pub const MIN: $name = $name($min);
+            /// Largest valid value
+            pub const MAX: $name = $name(1.0);
+
+            /// Returns the value, or `None` if it is out of range or NaN
+            pub fn new(value: $inner) -> Option<$name> {
+                if ($min..=1.0).contains(&value) {
+                    Some($name(value))
+                } else {
+                    None
+                }
+            }
+            /// Returns the value clamped to the valid range. NaN becomes zero.
+            pub fn saturating(value: $inner) -> $name {
+                if value.is_nan() {
+                    $name(0.0)
+                } else {
+                    $name(value.clamp($min, 1.0))
+                }
+            }
+            /// Returns the contained value
+            pub fn get(self) -> $inner {
+                self.0
+            }
+        }
+
+        impl TryFrom<$inner> for $name {
+            type Error = RangeError;
+
+            fn try_from(value: $inner) -> Result<$name, RangeError> {
+                $name::new(value).ok_or(RangeError {
+                    param: None,
+                    value: f64::from(value),
+                    min: $min,
+                    max: 1.0,
+                })
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> $inner {
+                value.0
+            }
+        }
+    };
+}
+
+unit_type!(
+    /// An `f32` between `0.0` and `1.0`
+    UnitF32,
+    f32,
+    0.0
+);
+unit_type!(
+    /// An `f32` between `-1.0` and `1.0`
+    SignedUnitF32,
+    f32,
+    -1.0
+);
+unit_type!(
+    /// An `f64` between `0.0` and `1.0`
+    UnitF64,
+    f64,
+    0.0
+);
+unit_type!(
+    /// An `f64` between `-1.0` and `1.0`
+    SignedUnitF64,
+    f64,
+    -1.0
+);
+
+/// A finite `f32`, neither infinite nor NaN
+#[derive(PartialEq, PartialOrd, Copy, Clone, Debug, Default)]
+pub struct FiniteF32(f32);
+
+impl FiniteF32 {
+    /// Returns the value, or `None` if it is infinite or NaN
+    pub fn new(value: f32) -> Option<FiniteF32> {
+        if value.is_finite() {
+            Some(FiniteF32(value))
+        } else {
+            None
+        }
+    }
+    /// Returns the contained value
+    pub fn get(self) -> f32 {
+        self.0
+    }
+}
+
+impl TryFrom<f32> for FiniteF32 {
+    type Error = RangeError;
+
+    fn try_from(value: f32) -> Result<FiniteF32, RangeError> {
+        FiniteF32::new(value).ok_or(RangeError {
+            param: None,
+            value: f64::from(value),
+            min: f64::from(f32::MIN),
+            max: f64::from(f32::MAX),
+        })
+    }
+}
+
+impl From<FiniteF32> for f32 {
+    fn from(value: FiniteF32) -> f32 {
+        value.0
+    }
+}
+
+/// Returns `value` if it is within the range of `param`
+fn check_range(param: Param, value: f32) -> Result<f32, RangeError> {
+    let (min, max) = param.range();
+    if (min..=max).contains(&f64::from(value)) {
+        Ok(value)
+    } else {
+        Err(RangeError {
+            param: Some(param),
+            value: f64::from(value),
+            min,
+            max,
+        })
+    }
+}
+
+/// Returns `target` if its value is within the range of `param` and its duration is not
+/// negative
+fn check_target(
+    param: Param,
+    target: Option<RampTarget>,
+) -> Result<Option<RampTarget>, RangeError> {
+    if let Some(target) = target {
+        check_range(param, target.value)?;
+        if target.duration_secs.is_nan() || target.duration_secs < 0.0 {
+            return Err(RangeError {
+                param: None,
+                value: f64::from(target.duration_secs),
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+    }
+    Ok(target)
+}
+
+macro_rules! typed_sample {
+    ($($(#[$meta:meta])* $field:ident: $param:ident, $ty:ty;)*) => {
+        /// A `Sample` whose fields can only hold values in their valid ranges
+        ///
+        /// Converts losslessly to and from `Sample`, and can be played with
+        /// `Generator::from_typed` without validation.
+        ///
+        /// # Examples
+        ///
+        /// ``` rust
+        /// use std::convert::TryFrom;
+        /// use sfxr::{Easing, Generator, RampTarget, Sample, TypedSample, UnitF64};
+        /// let mut sample = TypedSample::new();
+        /// sample.base_freq = UnitF64::try_from(0.6).unwrap();
+        /// let generator = Generator::from_typed(sample);
+        ///
+        /// let mut raw = Sample::new();
+        /// raw.env_punch = 2.0;
+        /// assert!(TypedSample::try_from(raw).is_err());
+        ///
+        /// let mut raw = Sample::new();
+        /// raw.gain_db = f32::NAN;
+        /// assert!(TypedSample::try_from(raw).is_err());
+        ///
+        /// let mut raw = Sample::new();
+        /// raw.lpf_target = Some(RampTarget {
+        ///     value: 0.5,
+        ///     duration_secs: -1.0,
+        ///     easing: Easing::Linear,
+        /// });
+        /// assert!(TypedSample::try_from(raw).is_err());
+        /// ```
+        #[derive(PartialEq, Clone, Debug)]
+        pub struct TypedSample {
+            /// Oscillator wave type
+            pub wave_type: WaveType,
+            $($(#[$meta])* pub $field: $ty,)*
+            /// Number of unison voices. All values are valid.
+            pub unison_voices: u8,
+            /// Volume of this sound
+            pub volume: UnitF32,
+            /// Restart the noise on every repeat and reset
+            pub noise_retrigger: bool,
+            /// Seed of the noise waveform
+            pub noise_seed: Option<u64>,
+            /// Whether noise is pitched or continuous
+            pub noise_mode: NoiseMode,
+            /// How the frequency slides
+            pub slide_mode: SlideMode,
+            /// Time based duty cycle transition, with a `value` between `0.0` and `1.0` and a
+            /// `duration_secs` that is not negative
+            pub duty_target: Option<RampTarget>,
+            /// Time based low pass cutoff transition, with a `value` between `0.0` and `1.0` and a
+            /// `duration_secs` that is not negative
+            pub lpf_target: Option<RampTarget>,
+            /// Three band equalizer. Out of range values are clamped when played.
+            pub equalizer: Option<Equalizer>,
+            /// Dynamics compressor. Out of range values are clamped when played.
+            pub compressor: Option<Compressor>,
+            /// Random stepped modulation
+            pub sample_hold: SampleHold,
+            /// Time each random `sample_hold` value is held. All values are valid.
+            pub sample_hold_secs: f32,
+            /// What restarts on each repeat
+            pub repeat_mode: RepeatMode,
+            /// Whether the arpeggio steps once or cycles
+            pub arp_mode: ArpMode,
+            /// Length of the phaser delay line. All values are valid.
+            pub pha_length: u16,
+            /// Right channel phaser offset
+            pub pha_stereo: SignedUnitF32,
+            /// Additional gain in decibels
+            pub gain_db: FiniteF32,
+            /// Values played in place of random noise
+            pub custom_noise: Option<NoiseTable>,
+            /// Key the pitch snaps to
+            pub snap_to_key: Option<Key>,
+            /// Whether the sound ends at the frequency limit
+            pub freq_limit_behavior: FreqLimitBehavior,
+        }
+
+        impl From<TypedSample> for Sample {
+            fn from(typed: TypedSample) -> Sample {
+                Sample {
+                    wave_type: typed.wave_type,
+                    $($field: typed.$field.get(),)*
+                    unison_voices: typed.unison_voices,
+                    volume: typed.volume.get(),
+                    noise_retrigger: typed.noise_retrigger,
+                    noise_seed: typed.noise_seed,
+                    noise_mode: typed.noise_mode,
+                    slide_mode: typed.slide_mode,
+                    duty_target: typed.duty_target,
+                    lpf_target: typed.lpf_target,
+                    equalizer: typed.equalizer,
+                    compressor: typed.compressor,
+                    sample_hold: typed.sample_hold,
+                    sample_hold_secs: typed.sample_hold_secs,
+                    repeat_mode: typed.repeat_mode,
+                    arp_mode: typed.arp_mode,
+                    pha_length: typed.pha_length,
+                    pha_stereo: typed.pha_stereo.get(),
+                    gain_db: typed.gain_db.get(),
+                    custom_noise: typed.custom_noise,
+                    snap_to_key: typed.snap_to_key,
+                    freq_limit_behavior: typed.freq_limit_behavior,
+                }
+            }
+        }
+
+        impl TryFrom<Sample> for TypedSample {
+            type Error = RangeError;
+
+            fn try_from(sample: Sample) -> Result<TypedSample, RangeError> {
+                Ok(TypedSample {
+                    wave_type: sample.wave_type,
+                    $($field: <$ty>::try_from(sample.$field).map_err(|e| RangeError {
+                        param: Some(Param::$param),
+                        ..e
+                    })?,)*
+                    unison_voices: sample.unison_voices,
+                    volume: UnitF32::try_from(sample.volume).map_err(|e| RangeError {
+                        param: Some(Param::Volume),
+                        ..e
+                    })?,
+                    noise_retrigger: sample.noise_retrigger,
+                    noise_seed: sample.noise_seed,
+                    noise_mode: sample.noise_mode,
+                    slide_mode: sample.slide_mode,
+                    duty_target: check_target(Param::Duty, sample.duty_target)?,
+                    lpf_target: check_target(Param::LpfFreq, sample.lpf_target)?,
+                    equalizer: sample.equalizer,
+                    compressor: sample.compressor,
+                    sample_hold: sample.sample_hold,
+                    sample_hold_secs: sample.sample_hold_secs,
+                    repeat_mode: sample.repeat_mode,
+                    arp_mode: sample.arp_mode,
+                    pha_length: sample.pha_length,
+                    pha_stereo: SignedUnitF32::try_from(sample.pha_stereo).map_err(|e| {
+                        RangeError {
+                            param: Some(Param::PhaStereo),
+                            ..e
+                        }
+                    })?,
+                    gain_db: FiniteF32::try_from(sample.gain_db).map_err(|e| RangeError {
+                        param: Some(Param::GainDb),
+                        ..e
+                    })?,
+                    custom_noise: sample.custom_noise,
+                    snap_to_key: sample.snap_to_key,
+                    freq_limit_behavior: sample.freq_limit_behavior,
+                })
+            }
+        }
+    };
+}
+
+typed_sample! {
+    /// Oscillator base frequency
+    base_freq: BaseFreq, UnitF64;
+    /// Oscillator frequency limit
+    freq_limit: FreqLimit, UnitF64;
+    /// Oscillator frequency change over time
+    freq_ramp: FreqRamp, SignedUnitF64;
+    /// Change of oscillator frequency change over time
+    freq_dramp: FreqDramp, SignedUnitF64;
+    /// Duty cycle of the square and sawtooth waves
+    duty: Duty, UnitF32;
+    /// Duty cycle change over time
+    duty_ramp: DutyRamp, SignedUnitF32;
+    /// Vibrato strength
+    vib_strength: VibStrength, UnitF64;
+    /// Vibrato speed
+    vib_speed: VibSpeed, UnitF64;
+    /// Vibrato delay
+    vib_delay: VibDelay, UnitF32;
+    /// Envelope attack length
+    env_attack: EnvAttack, UnitF32;
+    /// Envelope sustain length
+    env_sustain: EnvSustain, UnitF32;
+    /// Envelope decay length
+    env_decay: EnvDecay, UnitF32;
+    /// Envelope sustain punch
+    env_punch: EnvPunch, SignedUnitF32;
+    /// Low pass filter resonance
+    lpf_resonance: LpfResonance, UnitF32;
+    /// Low pass filter cutoff frequency
+    lpf_freq: LpfFreq, UnitF32;
+    /// Low pass filter cutoff frequency change over time
+    lpf_ramp: LpfRamp, SignedUnitF32;
+    /// High pass filter cutoff frequency
+    hpf_freq: HpfFreq, UnitF32;
+    /// High pass filter cutoff frequency change over time
+    hpf_ramp: HpfRamp, SignedUnitF32;
+    /// Phaser temporal offset
+    pha_offset: PhaOffset, SignedUnitF32;
+    /// Phaser temporal offset change over time
+    pha_ramp: PhaRamp, SignedUnitF32;
+    /// Sample repeat speed
+    repeat_speed: RepeatSpeed, UnitF32;
+    /// Arpeggio interval
+    arp_speed: ArpSpeed, UnitF32;
+    /// Arpeggio step in frequency
+    arp_mod: ArpMod, SignedUnitF64;
+    /// Unison detune spread
+    unison_detune: UnisonDetune, UnitF32;
+}
+
+#[allow(clippy::new_without_default)]
+impl TypedSample {
+    /// Constructs a new TypedSample with the settings of `Sample::new`
+    pub fn new() -> TypedSample {
+        TypedSample::try_from(Sample::new()).expect("valid default sample")
+    }
+}
@@ -0,0 +1,77 @@
+use rand::Rng;
+
+use crate::{rand_f32, rand_f64, Generator, GeneratorConfig, GeneratorOptions, Param, Sample};
+
+/// A Sample that varies slightly each time it is played
+///
+/// Stores a base Sample and jitter amounts for selected fields. Each call to `spawn` draws new
+/// values within `±amount` of the base values, so repeated sounds don't become monotonous.
+///
+/// # Examples
+///
+/// ``` rust
+/// use rand::rngs::SmallRng;
+/// use rand::SeedableRng;
+/// use sfxr::{Param, Sample, VariedSample};
+/// let mut shot = VariedSample::new(Sample::laser(None));
+/// shot.jitter(Param::EnvDecay, 0.05);
+/// shot.pitch_jitter_semitones = 1.0;
+///
+/// let rng = &mut SmallRng::seed_from_u64(0);
+/// let mut generator = shot.spawn(rng);
+/// let mut buffer = [0.0; 1_024];
+/// generator.generate(&mut buffer);
+/// ```
+#[derive(Clone, Debug)]
+pub struct VariedSample {
+    /// Sample the variations are based on
+    pub sample: Sample,
+    /// Maximum deviation from the base value for each varied field
+    pub jitter: Vec<(Param, f64)>,
+    /// Maximum pitch deviation in semitones
+    pub pitch_jitter_semitones: f32,
+    /// Maximum gain deviation from `1.0`
+    pub gain_jitter: f32,
+}
+
+impl VariedSample {
+    /// Constructs a new VariedSample based on `sample` without any variation
+    pub fn new(sample: Sample) -> VariedSample {
+        VariedSample {
+            sample,
+            jitter: Vec::new(),
+            pitch_jitter_semitones: 0.0,
+            gain_jitter: 0.0,
+        }
+    }
+    /// Varies `param` by up to `±amount`, replacing any previous jitter for it
+    pub fn jitter(&mut self, param: Param, amount: f64) {
+        self.jitter.retain(|&(p, _)| p != param);
+        self.jitter.push((param, amount));
+    }
+    /// Returns a new variation of the base Sample. Varied fields stay within their valid ranges.
+    pub fn vary<R: Rng + ?Sized>(&self, rng: &mut R) -> Sample {
+        let mut s = self.sample.clone();
+        for &(param, amount) in self.jitter.iter() {
+            let (min, max) = param.range();
+            let value = s.param(param) + amount * rand_f64(rng, -1.0, 1.0);
+            s.set_param(param, value.clamp(min, max));
+        }
+        s
+    }
+    /// Returns a Generator playing a new variation
+    pub fn spawn<R: Rng + ?Sized>(&self, rng: &mut R) -> Generator {
+        self.spawn_with_config(rng, GeneratorConfig::new())
+    }
+    /// Returns a Generator playing a new variation, rendered according to `config`
+    pub fn spawn_with_config<R: Rng + ?Sized>(
+        &self,
+        rng: &mut R,
+        config: GeneratorConfig,
+    ) -> Generator {
+        let mut options = GeneratorOptions::new();
+        options.pitch_offset_semitones = self.pitch_jitter_semitones * rand_f32(rng, -1.0, 1.0);
+        options.gain = (1.0 + self.gain_jitter * rand_f32(rng, -1.0, 1.0)).max(0.0);
+        Generator::new_with_config(self.vary(rng), options, config)
+    }
+}
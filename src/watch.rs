@@ -0,0 +1,159 @@
+//! Reloading sound effects from a directory while a game is running
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::{Error, Sample, SoundBank};
+
+/// Handle to a Sample of a BankWatcher, which sees the latest version of its file
+///
+/// Clones share the same Sample, so a handle can be kept by each sound emitter, or sent to
+/// another thread.
+#[derive(Clone, Debug)]
+pub struct LiveSample {
+    sample: Arc<Mutex<Sample>>,
+}
+
+impl LiveSample {
+    /// Returns the current version of the Sample
+    pub fn get(&self) -> Sample {
+        self.sample
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+    fn set(&self, sample: Sample) {
+        *self.sample.lock().unwrap_or_else(|e| e.into_inner()) = sample;
+    }
+}
+
+/// A SoundBank kept up to date with the `.sfs` and JSON files of a directory, created by
+/// `SoundBank::watch`
+///
+/// Each file is a Sample named by its file name without the extension. JSON files hold a
+/// serialized Sample, like the ones saved by `sfxr-tui`. `poll` reloads files changed since
+/// the last poll and adds new ones. Sounds whose file is deleted are kept.
+pub struct BankWatcher {
+    dir: PathBuf,
+    bank: SoundBank,
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    live: Vec<(String, LiveSample)>,
+}
+
+impl SoundBank {
+    /// Loads the `.sfs` and `.json` files in `dir` and watches them for changes, so sounds can
+    /// be tweaked in an editor without restarting the game. Fails if the directory can't be
+    /// read, or if any of the files fails to load.
+    ///
+    /// # Examples
+    ///
+    /// ``` rust no_run
+    /// use sfxr::{GeneratorPool, SoundBank};
+    /// let mut sounds = SoundBank::watch("assets/sounds").unwrap();
+    /// let coin = sounds.live("coin").unwrap();
+    /// let mut pool = GeneratorPool::new(8);
+    /// loop {
+    ///     // Once in a while during development
+    ///     for (name, result) in sounds.poll() {
+    ///         if let Err(e) = result {
+    ///             eprintln!("{}: {}", name, e);
+    ///         }
+    ///     }
+    ///     // When the coin is picked up, play its latest version
+    ///     pool.spawn(coin.get());
+    ///     # break;
+    /// }
+    /// ```
+    pub fn watch<P: AsRef<Path>>(dir: P) -> Result<BankWatcher, Error> {
+        let mut watcher = BankWatcher {
+            dir: dir.as_ref().to_path_buf(),
+            bank: SoundBank::new(),
+            modified: Vec::new(),
+            live: Vec::new(),
+        };
+        for path in watcher.files()? {
+            let modified = modified(&path);
+            let sample = Sample::try_from(path.as_path())?;
+            watcher.bank.insert(&name(&path), sample);
+            watcher.modified.push((path, modified));
+        }
+        Ok(watcher)
+    }
+}
+
+impl BankWatcher {
+    /// Returns the latest versions of the sounds
+    pub fn bank(&self) -> &SoundBank {
+        &self.bank
+    }
+    /// Returns a LiveSample following the sound `name`, or `None` if there is no such sound
+    pub fn live(&mut self, name: &str) -> Option<LiveSample> {
+        if let Some((_, live)) = self.live.iter().find(|(n, _)| n == name) {
+            return Some(live.clone());
+        }
+        let live = LiveSample {
+            sample: Arc::new(Mutex::new(self.bank.get(name)?.clone())),
+        };
+        self.live.push((name.to_string(), live.clone()));
+        Some(live)
+    }
+    /// Reloads the files changed since the last poll and loads new files. Returns the name of
+    /// each file loaded with the outcome.
+    ///
+    /// A file that fails to load, for example because it was read while being saved, keeps
+    /// its previous version until it changes again.
+    pub fn poll(&mut self) -> Vec<(String, Result<(), Error>)> {
+        let files = match self.files() {
+            Ok(files) => files,
+            Err(e) => return vec![(self.dir.display().to_string(), Err(e))],
+        };
+
+        let mut reloaded = Vec::new();
+        for path in files {
+            let modified = modified(&path);
+            match self.modified.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, last)) if *last == modified => continue,
+                Some((_, last)) => *last = modified,
+                None => self.modified.push((path.clone(), modified)),
+            }
+
+            let name = name(&path);
+            let result = Sample::try_from(path.as_path()).map(|sample| {
+                self.bank.insert(&name, sample.clone());
+                if let Some((_, live)) = self.live.iter().find(|(n, _)| *n == name) {
+                    live.set(sample);
+                }
+            });
+            reloaded.push((name, result));
+        }
+        reloaded
+    }
+    /// Returns the paths of the sound files in the directory
+    fn files(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            if path.is_file() && matches!(extension, Some("sfs") | Some("json")) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Returns the name of the sound in the file at `path`
+fn name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Returns the modification time of the file at `path`, or `None` if it isn't available
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
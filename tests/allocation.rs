@@ -0,0 +1,79 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use sfxr::{Generator, GeneratorOptions, Sample, WaveType};
+
+/// Counts heap allocations so the core path can be checked to never allocate
+struct CountingAllocator;
+
+thread_local! {
+    // Per thread, so allocations of the test harness threads aren't counted
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn core_path_does_not_allocate() {
+    let mut sample = Sample::new();
+    sample.wave_type = WaveType::Noise;
+    sample.repeat_speed = 0.6;
+    sample.arp_speed = 0.5;
+    sample.arp_mod = 0.3;
+    sample.pha_offset = 0.2;
+    sample.vib_strength = 0.4;
+    let mut options = GeneratorOptions::new();
+    options.speed = 1.3;
+    options.pitch_offset_semitones = -2.0;
+    let mut buffer = [0.0; 256];
+
+    let before = ALLOCATIONS.with(Cell::get);
+
+    let mut generator = Generator::new_with(sample, options);
+    generator.prewarm();
+    for _ in 0..100 {
+        generator.generate(&mut buffer);
+    }
+    let state = generator.state();
+    generator.pause();
+    generator.generate(&mut buffer);
+    generator.resume();
+    generator.restore(state);
+    generator.reset();
+    generator.generate(&mut buffer);
+
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn long_phaser_does_not_allocate_with_heapless() {
+    let mut sample = Sample::new();
+    sample.pha_offset = 0.8;
+    sample.pha_ramp = 0.1;
+    sample.pha_length = 4_000;
+    let mut buffer = [0.0; 256];
+
+    let before = ALLOCATIONS.with(Cell::get);
+
+    let mut generator = Generator::new(sample);
+    for _ in 0..100 {
+        generator.generate(&mut buffer);
+    }
+    let state = generator.state();
+    generator.restore(state);
+    generator.generate(&mut buffer);
+
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+}
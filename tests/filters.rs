@@ -0,0 +1,50 @@
+use sfxr::{Generator, GeneratorConfig, GeneratorOptions, Sample, WaveType};
+
+/// A sound whose resonant low pass and phaser ring on after the envelope ends
+fn ringing() -> Sample {
+    let mut sample = Sample::new();
+    sample.wave_type = WaveType::Square;
+    sample.env_decay = 0.1;
+    sample.lpf_freq = 0.2;
+    sample.lpf_resonance = 0.9;
+    sample.hpf_freq = 0.1;
+    sample.pha_offset = 0.3;
+    sample
+}
+
+#[test]
+fn decaying_filters_do_not_produce_denormals() {
+    let mut generator = Generator::new(ringing());
+    let mut buffer = vec![0.0; 10 * 44_100];
+    generator.generate(&mut buffer);
+
+    assert!(buffer.iter().all(|v| *v == 0.0 || v.is_normal()));
+    assert_eq!(buffer.last(), Some(&0.0));
+}
+
+#[test]
+fn nan_does_not_stay_in_filter_state() {
+    let mut generator = Generator::new(ringing());
+    // Fields are set directly, skipping the validation of `sample_mut`
+    generator.sample.hpf_freq = f32::NAN;
+    generator.reset();
+    let mut buffer = [0.0; 1_024];
+    generator.generate(&mut buffer);
+
+    generator.sample.hpf_freq = 0.1;
+    generator.reset();
+    generator.generate(&mut buffer);
+    assert!(buffer.iter().all(|v| v.is_finite()));
+}
+
+#[test]
+fn legacy_compat_does_not_flush_filter_state() {
+    let options = GeneratorOptions::new();
+    let mut generator =
+        Generator::new_with_config(ringing(), options, GeneratorConfig::legacy_compat());
+    let mut buffer = vec![0.0; 10 * 44_100];
+    generator.generate(&mut buffer);
+
+    // Original sfxr lets the state decay into the denormal range
+    assert!(buffer.iter().any(|v| *v != 0.0 && !v.is_normal()));
+}